@@ -1,6 +1,4 @@
-use tracing::{
-    debug, error, info, instrument, level_filters::LevelFilter, span, trace, warn, Level,
-};
+use tracing::{debug, error, info, level_filters::LevelFilter, span, trace, warn, Level};
 use tracing_subscriber::{layer::SubscriberExt, registry::Registry, Layer};
 use tracing_tree::HierarchicalLayer;
 
@@ -98,14 +96,3 @@ fn main() {
     log::error!("this is a log message");
     info!("exit");
 }
-
-#[instrument]
-fn call_a(name: &str) {
-    info!(name, "got a name");
-    call_b(name)
-}
-
-#[instrument]
-fn call_b(name: &str) {
-    info!(name, "got a name");
-}
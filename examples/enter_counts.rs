@@ -0,0 +1,29 @@
+use tracing::{debug, info, span, Level};
+use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+use tracing_tree::HierarchicalLayer;
+
+fn main() {
+    let layer = HierarchicalLayer::default()
+        .with_writer(std::io::stdout)
+        .with_indent_lines(true)
+        .with_indent_amount(2)
+        .with_span_retrace(true)
+        .with_enter_counts(true);
+
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let app_span = span!(Level::TRACE, "hierarchical-example", version = %0.1);
+    let _e = app_span.enter();
+
+    let worker_a = span!(Level::TRACE, "worker", name = "a");
+    let worker_b = span!(Level::TRACE, "worker", name = "b");
+
+    for round in 0..3 {
+        worker_a.in_scope(|| debug!(round, "working"));
+        worker_b.in_scope(|| debug!(round, "working"));
+    }
+    drop((worker_a, worker_b));
+
+    info!("exit");
+}
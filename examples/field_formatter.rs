@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use tracing::{debug, info, span, Level};
+use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+use tracing_tree::HierarchicalLayer;
+
+fn main() {
+    let layer = HierarchicalLayer::default()
+        .with_writer(std::io::stdout)
+        .with_indent_lines(true)
+        .with_indent_amount(2)
+        .with_field_formatter(|field, value, out| match field.name() {
+            "commit" => {
+                let hash = format!("{:?}", value);
+                write!(out, "{}", hash.trim_matches('"').get(..8).unwrap_or(&hash)).is_ok()
+            }
+            "duration_ns" => {
+                let nanos = format!("{:?}", value).parse().unwrap_or_default();
+                write!(out, "{:?}", Duration::from_nanos(nanos)).is_ok()
+            }
+            _ => false,
+        });
+
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let build = span!(
+        Level::TRACE,
+        "build",
+        commit = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b"
+    );
+    build.in_scope(|| {
+        debug!(duration_ns = 1_250_000_u64, "compiled");
+        debug!(duration_ns = 3_000_000_000_u64, crate_count = 12, "linked");
+    });
+    drop(build);
+    info!("exit");
+}
@@ -0,0 +1,28 @@
+use tracing::{debug, info, span, Level};
+use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+use tracing_tree::HierarchicalLayer;
+
+fn main() {
+    // Usually this is set by whoever runs the binary.
+    std::env::set_var(
+        "TRACING_TREE",
+        "indent=4,lines,targets,bracketed_fields,unknown",
+    );
+
+    let layer = HierarchicalLayer::from_env().with_writer(std::io::stdout);
+
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let app_span = span!(Level::TRACE, "hierarchical-example", version = %0.1);
+    let _e = app_span.enter();
+
+    let peer1 = span!(Level::TRACE, "conn", peer_addr = "82.9.9.9", port = 42381);
+    peer1.in_scope(|| {
+        debug!("connected");
+        debug!(length = 2, "message received");
+    });
+    drop(peer1);
+
+    info!("exit");
+}
@@ -0,0 +1,25 @@
+use tracing::{debug, info, span, warn, Level};
+use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+use tracing_tree::HierarchicalLayer;
+
+fn main() {
+    let layer = HierarchicalLayer::default()
+        .with_writer(std::io::stdout)
+        .with_markdown(true);
+
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let app_span = span!(Level::TRACE, "hierarchical-example", version = %0.1);
+    let _e = app_span.enter();
+
+    span!(Level::TRACE, "server", host = "localhost", port = 8080).in_scope(|| {
+        info!("starting");
+        span!(Level::TRACE, "conn", peer_addr = "82.9.9.9").in_scope(|| {
+            debug!(bytes = 42, "response sent");
+            warn!(query = "`id` = 1", "slow query");
+        });
+    });
+
+    info!("exit");
+}
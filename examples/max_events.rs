@@ -0,0 +1,30 @@
+use tracing::{debug, info, span, Level};
+use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+use tracing_tree::HierarchicalLayer;
+
+fn main() {
+    let layer = HierarchicalLayer::default()
+        .with_writer(std::io::stdout)
+        .with_indent_lines(true)
+        .with_indent_amount(2)
+        .with_max_events_per_span(3);
+
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let app_span = span!(Level::TRACE, "hierarchical-example", version = %0.1);
+    let _e = app_span.enter();
+
+    span!(Level::TRACE, "hot-loop").in_scope(|| {
+        for i in 0..415 {
+            debug!(i, "iteration");
+        }
+    });
+    span!(Level::TRACE, "short-loop").in_scope(|| {
+        for i in 0..2 {
+            debug!(i, "iteration");
+        }
+    });
+
+    info!("exit");
+}
@@ -0,0 +1,32 @@
+use tracing::{debug, info, span, warn, Level};
+use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+use tracing_tree::HierarchicalLayer;
+
+fn main() {
+    for (name, layer) in [
+        ("pretty", HierarchicalLayer::pretty()),
+        ("compact", HierarchicalLayer::compact()),
+        ("ci", HierarchicalLayer::ci()),
+    ] {
+        println!("-> {name}");
+        let layer = layer.with_ansi(false).with_writer(std::io::stdout);
+        let subscriber = Registry::default().with(layer);
+        tracing::subscriber::with_default(subscriber, workload);
+    }
+}
+
+fn workload() {
+    let app_span = span!(Level::TRACE, "hierarchical-example", version = %0.1);
+    let _e = app_span.enter();
+
+    let server_span = span!(Level::TRACE, "server", host = "localhost", port = 8080);
+    let _e2 = server_span.enter();
+    info!("starting");
+    let peer1 = span!(Level::TRACE, "conn", peer_addr = "82.9.9.9", port = 42381);
+    peer1.in_scope(|| {
+        debug!("connected");
+        warn!(algo = "xor", "weak encryption requested");
+    });
+    drop(peer1);
+    info!("exit");
+}
@@ -0,0 +1,32 @@
+use tracing::{debug, error, info, span, trace, Level};
+use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+use tracing_tree::HierarchicalLayer;
+
+fn main() {
+    let layer = HierarchicalLayer::default()
+        .with_writer(std::io::stdout)
+        .with_indent_lines(true)
+        .with_indent_amount(2)
+        .with_quiet_until_error(Level::ERROR);
+
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let app_span = span!(Level::TRACE, "hierarchical-example", version = %0.1);
+    let _e = app_span.enter();
+
+    span!(Level::TRACE, "request", id = 1).in_scope(|| {
+        debug!("parsing headers");
+        trace!(bytes = 512, "read body");
+        info!("handled");
+    });
+
+    span!(Level::TRACE, "request", id = 2).in_scope(|| {
+        debug!("parsing headers");
+        trace!(bytes = 17, "read body");
+        error!("malformed body");
+        debug!("closing connection");
+    });
+
+    info!("exit");
+}
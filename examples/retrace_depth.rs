@@ -0,0 +1,38 @@
+use tracing::{info, span, Level};
+use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+use tracing_tree::HierarchicalLayer;
+
+fn main() {
+    let layer = HierarchicalLayer::default()
+        .with_writer(std::io::stdout)
+        .with_indent_lines(true)
+        .with_indent_amount(2)
+        .with_span_retrace(true)
+        .with_retrace_depth(2);
+
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let app_span = span!(Level::TRACE, "hierarchical-example", version = %0.1);
+    let _e = app_span.enter();
+
+    let (conn_a, req_a) = open_request("82.9.9.9");
+    let (conn_b, req_b) = open_request("8.8.8.8");
+
+    req_a.in_scope(|| info!("reading headers"));
+    req_b.in_scope(|| info!("reading headers"));
+    req_a.in_scope(|| info!("writing response"));
+    req_b.in_scope(|| info!("writing response"));
+
+    drop((req_a, conn_a, req_b, conn_b));
+    info!("exit");
+}
+
+fn open_request(peer_addr: &str) -> (span::Span, span::Span) {
+    let conn = span!(Level::TRACE, "conn", peer_addr);
+    let req = conn.in_scope(|| {
+        let handler = span!(Level::TRACE, "handler");
+        handler.in_scope(|| span!(Level::TRACE, "request", method = "GET"))
+    });
+    (conn, req)
+}
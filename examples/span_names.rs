@@ -0,0 +1,33 @@
+use std::borrow::Cow;
+
+use tracing::{debug, info, instrument};
+use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+use tracing_tree::HierarchicalLayer;
+
+fn main() {
+    let layer = HierarchicalLayer::default()
+        .with_writer(std::io::stdout)
+        .with_indent_lines(true)
+        .with_indent_amount(2)
+        .with_span_name_fn(|metadata| match metadata.name().rsplit_once("::") {
+            Some((_, short)) => Cow::Owned(format!("…::{short}")),
+            None => Cow::Borrowed(metadata.name()),
+        });
+
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    handle_request(7);
+    info!("exit");
+}
+
+#[instrument(name = "server::handlers::users::get_user")]
+fn handle_request(id: u32) {
+    debug!("loading user");
+    load_user(id);
+}
+
+#[instrument]
+fn load_user(id: u32) {
+    debug!("querying database");
+}
@@ -0,0 +1,34 @@
+use tracing::{debug, info, span, Level};
+use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+use tracing_tree::HierarchicalLayer;
+
+fn main() {
+    let layer = HierarchicalLayer::default()
+        .with_writer(std::io::stdout)
+        .with_indent_lines(true)
+        .with_indent_amount(2)
+        .with_span_tags(true);
+
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let app_span = span!(Level::TRACE, "hierarchical-example", version = %0.1);
+    let _e = app_span.enter();
+
+    let peer1 = span!(Level::TRACE, "conn", peer_addr = "82.9.9.9", port = 42381);
+    let peer2 = span!(Level::TRACE, "conn", peer_addr = "8.8.8.8", port = 18230);
+
+    peer1.in_scope(|| debug!("connected"));
+    peer2.in_scope(|| debug!("connected"));
+    peer1.in_scope(|| debug!(length = 2, "message received"));
+    drop(peer1);
+
+    // The tag of the closed connection is reused
+    let peer3 = span!(Level::TRACE, "conn", peer_addr = "1.1.1.1", port = 50000);
+    peer3.in_scope(|| debug!("connected"));
+    peer2.in_scope(|| debug!("disconnected"));
+    drop(peer2);
+    drop(peer3);
+
+    info!("exit");
+}
@@ -0,0 +1,34 @@
+use tracing::{info, span, Level};
+use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+use tracing_tree::HierarchicalLayer;
+
+fn main() {
+    let layer = HierarchicalLayer::default()
+        .with_writer(std::io::stdout)
+        .with_indent_lines(true)
+        .with_indent_amount(2)
+        .with_span_retrace(true)
+        .with_thread_migrations(true);
+
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let app_span = span!(Level::TRACE, "hierarchical-example", version = %0.1);
+    let task = app_span.in_scope(|| span!(Level::TRACE, "task", id = 1));
+
+    task.in_scope(|| info!("polled on main"));
+
+    std::thread::Builder::new()
+        .name("worker".into())
+        .spawn(move || {
+            task.in_scope(|| {
+                info!("polled on worker");
+                info!("still on worker");
+            });
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+
+    app_span.in_scope(|| info!("exit"));
+}
@@ -0,0 +1,414 @@
+use std::{borrow::Cow, fmt, io, mem};
+
+use tracing_core::{field::Field, Level, Metadata};
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::{
+    flame::FoldedStacks, format::Config, histogram::Histograms, metrics::MetricsEvent,
+    section::SectionMarker, stats::SpanStats, time::FormatTime, writer::LevelWriters, AnsiMode,
+    Columns, Divider, Glyphs, HierarchicalLayer, MessagePosition, Newlines, Separator,
+    SeverityPrefix, Theme, TimeDisplay, WriteErrorPolicy,
+};
+
+/// A builder for [`HierarchicalLayer`] that checks the combination of options before building
+/// the layer.
+///
+/// The `with_*` methods of [`HierarchicalLayer`] happily accept combinations of options that
+/// are known to render broken output (or even panic). The builder offers the same methods, but
+/// [`build`](Self::build) rejects such combinations and
+/// [`build_normalized`](Self::build_normalized) fixes them up instead.
+///
+/// ```
+/// use tracing_tree::HierarchicalLayer;
+///
+/// let layer = HierarchicalLayer::builder()
+///     .with_indent_lines(true)
+///     .with_verbose_entry(true)
+///     .with_verbose_exit(true)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct HierarchicalLayerBuilder<W = fn() -> io::Stderr, FT = ()>
+where
+    W: for<'writer> MakeWriter<'writer> + 'static,
+    FT: FormatTime,
+{
+    layer: HierarchicalLayer<W, FT>,
+}
+
+/// A combination of options rejected by [`HierarchicalLayerBuilder::build`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// `indent_lines` requires an `indent_amount` of at least one.
+    ZeroIndentWithLines,
+    /// A `wraparound` of zero would wrap every single line.
+    ZeroWraparound,
+    /// `verbose_entry` without `verbose_exit` leaves the reprinted parent spans unclosed.
+    VerboseEntryWithoutExit,
+    /// `retrace_depth` has no effect without `span_retrace` or `deferred_spans`.
+    RetraceDepthWithoutRetrace,
+    /// A `retrace_depth` of zero would never show which span an event belongs to.
+    ZeroRetraceDepth,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfigError::ZeroIndentWithLines => {
+                "`indent_lines` requires a non-zero `indent_amount`"
+            }
+            ConfigError::ZeroWraparound => "`wraparound` must not be zero",
+            ConfigError::VerboseEntryWithoutExit => {
+                "`verbose_entry` requires `verbose_exit` to render matching guides"
+            }
+            ConfigError::RetraceDepthWithoutRetrace => {
+                "`retrace_depth` requires `span_retrace` or `deferred_spans`"
+            }
+            ConfigError::ZeroRetraceDepth => "`retrace_depth` must not be zero",
+        })
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Returns the first problematic combination of options.
+    pub(crate) fn validate(&self) -> Result<(), ConfigError> {
+        if self.indent_lines && self.indent_amount == 0 {
+            return Err(ConfigError::ZeroIndentWithLines);
+        }
+        if self.wraparound == 0 {
+            return Err(ConfigError::ZeroWraparound);
+        }
+        if self.verbose_entry && !self.verbose_exit {
+            return Err(ConfigError::VerboseEntryWithoutExit);
+        }
+        if self.retrace_depth == 0 {
+            return Err(ConfigError::ZeroRetraceDepth);
+        }
+        if self.retrace_depth != usize::MAX && !(self.span_retrace || self.deferred_spans) {
+            return Err(ConfigError::RetraceDepthWithoutRetrace);
+        }
+        Ok(())
+    }
+
+    /// Adjusts the options so that [`Config::validate`] accepts them.
+    pub(crate) fn normalize(mut self) -> Self {
+        while let Err(err) = self.validate() {
+            match err {
+                ConfigError::ZeroIndentWithLines => self.indent_amount = 1,
+                ConfigError::ZeroWraparound => self.wraparound = usize::MAX,
+                ConfigError::VerboseEntryWithoutExit => self.verbose_exit = true,
+                ConfigError::RetraceDepthWithoutRetrace => self.retrace_depth = usize::MAX,
+                ConfigError::ZeroRetraceDepth => self.retrace_depth = 1,
+            }
+        }
+        self
+    }
+}
+
+impl HierarchicalLayer<fn() -> io::Stderr> {
+    /// Starts a [`HierarchicalLayerBuilder`] with the default options.
+    pub fn builder() -> HierarchicalLayerBuilder {
+        HierarchicalLayerBuilder::from(Self::default())
+    }
+}
+
+impl<W, FT> From<HierarchicalLayer<W, FT>> for HierarchicalLayerBuilder<W, FT>
+where
+    W: for<'writer> MakeWriter<'writer> + 'static,
+    FT: FormatTime,
+{
+    fn from(layer: HierarchicalLayer<W, FT>) -> Self {
+        Self { layer }
+    }
+}
+
+macro_rules! forward {
+    ($($(#[$attr:meta])* $name:ident($arg:ident: $ty:ty);)*) => {
+        $(
+            $(#[$attr])*
+            pub fn $name(self, $arg: $ty) -> Self {
+                Self {
+                    layer: self.layer.$name($arg),
+                }
+            }
+        )*
+    };
+}
+
+impl<W, FT> HierarchicalLayerBuilder<W, FT>
+where
+    W: for<'writer> MakeWriter<'writer> + 'static,
+    FT: FormatTime,
+{
+    /// Builds the layer, or returns the first problematic combination of options.
+    pub fn build(self) -> Result<HierarchicalLayer<W, FT>, ConfigError> {
+        self.layer.config.validate()?;
+        Ok(self.layer)
+    }
+
+    /// Builds the layer, adjusting problematic combinations of options instead of rejecting
+    /// them. For example `verbose_entry` also enables `verbose_exit`.
+    pub fn build_normalized(self) -> HierarchicalLayer<W, FT> {
+        let mut layer = self.layer;
+        layer.config = mem::take(&mut layer.config).normalize();
+        layer
+    }
+
+    /// See [`HierarchicalLayer::with_writer`].
+    pub fn with_writer<W2>(self, make_writer: W2) -> HierarchicalLayerBuilder<W2, FT>
+    where
+        W2: for<'writer> MakeWriter<'writer>,
+    {
+        HierarchicalLayerBuilder {
+            layer: self.layer.with_writer(make_writer),
+        }
+    }
+
+    /// See [`HierarchicalLayer::with_level_writers`].
+    pub fn with_level_writers<A, B>(
+        self,
+        severe: A,
+        rest: B,
+    ) -> HierarchicalLayerBuilder<LevelWriters<A, B>, FT>
+    where
+        A: for<'writer> MakeWriter<'writer>,
+        B: for<'writer> MakeWriter<'writer>,
+    {
+        HierarchicalLayerBuilder {
+            layer: self.layer.with_level_writers(severe, rest),
+        }
+    }
+
+    /// See [`HierarchicalLayer::with_timer`].
+    pub fn with_timer<FT2: FormatTime>(self, timer: FT2) -> HierarchicalLayerBuilder<W, FT2> {
+        HierarchicalLayerBuilder {
+            layer: self.layer.with_timer(timer),
+        }
+    }
+
+    /// See [`HierarchicalLayer::with_span_name_fn`].
+    pub fn with_span_name_fn<F>(self, span_name: F) -> Self
+    where
+        F: Fn(&'static Metadata<'static>) -> Cow<'static, str> + Send + Sync + 'static,
+    {
+        Self {
+            layer: self.layer.with_span_name_fn(span_name),
+        }
+    }
+
+    /// See [`HierarchicalLayer::with_field_formatter`].
+    pub fn with_field_formatter<F>(self, field_formatter: F) -> Self
+    where
+        F: Fn(&Field, &dyn fmt::Debug, &mut dyn fmt::Write) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            layer: self.layer.with_field_formatter(field_formatter),
+        }
+    }
+
+    /// See [`HierarchicalLayer::with_section_marker`].
+    pub fn with_section_marker<M>(self, marker: M) -> Self
+    where
+        M: SectionMarker + 'static,
+    {
+        Self {
+            layer: self.layer.with_section_marker(marker),
+        }
+    }
+
+    /// See [`HierarchicalLayer::with_metrics_hook`].
+    pub fn with_metrics_hook<F>(self, hook: F) -> Self
+    where
+        F: Fn(MetricsEvent<'_>) + Send + Sync + 'static,
+    {
+        Self {
+            layer: self.layer.with_metrics_hook(hook),
+        }
+    }
+
+    /// See [`HierarchicalLayer::with_structured_mirror`].
+    pub fn with_structured_mirror<M>(self, make_writer: M) -> Self
+    where
+        M: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        Self {
+            layer: self.layer.with_structured_mirror(make_writer),
+        }
+    }
+
+    /// See [`HierarchicalLayer::with_csv_export`].
+    #[cfg(feature = "csv")]
+    pub fn with_csv_export<M>(self, make_writer: M) -> Self
+    where
+        M: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        Self {
+            layer: self.layer.with_csv_export(make_writer),
+        }
+    }
+
+    /// See [`HierarchicalLayer::with_target_filter`].
+    pub fn with_target_filter<I, T>(self, filter: I) -> Self
+    where
+        I: IntoIterator<Item = (T, Level)>,
+        T: Into<String>,
+    {
+        Self {
+            layer: self.layer.with_target_filter(filter),
+        }
+    }
+
+    /// See [`HierarchicalLayer::with_event_divider`].
+    pub fn with_event_divider(self, level: Level, divider: Divider) -> Self {
+        Self {
+            layer: self.layer.with_event_divider(level, divider),
+        }
+    }
+
+    forward! {
+        /// See [`HierarchicalLayer::with_config`].
+        with_config(config: Config);
+        /// See [`HierarchicalLayer::with_options`].
+        with_options(options: &str);
+        /// See [`HierarchicalLayer::with_ansi`].
+        with_ansi(ansi: bool);
+        /// See [`HierarchicalLayer::with_ansi_mode`].
+        with_ansi_mode(ansi_mode: AnsiMode);
+        /// See [`HierarchicalLayer::with_indent_amount`].
+        with_indent_amount(indent_amount: usize);
+        /// See [`HierarchicalLayer::with_indent_lines`].
+        with_indent_lines(indent_lines: bool);
+        /// See [`HierarchicalLayer::with_targets`].
+        with_targets(targets: bool);
+        /// See [`HierarchicalLayer::with_thread_ids`].
+        with_thread_ids(thread_ids: bool);
+        /// See [`HierarchicalLayer::with_thread_names`].
+        with_thread_names(thread_names: bool);
+        /// See [`HierarchicalLayer::with_continuation_thread_prefix`].
+        with_continuation_thread_prefix(continuation_thread_prefix: bool);
+        /// See [`HierarchicalLayer::with_wraparound`].
+        with_wraparound(wraparound: usize);
+        /// See [`HierarchicalLayer::with_width`].
+        with_width(width: usize);
+        /// See [`HierarchicalLayer::with_verbose_entry`].
+        with_verbose_entry(verbose_entry: bool);
+        /// See [`HierarchicalLayer::with_verbose_exit`].
+        with_verbose_exit(verbose_exit: bool);
+        /// See [`HierarchicalLayer::with_span_retrace`].
+        with_span_retrace(enabled: bool);
+        /// See [`HierarchicalLayer::with_retrace_depth`].
+        with_retrace_depth(depth: usize);
+        /// See [`HierarchicalLayer::with_max_events_per_span`].
+        with_max_events_per_span(max: usize);
+        /// See [`HierarchicalLayer::with_quiet_until_error`].
+        with_quiet_until_error(trigger: Level);
+        /// See [`HierarchicalLayer::with_summary_report`].
+        with_summary_report(enabled: bool);
+        /// See [`HierarchicalLayer::with_span_stats`].
+        with_span_stats(stats: SpanStats);
+        /// See [`HierarchicalLayer::with_latency_histograms`].
+        with_latency_histograms(histograms: Histograms);
+        /// See [`HierarchicalLayer::with_folded_stacks`].
+        with_folded_stacks(stacks: FoldedStacks);
+        /// See [`HierarchicalLayer::with_chrome_trace`].
+        #[cfg(feature = "chrome")]
+        with_chrome_trace(trace: crate::chrome::ChromeTrace);
+        /// See [`HierarchicalLayer::with_sqlite_sink`].
+        #[cfg(feature = "sqlite")]
+        with_sqlite_sink(sink: crate::sqlite::SqliteSink);
+        /// See [`HierarchicalLayer::with_perfetto_trace`].
+        #[cfg(feature = "perfetto")]
+        with_perfetto_trace(trace: crate::perfetto::PerfettoTrace);
+        /// See [`HierarchicalLayer::with_deferred_spans`].
+        with_deferred_spans(enabled: bool);
+        /// See [`HierarchicalLayer::with_span_modes`].
+        with_span_modes(enabled: bool);
+        /// See [`HierarchicalLayer::with_bracketed_fields`].
+        with_bracketed_fields(bracketed_fields: bool);
+        /// See [`HierarchicalLayer::with_bracketed_event_fields`].
+        with_bracketed_event_fields(bracketed_event_fields: bool);
+        /// See [`HierarchicalLayer::with_thread_migrations`].
+        with_thread_migrations(thread_migrations: bool);
+        /// See [`HierarchicalLayer::with_span_tags`].
+        with_span_tags(span_tags: bool);
+        /// See [`HierarchicalLayer::with_enter_counts`].
+        with_enter_counts(enter_counts: bool);
+        /// See [`HierarchicalLayer::with_short_levels`].
+        with_short_levels(short_levels: bool);
+        /// See [`HierarchicalLayer::with_level_from`].
+        with_level_from(level: Level);
+        /// See [`HierarchicalLayer::with_span_levels`].
+        with_span_levels(span_levels: bool);
+        /// See [`HierarchicalLayer::with_event_deltas`].
+        with_event_deltas(event_deltas: bool);
+        /// See [`HierarchicalLayer::with_elapsed_from_root`].
+        with_elapsed_from_root(elapsed_from_root: bool);
+        /// See [`HierarchicalLayer::with_time_display`].
+        with_time_display(time_display: TimeDisplay);
+        /// See [`HierarchicalLayer::with_wall_clock_width`].
+        with_wall_clock_width(width: usize);
+        /// See [`HierarchicalLayer::with_elapsed_width`].
+        with_elapsed_width(width: usize);
+        /// See [`HierarchicalLayer::with_severity_prefix`].
+        with_severity_prefix(prefix: SeverityPrefix);
+        /// See [`HierarchicalLayer::with_write_error_policy`].
+        with_write_error_policy(policy: WriteErrorPolicy);
+        /// See [`HierarchicalLayer::with_sanitize_fields`].
+        with_sanitize_fields(sanitize_fields: bool);
+        /// See [`HierarchicalLayer::with_field_newlines`].
+        with_field_newlines(newlines: Newlines);
+        /// See [`HierarchicalLayer::with_sanitize_ansi`].
+        with_sanitize_ansi(sanitize_ansi: bool);
+        /// See [`HierarchicalLayer::with_markdown`].
+        with_markdown(markdown: bool);
+        /// See [`HierarchicalLayer::with_otel_ids`].
+        #[cfg(feature = "opentelemetry")]
+        with_otel_ids(otel_ids: bool);
+        /// See [`HierarchicalLayer::with_field_budget_bytes`].
+        with_field_budget_bytes(budget: usize);
+        /// See [`HierarchicalLayer::with_github_groups`].
+        with_github_groups(github_groups: bool);
+        /// See [`HierarchicalLayer::with_critical_path`].
+        with_critical_path(critical_path: bool);
+        /// See [`HierarchicalLayer::with_tree_stats`].
+        with_tree_stats(tree_stats: bool);
+        /// See [`HierarchicalLayer::with_deterministic_output`].
+        with_deterministic_output(deterministic_output: bool);
+        /// See [`HierarchicalLayer::with_lenient_spans`].
+        with_lenient_spans(lenient_spans: bool);
+        /// See [`HierarchicalLayer::with_pair_markers`].
+        with_pair_markers(pair_markers: bool);
+        /// See [`HierarchicalLayer::with_columns`].
+        with_columns(columns: Columns);
+        /// See [`HierarchicalLayer::with_switch_markers`].
+        with_switch_markers(switch_markers: bool);
+        /// See [`HierarchicalLayer::with_retrace_elapsed`].
+        with_retrace_elapsed(retrace_elapsed: bool);
+        /// See [`HierarchicalLayer::with_span_time_range`].
+        with_span_time_range(span_time_range: bool);
+        /// See [`HierarchicalLayer::with_error_backtraces`].
+        with_error_backtraces(error_backtraces: bool);
+        /// See [`HierarchicalLayer::with_force_backtraces`].
+        with_force_backtraces(force_backtraces: bool);
+        /// See [`HierarchicalLayer::with_header_every`].
+        with_header_every(n: usize);
+        /// See [`HierarchicalLayer::with_root_separator`].
+        with_root_separator(separator: Separator);
+        /// See [`HierarchicalLayer::with_message_position`].
+        with_message_position(position: MessagePosition);
+        /// See [`HierarchicalLayer::with_max_level`].
+        with_max_level(level: Level);
+        /// See [`HierarchicalLayer::with_glyphs`].
+        with_glyphs(glyphs: Glyphs);
+        /// See [`HierarchicalLayer::with_guide_gradient`].
+        with_guide_gradient(guide_gradient: bool);
+        /// See [`HierarchicalLayer::with_theme`].
+        with_theme(theme: Theme);
+        /// See [`HierarchicalLayer::with_basic_colors`].
+        with_basic_colors(basic_colors: bool);
+    }
+}
@@ -0,0 +1,235 @@
+//! A trace of spans and events in the Chrome trace event format, written alongside the tree.
+//!
+//! The trace can be opened in `chrome://tracing` or [Perfetto](https://ui.perfetto.dev). Every
+//! closed span becomes a complete (`X`) event and every event an instant (`i`) event.
+//!
+//! ```no_run
+//! use std::{fs::File, io};
+//! use tracing_subscriber::{layer::SubscriberExt, Registry};
+//! use tracing_tree::{chrome::ChromeTrace, HierarchicalLayer};
+//!
+//! let trace = ChromeTrace::new(File::create("trace.json").unwrap());
+//! let layer = HierarchicalLayer::default()
+//!     // Only write the trace, not the tree
+//!     .with_writer(io::sink)
+//!     .with_chrome_trace(trace.clone());
+//! let subscriber = Registry::default().with(layer);
+//! // ... install the subscriber and do some work ...
+//!
+//! trace.flush().unwrap();
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Write as _},
+    io::{self, Write as _},
+    mem,
+    sync::{Arc, Mutex},
+    thread::{self, ThreadId},
+    time::Duration,
+};
+
+use tracing_core::{
+    field::{Field, Visit},
+    Event, Metadata,
+};
+
+use crate::{clock::Instant, fields::Fields, json::string};
+
+/// A handle to a trace written by a [`HierarchicalLayer`].
+///
+/// Clones write to the same trace, so a clone can be handed to
+/// [`HierarchicalLayer::with_chrome_trace`] while the original is kept to flush the trace.
+/// The JSON array is closed once the last clone is dropped.
+///
+/// [`HierarchicalLayer`]: crate::HierarchicalLayer
+/// [`HierarchicalLayer::with_chrome_trace`]: crate::HierarchicalLayer::with_chrome_trace
+#[derive(Clone)]
+pub struct ChromeTrace {
+    inner: Arc<Mutex<Trace>>,
+}
+
+struct Trace {
+    writer: Box<dyn io::Write + Send>,
+    /// The time stamps of the trace are relative to this instant
+    origin: Instant,
+    /// Small numbers for the threads, as `ThreadId` can't be turned into one
+    threads: HashMap<ThreadId, usize>,
+    /// Whether an event has been written, so the next one needs a comma
+    started: bool,
+    buf: String,
+}
+
+impl fmt::Debug for ChromeTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChromeTrace").finish_non_exhaustive()
+    }
+}
+
+impl ChromeTrace {
+    /// Writes a trace to `writer`, e.g. a file. The writer is buffered internally.
+    pub fn new<W: io::Write + Send + 'static>(writer: W) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Trace {
+                writer: Box::new(io::BufWriter::new(writer)),
+                origin: Instant::now(),
+                threads: HashMap::new(),
+                started: false,
+                buf: String::new(),
+            })),
+        }
+    }
+
+    /// Flushes the events written so far to the underlying writer.
+    pub fn flush(&self) -> io::Result<()> {
+        self.lock().writer.flush()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Trace> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Writes a closed span that was opened at `start` on `thread`.
+    pub(crate) fn span(
+        &self,
+        metadata: &Metadata<'_>,
+        fields: &Fields,
+        start: Instant,
+        elapsed: Duration,
+        thread: ThreadId,
+    ) {
+        let mut trace = self.lock();
+        let ts = start - trace.origin;
+        let tid = trace.tid(thread);
+        let mut buf = mem::take(&mut trace.buf);
+        header(&mut buf, metadata.name(), metadata, "X", ts, tid);
+        write!(buf, ",\"dur\":{}", micros(elapsed)).unwrap();
+        buf.push_str(",\"args\":{");
+        for (i, (name, value)) in fields.iter().enumerate() {
+            if i > 0 {
+                buf.push(',');
+            }
+            string(&mut buf, name);
+            buf.push(':');
+            string(&mut buf, value);
+        }
+        buf.push_str("}}");
+        trace.write(buf);
+    }
+
+    /// Writes an event that occurred just now on the current thread.
+    pub(crate) fn event(&self, metadata: &Metadata<'_>, event: &Event<'_>) {
+        let mut args = Args {
+            message: String::new(),
+            args: String::new(),
+        };
+        event.record(&mut args);
+
+        let mut trace = self.lock();
+        let ts = trace.origin.elapsed();
+        let tid = trace.tid(thread::current().id());
+        let mut buf = mem::take(&mut trace.buf);
+        let name = if args.message.is_empty() {
+            metadata.name()
+        } else {
+            &args.message
+        };
+        header(&mut buf, name, metadata, "i", ts, tid);
+        buf.push_str(",\"s\":\"t\",\"args\":{\"level\":");
+        string(&mut buf, metadata.level().as_str());
+        buf.push_str(&args.args);
+        buf.push_str("}}");
+        trace.write(buf);
+    }
+}
+
+impl Trace {
+    fn tid(&mut self, thread: ThreadId) -> usize {
+        let next = self.threads.len() + 1;
+        *self.threads.entry(thread).or_insert(next)
+    }
+
+    fn write(&mut self, mut buf: String) {
+        let separator = if mem::replace(&mut self.started, true) {
+            ",\n"
+        } else {
+            "[\n"
+        };
+        // Failing to write the trace must not take down the application
+        let _ = self
+            .writer
+            .write_all(separator.as_bytes())
+            .and_then(|()| self.writer.write_all(buf.as_bytes()));
+        buf.clear();
+        self.buf = buf;
+    }
+}
+
+impl Drop for Trace {
+    fn drop(&mut self) {
+        let end = if self.started { "\n]\n" } else { "[]\n" };
+        // There is nobody left to report a failure to
+        let _ = self.writer.write_all(end.as_bytes());
+        let _ = self.writer.flush();
+    }
+}
+
+/// Writes the fields every trace event has, leaving the object open.
+fn header(
+    buf: &mut String,
+    name: &str,
+    metadata: &Metadata<'_>,
+    ph: &str,
+    ts: Duration,
+    tid: usize,
+) {
+    buf.push_str("{\"name\":");
+    string(buf, name);
+    buf.push_str(",\"cat\":");
+    string(buf, metadata.target());
+    write!(
+        buf,
+        ",\"ph\":\"{}\",\"ts\":{},\"pid\":{},\"tid\":{}",
+        ph,
+        micros(ts),
+        std::process::id(),
+        tid
+    )
+    .unwrap();
+}
+
+/// Durations are given in microseconds, with fractions allowed.
+fn micros(duration: Duration) -> f64 {
+    duration.as_nanos() as f64 / 1000.0
+}
+
+/// Collects the fields of an event as JSON members, preceded by commas.
+struct Args {
+    message: String,
+    args: String,
+}
+
+impl Visit for Args {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message.push_str(value),
+            // Skip fields that are actually log metadata
+            #[cfg(feature = "tracing-log")]
+            name if name.starts_with("log.") => {}
+            _ => {
+                self.args.push(',');
+                string(&mut self.args, field.name());
+                self.args.push(':');
+                string(&mut self.args, value);
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            write!(self.message, "{:?}", value).unwrap();
+        } else {
+            self.record_str(field, &format!("{:?}", value));
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! The clock used to measure the time spent in spans.
+//!
+//! `std::time::Instant` panics in the browser, so with the `wasm` feature the time is taken
+//! from JavaScript's `Date.now()` there instead.
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown", feature = "wasm")))]
+pub(crate) use std::time::Instant;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown", feature = "wasm"))]
+pub(crate) use self::wasm::Instant;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown", feature = "wasm"))]
+mod wasm {
+    use std::{ops::Sub, time::Duration};
+
+    /// Milliseconds since the unix epoch, as reported by `Date.now()`.
+    #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+    pub(crate) struct Instant(f64);
+
+    impl Instant {
+        pub(crate) fn now() -> Self {
+            Instant(js_sys::Date::now())
+        }
+
+        pub(crate) fn elapsed(&self) -> Duration {
+            Self::now() - *self
+        }
+    }
+
+    impl Sub for Instant {
+        type Output = Duration;
+
+        fn sub(self, earlier: Instant) -> Duration {
+            // The system clock may go backwards
+            Duration::from_secs_f64((self.0 - earlier.0).max(0.0) / 1000.0)
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Rows of comma separated values of the events rendered in the tree, for spreadsheets.
+
+use std::{
+    fmt::{self, Write as _},
+    io::Write as _,
+    sync::Once,
+    time::Duration,
+};
+
+use tracing_core::{Event, Metadata};
+use tracing_subscriber::fmt::{writer::BoxMakeWriter, MakeWriter};
+
+use crate::{fields, format::Config};
+
+/// The header row, written before the first event.
+const HEADER: &str = "level,target,path,elapsed_us,message,fields\n";
+
+/// The writer of [`HierarchicalLayer::with_csv_export`].
+///
+/// Every event is one row with its `level` and `target`, the `path` of span names from the
+/// root separated by `:`, the `elapsed_us` microseconds since its span was opened, its
+/// `message` and its other `fields` as `name=value` pairs separated by spaces. Field values are
+/// formatted as they are in the tree.
+///
+/// [`HierarchicalLayer::with_csv_export`]: crate::HierarchicalLayer::with_csv_export
+pub(crate) struct CsvExport {
+    make_writer: BoxMakeWriter,
+    header: Once,
+}
+
+impl fmt::Debug for CsvExport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CsvExport").finish_non_exhaustive()
+    }
+}
+
+impl CsvExport {
+    pub(crate) fn new<M>(make_writer: M) -> Self
+    where
+        M: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        Self {
+            make_writer: BoxMakeWriter::new(make_writer),
+            header: Once::new(),
+        }
+    }
+
+    /// Writes an event that occurred `elapsed` after its span was opened.
+    pub(crate) fn event<'a>(
+        &self,
+        metadata: &Metadata<'_>,
+        path: impl Iterator<Item = &'a str>,
+        event: &Event<'_>,
+        config: &Config,
+        elapsed: Option<Duration>,
+    ) {
+        let mut buf = String::new();
+        write!(buf, "{},", metadata.level()).unwrap();
+        cell(&mut buf, metadata.target());
+        buf.push(',');
+        let path: Vec<_> = path.collect();
+        cell(&mut buf, &path.join(":"));
+        buf.push(',');
+        if let Some(elapsed) = elapsed {
+            write!(buf, "{}", elapsed.as_micros()).unwrap();
+        }
+
+        let mut message = String::new();
+        let mut others = String::new();
+        fields::flatten(event, config, |name, value| {
+            if name == "message" {
+                message.push_str(value);
+                return;
+            }
+            if !others.is_empty() {
+                others.push(' ');
+            }
+            write!(others, "{}={}", name, value).unwrap();
+        });
+        buf.push(',');
+        cell(&mut buf, &message);
+        buf.push(',');
+        cell(&mut buf, &others);
+        buf.push('\n');
+
+        let mut writer = self.make_writer.make_writer_for(metadata);
+        // The export is a side channel, failing to write it must not affect the tree
+        self.header.call_once(|| {
+            let _ = writer.write_all(HEADER.as_bytes());
+        });
+        let _ = writer.write_all(buf.as_bytes());
+    }
+
+    pub(crate) fn flush(&self) -> std::io::Result<()> {
+        self.make_writer.make_writer().flush()
+    }
+}
+
+/// Writes `s` as a cell, quoted if it contains a delimiter, a quote or a line break.
+fn cell(buf: &mut String, s: &str) {
+    if !s.contains(&[',', '"', '\n', '\r'][..]) {
+        buf.push_str(s);
+        return;
+    }
+    buf.push('"');
+    for c in s.chars() {
+        if c == '"' {
+            buf.push('"');
+        }
+        buf.push(c);
+    }
+    buf.push('"');
+}
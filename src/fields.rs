@@ -0,0 +1,135 @@
+//! Compact storage for the fields of a span, and flattening the fields of events.
+
+use std::fmt;
+
+use tracing_core::{
+    field::{Field, Visit},
+    Event,
+};
+
+use crate::format::Config;
+
+/// How many fields are stored without allocating a list of them.
+const INLINE: usize = 4;
+
+/// The fields of a span, in the order they were recorded.
+///
+/// All values share a single string, and the names of the first few fields are stored inline,
+/// so that a span with a handful of fields needs at most one allocation.
+#[derive(Debug, Default)]
+pub(crate) struct Fields {
+    values: String,
+    entries: Entries,
+}
+
+/// The names of the fields along with the end of their values in [`Fields::values`].
+#[derive(Debug)]
+enum Entries {
+    Inline(usize, [(&'static str, usize); INLINE]),
+    Heap(Vec<(&'static str, usize)>),
+}
+
+impl Default for Entries {
+    fn default() -> Self {
+        Entries::Inline(0, [("", 0); INLINE])
+    }
+}
+
+impl Entries {
+    fn as_slice(&self) -> &[(&'static str, usize)] {
+        match self {
+            Entries::Inline(len, entries) => &entries[..*len],
+            Entries::Heap(entries) => entries,
+        }
+    }
+
+    fn push(&mut self, entry: (&'static str, usize)) {
+        match self {
+            Entries::Inline(len, entries) if *len < INLINE => {
+                entries[*len] = entry;
+                *len += 1;
+            }
+            Entries::Inline(_, entries) => {
+                let mut heap = Vec::with_capacity(INLINE * 2);
+                heap.extend_from_slice(entries);
+                heap.push(entry);
+                *self = Entries::Heap(heap);
+            }
+            Entries::Heap(entries) => entries.push(entry),
+        }
+    }
+}
+
+impl Fields {
+    /// Adds a field whose value is appended to the given buffer by `write`.
+    ///
+    /// Once the values take up more than `budget` bytes, they are cut off with `…`.
+    pub(crate) fn push(
+        &mut self,
+        name: &'static str,
+        budget: usize,
+        write: impl FnOnce(&mut String),
+    ) {
+        let start = self.values.len();
+        if start >= budget {
+            // The budget was used up by previous fields
+            self.values.push('…');
+        } else {
+            write(&mut self.values);
+            if self.values.len() > budget {
+                let mut end = budget;
+                while !self.values.is_char_boundary(end) {
+                    end -= 1;
+                }
+                self.values.truncate(end);
+                self.values.push('…');
+                self.values.shrink_to_fit();
+            }
+        }
+        self.entries.push((name, self.values.len()));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.as_slice().is_empty()
+    }
+
+    /// The names and values of the fields.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&'static str, &str)> + '_ {
+        let mut start = 0;
+        self.entries.as_slice().iter().map(move |&(name, end)| {
+            let value = &self.values[start..end];
+            start = end;
+            (name, value)
+        })
+    }
+}
+
+/// Calls `visit` with the name and formatted value of every field of `event`, for the machine
+/// readable outputs. Values are formatted as they are in the tree.
+pub(crate) fn flatten(event: &Event<'_>, config: &Config, visit: impl FnMut(&'static str, &str)) {
+    event.record(&mut FlatFields {
+        config,
+        value: String::new(),
+        visit,
+    });
+}
+
+struct FlatFields<'a, F> {
+    config: &'a Config,
+    /// Reused for formatting each value
+    value: String,
+    visit: F,
+}
+
+impl<F: FnMut(&'static str, &str)> Visit for FlatFields<'_, F> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        // Skip fields that are actually log metadata
+        #[cfg(feature = "tracing-log")]
+        if field.name().starts_with("log.") {
+            return;
+        }
+        self.value.clear();
+        self.config.format_field(field, value, &mut self.value);
+        (self.visit)(field.name(), &self.value);
+    }
+}
@@ -0,0 +1,134 @@
+//! Time spent in spans, aggregated per stack of spans in the folded format of
+//! [inferno](https://github.com/jonhoo/inferno) and `flamegraph.pl`.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use tracing_subscriber::{layer::SubscriberExt, Registry};
+//! use tracing_tree::{flame::FoldedStacks, HierarchicalLayer};
+//!
+//! let stacks = FoldedStacks::new();
+//! let layer = HierarchicalLayer::default().with_folded_stacks(stacks.clone());
+//! let subscriber = Registry::default().with(layer);
+//! // ... install the subscriber and do some work ...
+//!
+//! stacks.write_to(File::create("stacks.folded").unwrap()).unwrap();
+//! // inferno-flamegraph < stacks.folded > flamegraph.svg
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    io,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+/// A handle to the stacks collected by a [`HierarchicalLayer`].
+///
+/// Each line holds the names of the spans from the root to a span, separated by `;`, followed
+/// by the time spent in such spans in microseconds. Time spent in child spans is only counted
+/// for the child, so the widths in a flamegraph add up.
+///
+/// Clones share the same stacks, so a clone can be handed to
+/// [`HierarchicalLayer::with_folded_stacks`] while the original is kept to write the stacks.
+///
+/// [`HierarchicalLayer`]: crate::HierarchicalLayer
+/// [`HierarchicalLayer::with_folded_stacks`]: crate::HierarchicalLayer::with_folded_stacks
+#[derive(Clone, Default)]
+pub struct FoldedStacks {
+    inner: Arc<Mutex<Stacks>>,
+}
+
+#[derive(Default)]
+struct Stacks {
+    /// The self time per stack, sorted so the output is stable
+    stacks: BTreeMap<String, Duration>,
+    /// Where to write the stacks once the last handle is dropped
+    writer: Option<Box<dyn io::Write + Send>>,
+}
+
+impl fmt::Debug for FoldedStacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FoldedStacks")
+            .field("stacks", &self.lock().stacks.len())
+            .finish()
+    }
+}
+
+impl FoldedStacks {
+    /// Creates an empty set of stacks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty set of stacks, which are written to `writer` once the layer and all
+    /// other clones are dropped.
+    pub fn with_writer<W: io::Write + Send + 'static>(writer: W) -> Self {
+        let stacks = Self::new();
+        stacks.lock().writer = Some(Box::new(writer));
+        stacks
+    }
+
+    /// The stacks collected so far, one per line.
+    pub fn folded(&self) -> String {
+        let mut buf = String::new();
+        self.lock().render(&mut buf);
+        buf
+    }
+
+    /// Writes the stacks collected so far to `writer`.
+    pub fn write_to<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.folded().as_bytes())?;
+        writer.flush()
+    }
+
+    /// Forgets all stacks collected so far.
+    pub fn reset(&self) {
+        self.lock().stacks.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Stacks> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Adds `self_time` to the stack made up of `names`, starting at the root.
+    pub(crate) fn record<'a>(&self, names: impl IntoIterator<Item = &'a str>, self_time: Duration) {
+        let mut stack = String::new();
+        for name in names {
+            if !stack.is_empty() {
+                stack.push(';');
+            }
+            // Semicolons separate the frames and newlines the stacks
+            stack.extend(name.chars().map(|c| match c {
+                ';' => ':',
+                '\n' | '\r' => ' ',
+                c => c,
+            }));
+        }
+        *self.lock().stacks.entry(stack).or_default() += self_time;
+    }
+}
+
+impl Stacks {
+    fn render(&self, buf: &mut String) {
+        for (stack, time) in &self.stacks {
+            let micros = time.as_micros();
+            // Stacks that took no measurable time would only clutter the flamegraph
+            if micros > 0 {
+                writeln!(buf, "{} {}", stack, micros).unwrap();
+            }
+        }
+    }
+}
+
+impl Drop for Stacks {
+    fn drop(&mut self) {
+        if let Some(mut writer) = self.writer.take() {
+            let mut buf = String::new();
+            self.render(&mut buf);
+            // There is nobody left to report a failure to
+            let _ = writer.write_all(buf.as_bytes());
+            let _ = writer.flush();
+        }
+    }
+}
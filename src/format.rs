@@ -1,22 +1,691 @@
-use nu_ansi_term::Color;
+use nu_ansi_term::{Color, Style};
 use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
     fmt::{self, Write as _},
-    io,
+    io, mem,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    thread::ThreadId,
+    time::Duration,
 };
 use tracing_core::{
     field::{Field, Visit},
-    span, Level,
+    span, Level, Metadata,
 };
 
-pub(crate) const LINE_VERT: &str = "│";
-const LINE_HORIZ: &str = "─";
-pub(crate) const LINE_BRANCH: &str = "├";
-pub(crate) const LINE_CLOSE: &str = "┘";
-pub(crate) const LINE_CLOSE2: char = '┌';
-pub(crate) const LINE_OPEN: &str = "┐";
-pub(crate) const LINE_OPEN2: char = '└';
+use crate::{
+    paint::paint,
+    section::{GitHubActions, SectionMarker},
+    terminal,
+};
+
+/// The characters used to draw the tree when `indent_lines` is enabled.
+///
+/// ```text
+/// ┐open
+/// ├─ event
+/// └─┐open
+///   ├─ event
+///   │  continuation
+/// ┌─┘
+/// ┘
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Glyphs {
+    /// Continues the guide of a span on subsequent lines (`│`).
+    pub vertical: &'static str,
+    /// Connects lines to their parent's guide (`─`).
+    pub horizontal: &'static str,
+    /// Attaches an event to its span's guide (`├`).
+    pub branch: &'static str,
+    /// Starts the guide of an opened span (`┐`).
+    pub open: &'static str,
+    /// Leaves the parent's guide towards an opened span (`└`).
+    pub open_corner: &'static str,
+    /// Ends the guide of a closed span (`┘`).
+    pub close: &'static str,
+    /// Returns to the parent's guide from a closed span (`┌`).
+    pub close_corner: &'static str,
+    /// Replaces the `branch` and `horizontal` glyphs in front of events, e.g. with a bullet
+    /// like `•`, or `None` to attach events to the guide like spans.
+    pub event: Option<&'static str>,
+    /// Replaces the `vertical` glyph and the spaces after it in front of the continuation lines
+    /// of multi-line messages, e.g. with `┆ ` or `↳ `.
+    pub continuation: Option<&'static str>,
+}
+
+impl Glyphs {
+    /// Box drawing characters, the default.
+    pub const fn unicode() -> Self {
+        Self {
+            vertical: "│",
+            horizontal: "─",
+            branch: "├",
+            open: "┐",
+            open_corner: "└",
+            close: "┘",
+            close_corner: "┌",
+            event: None,
+            continuation: None,
+        }
+    }
+
+    /// Plain ASCII characters for terminals and log viewers without unicode support.
+    pub const fn ascii() -> Self {
+        Self {
+            vertical: "|",
+            horizontal: "-",
+            branch: "|",
+            open: "+",
+            open_corner: "\\",
+            close: "+",
+            close_corner: "/",
+            event: None,
+            continuation: None,
+        }
+    }
+
+    /// Draws `event`, e.g. `•` or `›`, in front of events instead of a branch of the guide.
+    pub const fn with_event(self, event: &'static str) -> Self {
+        Self {
+            event: Some(event),
+            ..self
+        }
+    }
+
+    /// Draws `continuation`, e.g. `┆ ` or `↳ `, in front of the continuation lines of
+    /// multi-line messages instead of the guide of their span.
+    pub const fn with_continuation(self, continuation: &'static str) -> Self {
+        Self {
+            continuation: Some(continuation),
+            ..self
+        }
+    }
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Self::unicode()
+    }
+}
+
+/// The styles of the parts of the output, used if `ansi` is enabled.
+///
+/// ```
+/// use tracing_tree::{nu_ansi_term::{Color, Style}, HierarchicalLayer, Theme};
+///
+/// let theme = Theme::default()
+///     .with_wall_clock(Color::Blue.normal())
+///     .with_elapsed(Style::new().bold());
+/// let layer = HierarchicalLayer::default().with_theme(theme);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Theme {
+    /// The style of the timer's wall-clock time. `None` leaves the styling to the timer, which
+    /// usually does not style it.
+    pub wall_clock: Option<Style>,
+    /// The style of the time elapsed in a span. `None` leaves the styling to the timer, which
+    /// usually dims it.
+    pub elapsed: Option<Style>,
+    /// The style of span names and the brackets around span fields.
+    pub span_name: Style,
+    /// The style of secondary text such as targets, span tags and annotations.
+    pub secondary: Style,
+    /// The style of the `TRACE` level.
+    pub trace: Style,
+    /// The style of the `DEBUG` level.
+    pub debug: Style,
+    /// The style of the `INFO` level.
+    pub info: Style,
+    /// The style of the `WARN` level.
+    pub warn: Style,
+    /// The style of the `ERROR` level.
+    pub error: Style,
+}
+
+impl Theme {
+    /// The default theme, made for terminals with a dark background.
+    pub fn dark() -> Self {
+        Self {
+            wall_clock: None,
+            elapsed: None,
+            span_name: Color::Green.bold(),
+            secondary: Style::new().dimmed(),
+            trace: Color::Purple.bold(),
+            debug: Color::Blue.bold(),
+            info: Color::Green.bold(),
+            warn: Color::Rgb(252, 234, 160).bold(), // orange
+            error: Color::Red.bold(),
+        }
+    }
+
+    /// A theme for terminals with a light background, with darker colors and gray instead of
+    /// dimmed text, which many terminals render nearly invisible on white.
+    pub fn light() -> Self {
+        let gray = Color::Fixed(242).normal();
+        Self {
+            wall_clock: Some(gray),
+            elapsed: Some(gray),
+            span_name: Color::Fixed(22).bold(),
+            secondary: gray,
+            trace: Color::Fixed(90).bold(),
+            debug: Color::Fixed(18).bold(),
+            info: Color::Fixed(22).bold(),
+            warn: Color::Fixed(130).bold(),
+            error: Color::Fixed(124).bold(),
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "dark" => Self::dark(),
+            "light" => Self::light(),
+            _ => return None,
+        })
+    }
+
+    /// The style of `level`.
+    pub fn level(&self, level: &Level) -> Style {
+        match *level {
+            Level::TRACE => self.trace,
+            Level::DEBUG => self.debug,
+            Level::INFO => self.info,
+            Level::WARN => self.warn,
+            Level::ERROR => self.error,
+        }
+    }
+
+    /// Styles the wall-clock time with `style`, e.g. `Style::new()` to not style it at all.
+    pub fn with_wall_clock(self, style: Style) -> Self {
+        Self {
+            wall_clock: Some(style),
+            ..self
+        }
+    }
+
+    /// Styles the elapsed time with `style`, e.g. `Style::new()` to not style it at all.
+    pub fn with_elapsed(self, style: Style) -> Self {
+        Self {
+            elapsed: Some(style),
+            ..self
+        }
+    }
+
+    /// Styles span names with `style`.
+    pub fn with_span_name(self, style: Style) -> Self {
+        Self {
+            span_name: style,
+            ..self
+        }
+    }
+
+    /// Styles secondary text such as targets with `style`.
+    pub fn with_secondary(self, style: Style) -> Self {
+        Self {
+            secondary: style,
+            ..self
+        }
+    }
+
+    /// Styles `level` with `style`.
+    pub fn with_level(mut self, level: Level, style: Style) -> Self {
+        match level {
+            Level::TRACE => self.trace = style,
+            Level::DEBUG => self.debug = style,
+            Level::INFO => self.info = style,
+            Level::WARN => self.warn = style,
+            Level::ERROR => self.error = style,
+        }
+        self
+    }
+
+    /// The theme with every color replaced by the closest of the 16 basic ANSI colors.
+    pub(crate) fn basic(self) -> Self {
+        Self {
+            wall_clock: self.wall_clock.map(basic_style),
+            elapsed: self.elapsed.map(basic_style),
+            span_name: basic_style(self.span_name),
+            secondary: basic_style(self.secondary),
+            trace: basic_style(self.trace),
+            debug: basic_style(self.debug),
+            info: basic_style(self.info),
+            warn: basic_style(self.warn),
+            error: basic_style(self.error),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+fn basic_style(style: Style) -> Style {
+    Style {
+        foreground: style.foreground.map(basic_color),
+        background: style.background.map(basic_color),
+        ..style
+    }
+}
+
+/// The basic ANSI color closest to `color`. Colors are matched by hue rather than by distance,
+/// as pale colors such as the orange of `WARN` would otherwise all turn white.
+fn basic_color(color: Color) -> Color {
+    const BASIC: [Color; 16] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Purple,
+        Color::Cyan,
+        Color::White,
+        Color::DarkGray,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightBlue,
+        Color::LightPurple,
+        Color::LightCyan,
+        Color::LightGray,
+    ];
+    const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    let (r, g, b) = match color {
+        Color::Fixed(n) if n < 16 => return BASIC[usize::from(n)],
+        Color::Fixed(n) if n < 232 => {
+            let n = usize::from(n - 16);
+            (CUBE[n / 36], CUBE[n / 6 % 6], CUBE[n % 6])
+        }
+        Color::Fixed(n) => {
+            let gray = 8 + 10 * (n - 232);
+            (gray, gray, gray)
+        }
+        Color::Rgb(r, g, b) => (r, g, b),
+        basic => return basic,
+    };
+
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let value = f32::from(max) / 255.0;
+    if max == 0 || f32::from(max - min) / f32::from(max) < 0.25 {
+        return match value {
+            v if v < 0.25 => Color::Black,
+            v if v < 0.6 => Color::DarkGray,
+            v if v < 0.85 => Color::White,
+            _ => Color::LightGray,
+        };
+    }
+
+    let chroma = f32::from(max - min);
+    let (rf, gf, bf) = (f32::from(r), f32::from(g), f32::from(b));
+    let hue = if max == r {
+        60.0 * ((gf - bf) / chroma)
+    } else if max == g {
+        60.0 * ((bf - rf) / chroma + 2.0)
+    } else {
+        60.0 * ((rf - gf) / chroma + 4.0)
+    };
+    let sector = ((hue + 390.0) % 360.0 / 60.0) as usize;
+    [
+        Color::Red,
+        Color::Yellow,
+        Color::Green,
+        Color::Cyan,
+        Color::Blue,
+        Color::Purple,
+    ][sector]
+}
+
+/// The layout of [`HierarchicalLayer::with_columns`](crate::HierarchicalLayer::with_columns),
+/// which prints one line per event with the columns
+///
+/// ```text
+/// timestamp | thread | depth | level | path | message | fields
+/// ```
+///
+/// The thread, depth, level and path columns are padded to a fixed width, so that the columns
+/// line up, while the message and fields are not padded. The path lists the names of the spans
+/// around the event from the root, separated by `:`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Columns {
+    /// Separates the columns.
+    pub delimiter: Cow<'static, str>,
+    /// The width the thread column is padded to.
+    pub thread_width: usize,
+    /// The width the path column is padded to.
+    pub path_width: usize,
+}
+
+impl Columns {
+    /// Columns separated by `delimiter`.
+    pub fn new(delimiter: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            delimiter: delimiter.into(),
+            thread_width: 12,
+            path_width: 24,
+        }
+    }
+
+    /// Pads the thread column to `width`.
+    pub fn with_thread_width(self, width: usize) -> Self {
+        Self {
+            thread_width: width,
+            ..self
+        }
+    }
+
+    /// Pads the path column to `width`.
+    pub fn with_path_width(self, width: usize) -> Self {
+        Self {
+            path_width: width,
+            ..self
+        }
+    }
+}
+
+impl Default for Columns {
+    /// Columns separated by ` | `.
+    fn default() -> Self {
+        Self::new(" | ")
+    }
+}
+
+/// Which times are printed in front of an event, and in which order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum TimeDisplay {
+    /// The wall-clock time of the timer followed by the time elapsed in the event's span.
+    #[default]
+    Both,
+    /// The time elapsed in the event's span followed by the wall-clock time.
+    ElapsedFirst,
+    /// Only the wall-clock time.
+    WallClock,
+    /// Only the time elapsed in the event's span.
+    Elapsed,
+    /// No times at all.
+    None,
+}
+
+impl TimeDisplay {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "both" => Self::Both,
+            "elapsed_first" => Self::ElapsedFirst,
+            "wall_clock" => Self::WallClock,
+            "elapsed" => Self::Elapsed,
+            "none" => Self::None,
+            _ => return None,
+        })
+    }
+
+    pub(crate) fn wall_clock(self) -> bool {
+        !matches!(self, Self::Elapsed | Self::None)
+    }
+
+    pub(crate) fn elapsed(self) -> bool {
+        !matches!(self, Self::WallClock | Self::None)
+    }
+}
+
+/// A prefix in front of every line that tells log collectors its severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum SeverityPrefix {
+    /// No prefix.
+    #[default]
+    None,
+    /// The `sd-daemon` prefixes `<3>` (error) to `<7>` (trace), which systemd and syslog
+    /// understand on plain stderr.
+    Systemd,
+}
+
+impl SeverityPrefix {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "none" => Self::None,
+            "systemd" => Self::Systemd,
+            _ => return None,
+        })
+    }
+}
+
+/// What is written between the output of two root spans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Separator {
+    /// An empty line.
+    BlankLine,
+    /// A line of the character across the width of the output.
+    Rule(char),
+}
+
+impl Separator {
+    fn from_name(name: &str) -> Option<Self> {
+        let mut chars = name.chars();
+        Some(match (name, chars.next(), chars.next()) {
+            ("blank", _, _) => Self::BlankLine,
+            ("rule", _, _) => Self::Rule('─'),
+            (_, Some(c), None) => Self::Rule(c),
+            _ => return None,
+        })
+    }
+}
+
+/// What is written below events at or above the level of
+/// [`HierarchicalLayer::with_event_divider`](crate::HierarchicalLayer::with_event_divider).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Divider {
+    /// A short dimmed rule.
+    #[default]
+    Rule,
+    /// The name and fields of the event's span, like
+    /// [`HierarchicalLayer::with_header_every`](crate::HierarchicalLayer::with_header_every).
+    Header,
+}
+
+/// Where the `message` field of an event is printed relative to its other fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum MessagePosition {
+    /// Before the other fields.
+    #[default]
+    First,
+    /// After the other fields.
+    Last,
+}
+
+impl MessagePosition {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "first" => Self::First,
+            "last" => Self::Last,
+            _ => return None,
+        })
+    }
+}
 
-#[derive(Debug, Copy, Clone)]
+/// Whether colors are used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AnsiMode {
+    /// Colors are used if the writer is known to be a terminal, and detected again whenever the
+    /// writer changes. A writer that can't be identified gets no colors.
+    Auto,
+    /// Colors are always used.
+    Always,
+    /// Colors are never used.
+    Never,
+}
+
+/// How line breaks and tabs in field values other than the message are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Newlines {
+    /// As they are, so that the value continues on the following lines.
+    Keep,
+    /// As the escapes `\n`, `\r` and `\t`.
+    #[default]
+    Escape,
+    /// As the control pictures `␤`, `␍` and `␉`.
+    Symbols,
+}
+
+impl Newlines {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "keep" => Self::Keep,
+            "escape" => Self::Escape,
+            "symbols" => Self::Symbols,
+            _ => return None,
+        })
+    }
+
+    /// The replacement of `c`, if it is replaced.
+    fn replace(self, c: char) -> Option<&'static str> {
+        match (self, c) {
+            (Self::Keep, _) => None,
+            (Self::Escape, '\n') => Some("\\n"),
+            (Self::Escape, '\r') => Some("\\r"),
+            (Self::Escape, '\t') => Some("\\t"),
+            (Self::Symbols, '\n') => Some("␤"),
+            (Self::Symbols, '\r') => Some("␍"),
+            (Self::Symbols, '\t') => Some("␉"),
+            _ => None,
+        }
+    }
+}
+
+/// What the layer does when writing to its writer fails.
+///
+/// The output that failed to be written is dropped in any case, the layer never panics.
+#[derive(Clone, Default)]
+#[non_exhaustive]
+pub enum WriteErrorPolicy {
+    /// Ignores the error.
+    #[default]
+    Ignore,
+    /// Counts the error, see
+    /// [`HierarchicalLayer::write_errors`](crate::HierarchicalLayer::write_errors).
+    CountAndIgnore,
+    /// Passes the error to a callback. Events emitted by the callback are not shown by the
+    /// layer.
+    Callback(Arc<dyn Fn(&io::Error) + Send + Sync>),
+}
+
+impl WriteErrorPolicy {
+    /// Passes every error to `callback`.
+    pub fn callback<F>(callback: F) -> Self
+    where
+        F: Fn(&io::Error) + Send + Sync + 'static,
+    {
+        Self::Callback(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for WriteErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ignore => f.write_str("Ignore"),
+            Self::CountAndIgnore => f.write_str("CountAndIgnore"),
+            Self::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// Whether a character of a field value could corrupt the terminal or the tree.
+fn needs_escape(c: char) -> bool {
+    match c {
+        '\n' | '\t' | '\x1b' => false,
+        // Bidirectional overrides and isolates
+        '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}' => true,
+        c => c.is_control(),
+    }
+}
+
+/// Escapes the characters of `buf[start..]` rejected by [`needs_escape`].
+fn sanitize(buf: &mut String, start: usize) {
+    let Some(first) = buf[start..].find(needs_escape) else {
+        return;
+    };
+    let tail = buf.split_off(start + first);
+    for c in tail.chars() {
+        if needs_escape(c) {
+            write!(buf, "{}", c.escape_default()).unwrap();
+        } else {
+            buf.push(c);
+        }
+    }
+}
+
+/// Removes the escape sequences from `buf[start..]`: control sequences like `\x1b[2J`, string
+/// sequences like the hyperlinks and window titles of `\x1b]…\x07` and any other character
+/// following an escape.
+fn strip_ansi(buf: &mut String, start: usize) {
+    let Some(first) = buf[start..].find('\x1b') else {
+        return;
+    };
+    let tail = buf.split_off(start + first);
+    let mut chars = tail.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            buf.push(c);
+            continue;
+        }
+        match chars.next() {
+            // Control sequences end with a character from `@` to `~`
+            Some('[') => {
+                chars.find(|c| ('@'..='~').contains(c));
+            }
+            // String sequences end with a bell or the string terminator `\x1b\\`
+            Some(']' | 'P' | 'X' | '^' | '_') => {
+                let mut escape = false;
+                chars.find(|&c| {
+                    let end = c == '\x07' || (escape && c == '\\');
+                    escape = c == '\x1b';
+                    end
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replaces the line breaks and tabs of `buf[start..]` as configured by `newlines`, so that a
+/// value does not break the indentation of the following lines.
+fn escape_newlines(buf: &mut String, start: usize, newlines: Newlines) {
+    let Some(first) = buf[start..].find(|c| newlines.replace(c).is_some()) else {
+        return;
+    };
+    let tail = buf.split_off(start + first);
+    for c in tail.chars() {
+        match newlines.replace(c) {
+            Some(replacement) => buf.push_str(replacement),
+            None => buf.push(c),
+        }
+    }
+}
+
+/// The syslog priority of a level, as used by `tracing-journald`.
+pub(crate) fn syslog_priority(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 5,
+        Level::DEBUG => 6,
+        Level::TRACE => 7,
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub(crate) enum SpanMode {
     /// Executed on the parent before entering a child span
     PreOpen,
@@ -34,10 +703,38 @@ pub(crate) enum SpanMode {
     Event,
 }
 
-#[derive(Debug)]
+/// A user supplied callback stored in the [`Config`] or the outputs of a layer.
+pub(crate) struct Hook<F: ?Sized>(pub(crate) Arc<F>);
+
+impl<F: ?Sized> Clone for Hook<F> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<F: ?Sized> fmt::Debug for Hook<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Hook(..)")
+    }
+}
+
+pub(crate) type SpanNameFn = dyn Fn(&'static Metadata<'static>) -> Cow<'static, str> + Send + Sync;
+
+pub(crate) type FieldFormatterFn =
+    dyn Fn(&Field, &dyn fmt::Debug, &mut dyn fmt::Write) -> bool + Send + Sync;
+
+/// The options of a [`HierarchicalLayer`](crate::HierarchicalLayer).
+///
+/// Usually the layer is configured through its `with_*` methods, but a `Config` can be passed
+/// around, adjusted and handed to
+/// [`HierarchicalLayer::with_config`](crate::HierarchicalLayer::with_config) in one go.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct Config {
     /// Whether to use colors.
     pub ansi: bool,
+    /// Whether `ansi` is set explicitly or detected from the writer.
+    pub ansi_mode: AnsiMode,
     /// Whether an ascii art tree is used or (if false) whether to just use whitespace indent
     pub indent_lines: bool,
     /// The amount of chars to indent.
@@ -48,118 +745,891 @@ pub struct Config {
     pub render_thread_ids: bool,
     /// Whether to show thread names.
     pub render_thread_names: bool,
+    /// Whether the thread ids and names are repeated in front of the continuation lines of
+    /// multi-line messages instead of being replaced by spaces.
+    pub continuation_thread_prefix: bool,
     /// Specifies after how many indentation levels we will wrap back around to zero
     pub wraparound: usize,
+    /// The width of the output in columns, or `None` to detect the width of the terminal.
+    pub width: Option<usize>,
     /// Whether to print the current span before activating a new one
     pub verbose_entry: bool,
     /// Whether to print the current span before exiting it.
     pub verbose_exit: bool,
     /// Print the path leading up to a span if a different span was entered concurrently
     pub span_retrace: bool,
+    /// The maximum number of spans reprinted by a retrace
+    pub retrace_depth: usize,
     /// Whether to print squiggly brackets (`{}`) around the list of fields in a span.
     pub bracketed_fields: bool,
+    /// Whether to print squiggly brackets (`{}`) around the fields of an event, apart from its
+    /// message.
+    pub bracketed_event_fields: bool,
     /// Defer printing a span until an event is generated inside of it
     pub deferred_spans: bool,
     /// Print a label of the span mode (open/close etc).
     pub span_modes: bool,
+    /// Whether to annotate span activity that happens on a different thread than before.
+    pub thread_migrations: bool,
+    /// Whether to tag spans and their events with a short identifier.
+    pub span_tags: bool,
+    /// Whether to show how often a span has been entered on its retraced lines.
+    pub enter_counts: bool,
+    /// The maximum number of events printed directly inside of a span
+    pub max_events_per_span: usize,
+    /// Hold back low severity events unless an event of this level occurs in their span
+    pub quiet_until: Option<Level>,
+    /// Whether to abbreviate levels to a single letter.
+    pub short_levels: bool,
+    /// The least severe level shown on event lines, or `None` to show all levels.
+    pub level_from: Option<Level>,
+    /// Whether to show the level of a span on its lines.
+    pub span_levels: bool,
+    /// Whether to show the time since the previous event in the same span.
+    pub event_deltas: bool,
+    /// Whether elapsed times are measured from the start of the root span.
+    pub elapsed_from_root: bool,
+    /// Which times are printed in front of events.
+    pub time_display: TimeDisplay,
+    /// The minimum width of the wall-clock time of events.
+    pub wall_clock_width: usize,
+    /// The minimum width of the elapsed time of events.
+    pub elapsed_width: usize,
+    /// A prefix in front of every line that tells its severity.
+    pub severity_prefix: SeverityPrefix,
+    /// What to do when writing the output fails.
+    pub write_error_policy: WriteErrorPolicy,
+    /// Whether to escape control characters in field values.
+    pub sanitize_fields: bool,
+    /// How line breaks and tabs in field values other than the message are written if
+    /// `sanitize_fields` is enabled.
+    pub field_newlines: Newlines,
+    /// Whether escape sequences are stripped from field values, or `None` to strip them only if
+    /// `ansi` is disabled.
+    pub sanitize_ansi: Option<bool>,
+    /// Whether to render a nested Markdown list instead of a tree.
+    pub markdown: bool,
+    /// Whether to render the OpenTelemetry trace id on span open lines.
+    #[cfg(feature = "opentelemetry")]
+    pub otel_ids: bool,
+    /// The maximum number of bytes of field values stored per span.
+    pub field_budget: usize,
+    /// Whether to wrap every root span in a collapsible GitHub Actions group.
+    pub github_groups: bool,
+    /// Whether the slowest chain of spans is printed when a root span closes.
+    pub critical_path: bool,
+    /// Whether the shape of a span tree is printed when its root span closes.
+    pub tree_stats: bool,
+    /// Whether times and thread ids are replaced by stable placeholders, for snapshot tests.
+    pub deterministic_output: bool,
+    /// Whether spans without data of this layer are rendered with their name only instead of
+    /// panicking.
+    pub lenient_spans: bool,
+    /// Whether the open and close lines of a span end with the same marker.
+    pub pair_markers: bool,
+    /// The layout of the columns if events are printed in columns instead of a tree.
+    pub columns: Option<Columns>,
+    /// Whether a line names the span that was left when retracing switches to another branch.
+    pub switch_markers: bool,
+    /// Whether retraced spans show the time elapsed since they were opened.
+    pub retrace_elapsed: bool,
+    /// Whether the wall-clock times a span was opened and closed at are printed on its close
+    /// line.
+    pub span_time_range: bool,
+    /// Whether a backtrace is printed below `ERROR` events.
+    pub error_backtraces: bool,
+    /// Whether backtraces are captured regardless of `RUST_BACKTRACE`.
+    pub force_backtraces: bool,
+    /// After how many events inside of a span its header is printed again, or `0` to never
+    /// print it again.
+    pub header_every: usize,
+    /// What is written before a root span is opened, unless it is the first one.
+    pub root_separator: Option<Separator>,
+    /// The least severe level of events that are followed by `divider`, or `None` for no
+    /// dividers.
+    pub divider_level: Option<Level>,
+    /// What is written below events at or above `divider_level`.
+    pub divider: Divider,
+    /// Where the message of an event is printed relative to its other fields.
+    pub message_position: MessagePosition,
+    pub(crate) thread_aliases: ThreadAliases,
+    /// The most verbose level of spans and events that are recorded at all.
+    pub max_level: Option<Level>,
+    /// The most verbose level per target prefix, taking precedence over `max_level`.
+    pub target_filter: Vec<(String, Level)>,
+    /// The characters used to draw the tree.
+    pub glyphs: Glyphs,
+    /// Whether the tree is colored with a gradient by depth.
+    pub guide_gradient: bool,
+    /// The styles of the parts of the output.
+    pub theme: Theme,
+    /// Whether only the 16 basic ANSI colors are used.
+    pub basic_colors: bool,
+    /// Rewrites span names before they are displayed.
+    pub(crate) span_name: Option<Hook<SpanNameFn>>,
+    /// Rewrites field values before they are displayed.
+    pub(crate) field_formatter: Option<Hook<FieldFormatterFn>>,
+    /// Delimits the output of root spans, overriding `github_groups`.
+    pub(crate) section_marker: Option<Hook<dyn SectionMarker>>,
 }
 
-impl Config {
-    pub fn with_ansi(self, ansi: bool) -> Self {
-        Self { ansi, ..self }
+impl Config {
+    pub fn with_ansi(self, ansi: bool) -> Self {
+        Self {
+            ansi,
+            ansi_mode: if ansi {
+                AnsiMode::Always
+            } else {
+                AnsiMode::Never
+            },
+            ..self
+        }
+    }
+
+    /// Sets whether colors are used. [`AnsiMode::Auto`] takes effect with the next detection,
+    /// see [`HierarchicalLayer::with_ansi_mode`](crate::HierarchicalLayer::with_ansi_mode).
+    pub fn with_ansi_mode(self, ansi_mode: AnsiMode) -> Self {
+        match ansi_mode {
+            AnsiMode::Auto => Self { ansi_mode, ..self },
+            AnsiMode::Always => self.with_ansi(true),
+            AnsiMode::Never => self.with_ansi(false),
+        }
+    }
+
+    /// Uses colors if the writer is a `terminal`, when `ansi_mode` is [`AnsiMode::Auto`]. A
+    /// writer that may or may not be a terminal (`None`) gets no colors.
+    pub(crate) fn detect_ansi(self, terminal: Option<bool>) -> Self {
+        if self.ansi_mode != AnsiMode::Auto {
+            return self;
+        }
+        Self {
+            ansi: terminal.unwrap_or(false) && !self.markdown,
+            ..self
+        }
+    }
+
+    pub fn with_indent_lines(self, indent_lines: bool) -> Self {
+        Self {
+            indent_lines,
+            ..self
+        }
+    }
+
+    pub fn with_targets(self, targets: bool) -> Self {
+        Self { targets, ..self }
+    }
+
+    pub fn with_thread_ids(self, render_thread_ids: bool) -> Self {
+        Self {
+            render_thread_ids,
+            ..self
+        }
+    }
+
+    pub fn with_thread_names(self, render_thread_names: bool) -> Self {
+        Self {
+            render_thread_names,
+            ..self
+        }
+    }
+
+    pub fn with_continuation_thread_prefix(self, continuation_thread_prefix: bool) -> Self {
+        Self {
+            continuation_thread_prefix,
+            ..self
+        }
+    }
+
+    pub fn with_wraparound(self, wraparound: usize) -> Self {
+        Self { wraparound, ..self }
+    }
+
+    pub fn with_width(self, width: usize) -> Self {
+        Self {
+            width: Some(width),
+            ..self
+        }
+    }
+
+    /// The width of the output, if set or known.
+    pub(crate) fn output_width(&self) -> Option<usize> {
+        self.width.or_else(terminal::width)
+    }
+
+    pub fn with_verbose_entry(self, verbose_entry: bool) -> Self {
+        Self {
+            verbose_entry,
+            ..self
+        }
+    }
+
+    pub fn with_verbose_exit(self, verbose_exit: bool) -> Self {
+        Self {
+            verbose_exit,
+            ..self
+        }
+    }
+
+    pub fn with_span_retrace(self, enabled: bool) -> Self {
+        Self {
+            span_retrace: enabled,
+            ..self
+        }
+    }
+
+    pub fn with_retrace_depth(self, retrace_depth: usize) -> Self {
+        Self {
+            retrace_depth,
+            ..self
+        }
+    }
+
+    pub fn with_deferred_spans(self, enable: bool) -> Self {
+        Self {
+            deferred_spans: enable,
+            ..self
+        }
+    }
+
+    pub fn with_span_modes(self, enable: bool) -> Self {
+        Self {
+            span_modes: enable,
+            ..self
+        }
+    }
+
+    pub fn with_bracketed_fields(self, bracketed_fields: bool) -> Self {
+        Self {
+            bracketed_fields,
+            ..self
+        }
+    }
+
+    pub fn with_bracketed_event_fields(self, bracketed_event_fields: bool) -> Self {
+        Self {
+            bracketed_event_fields,
+            ..self
+        }
+    }
+
+    pub fn with_thread_migrations(self, thread_migrations: bool) -> Self {
+        Self {
+            thread_migrations,
+            ..self
+        }
+    }
+
+    pub fn with_span_tags(self, span_tags: bool) -> Self {
+        Self { span_tags, ..self }
+    }
+
+    pub fn with_enter_counts(self, enter_counts: bool) -> Self {
+        Self {
+            enter_counts,
+            ..self
+        }
+    }
+
+    pub fn with_max_events_per_span(self, max_events_per_span: usize) -> Self {
+        Self {
+            max_events_per_span,
+            ..self
+        }
+    }
+
+    pub fn with_quiet_until_error(self, quiet_until: Option<Level>) -> Self {
+        Self {
+            quiet_until,
+            ..self
+        }
+    }
+
+    pub fn with_short_levels(self, short_levels: bool) -> Self {
+        Self {
+            short_levels,
+            ..self
+        }
+    }
+
+    pub fn with_level_from(self, level_from: Option<Level>) -> Self {
+        Self { level_from, ..self }
+    }
+
+    pub fn with_span_levels(self, span_levels: bool) -> Self {
+        Self {
+            span_levels,
+            ..self
+        }
+    }
+
+    pub fn with_event_deltas(self, event_deltas: bool) -> Self {
+        Self {
+            event_deltas,
+            ..self
+        }
+    }
+
+    pub fn with_elapsed_from_root(self, elapsed_from_root: bool) -> Self {
+        Self {
+            elapsed_from_root,
+            ..self
+        }
+    }
+
+    pub fn with_time_display(self, time_display: TimeDisplay) -> Self {
+        Self {
+            time_display,
+            ..self
+        }
+    }
+
+    pub fn with_wall_clock_width(self, width: usize) -> Self {
+        Self {
+            wall_clock_width: width,
+            ..self
+        }
+    }
+
+    pub fn with_elapsed_width(self, width: usize) -> Self {
+        Self {
+            elapsed_width: width,
+            ..self
+        }
+    }
+
+    pub fn with_severity_prefix(self, prefix: SeverityPrefix) -> Self {
+        Self {
+            severity_prefix: prefix,
+            ..self
+        }
+    }
+
+    pub fn with_write_error_policy(self, policy: WriteErrorPolicy) -> Self {
+        Self {
+            write_error_policy: policy,
+            ..self
+        }
+    }
+
+    pub fn with_sanitize_fields(self, sanitize_fields: bool) -> Self {
+        Self {
+            sanitize_fields,
+            ..self
+        }
+    }
+
+    pub fn with_field_newlines(self, field_newlines: Newlines) -> Self {
+        Self {
+            field_newlines,
+            ..self
+        }
+    }
+
+    pub fn with_sanitize_ansi(self, sanitize_ansi: Option<bool>) -> Self {
+        Self {
+            sanitize_ansi,
+            ..self
+        }
+    }
+
+    /// Enabling this also disables colors.
+    pub fn with_markdown(self, markdown: bool) -> Self {
+        Self {
+            markdown,
+            ansi: self.ansi && !markdown,
+            ..self
+        }
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    pub fn with_otel_ids(self, otel_ids: bool) -> Self {
+        Self { otel_ids, ..self }
+    }
+
+    pub fn with_field_budget_bytes(self, budget: usize) -> Self {
+        Self {
+            field_budget: budget,
+            ..self
+        }
+    }
+
+    pub fn with_github_groups(self, github_groups: bool) -> Self {
+        Self {
+            github_groups,
+            ..self
+        }
+    }
+
+    pub fn with_critical_path(self, critical_path: bool) -> Self {
+        Self {
+            critical_path,
+            ..self
+        }
+    }
+
+    pub fn with_tree_stats(self, tree_stats: bool) -> Self {
+        Self { tree_stats, ..self }
+    }
+
+    pub fn with_deterministic_output(self, deterministic_output: bool) -> Self {
+        Self {
+            deterministic_output,
+            ..self
+        }
+    }
+
+    pub fn with_lenient_spans(self, lenient_spans: bool) -> Self {
+        Self {
+            lenient_spans,
+            ..self
+        }
+    }
+
+    pub fn with_pair_markers(self, pair_markers: bool) -> Self {
+        Self {
+            pair_markers,
+            ..self
+        }
+    }
+
+    pub fn with_columns(self, columns: Option<Columns>) -> Self {
+        Self { columns, ..self }
     }
 
-    pub fn with_indent_lines(self, indent_lines: bool) -> Self {
+    pub fn with_switch_markers(self, switch_markers: bool) -> Self {
         Self {
-            indent_lines,
+            switch_markers,
             ..self
         }
     }
 
-    pub fn with_targets(self, targets: bool) -> Self {
-        Self { targets, ..self }
+    pub fn with_retrace_elapsed(self, retrace_elapsed: bool) -> Self {
+        Self {
+            retrace_elapsed,
+            ..self
+        }
     }
 
-    pub fn with_thread_ids(self, render_thread_ids: bool) -> Self {
+    pub fn with_span_time_range(self, span_time_range: bool) -> Self {
         Self {
-            render_thread_ids,
+            span_time_range,
             ..self
         }
     }
 
-    pub fn with_thread_names(self, render_thread_names: bool) -> Self {
+    pub fn with_error_backtraces(self, error_backtraces: bool) -> Self {
         Self {
-            render_thread_names,
+            error_backtraces,
             ..self
         }
     }
 
-    pub fn with_wraparound(self, wraparound: usize) -> Self {
-        Self { wraparound, ..self }
+    pub fn with_force_backtraces(self, force_backtraces: bool) -> Self {
+        Self {
+            force_backtraces,
+            ..self
+        }
     }
 
-    pub fn with_verbose_entry(self, verbose_entry: bool) -> Self {
+    pub fn with_header_every(self, header_every: usize) -> Self {
         Self {
-            verbose_entry,
+            header_every,
             ..self
         }
     }
 
-    pub fn with_verbose_exit(self, verbose_exit: bool) -> Self {
+    pub fn with_root_separator(self, root_separator: Option<Separator>) -> Self {
         Self {
-            verbose_exit,
+            root_separator,
             ..self
         }
     }
 
-    pub fn with_span_retrace(self, enabled: bool) -> Self {
+    pub fn with_event_divider(self, divider_level: Option<Level>, divider: Divider) -> Self {
         Self {
-            span_retrace: enabled,
+            divider_level,
+            divider,
             ..self
         }
     }
 
-    pub fn with_deferred_spans(self, enable: bool) -> Self {
+    pub fn with_message_position(self, message_position: MessagePosition) -> Self {
         Self {
-            deferred_spans: enable,
+            message_position,
             ..self
         }
     }
 
-    pub fn with_span_modes(self, enable: bool) -> Self {
+    pub fn with_max_level(self, max_level: Option<Level>) -> Self {
+        Self { max_level, ..self }
+    }
+
+    pub fn with_target_filter(self, target_filter: Vec<(String, Level)>) -> Self {
         Self {
-            span_modes: enable,
+            target_filter,
             ..self
         }
     }
 
-    pub fn with_bracketed_fields(self, bracketed_fields: bool) -> Self {
+    pub fn with_glyphs(self, glyphs: Glyphs) -> Self {
+        Self { glyphs, ..self }
+    }
+
+    pub fn with_guide_gradient(self, guide_gradient: bool) -> Self {
         Self {
-            bracketed_fields,
+            guide_gradient,
             ..self
         }
     }
 
-    pub(crate) fn prefix(&self) -> String {
-        let mut buf = String::new();
-        if self.render_thread_ids {
-            write!(buf, "{:?}", std::thread::current().id()).unwrap();
-            if buf.ends_with(')') {
-                buf.truncate(buf.len() - 1);
-            }
-            if buf.starts_with("ThreadId(") {
-                buf.drain(0.."ThreadId(".len());
+    pub fn with_theme(self, theme: Theme) -> Self {
+        Self { theme, ..self }
+    }
+
+    pub fn with_basic_colors(self, basic_colors: bool) -> Self {
+        Self {
+            basic_colors,
+            ..self
+        }
+    }
+
+    /// The theme the output is styled with.
+    pub(crate) fn theme(&self) -> Theme {
+        if self.basic_colors {
+            self.theme.basic()
+        } else {
+            self.theme
+        }
+    }
+
+    pub fn with_span_name_fn<F>(self, span_name: F) -> Self
+    where
+        F: Fn(&'static Metadata<'static>) -> Cow<'static, str> + Send + Sync + 'static,
+    {
+        Self {
+            span_name: Some(Hook(Arc::new(span_name))),
+            ..self
+        }
+    }
+
+    /// The name to display for a span with the given metadata.
+    pub(crate) fn span_name(&self, metadata: &'static Metadata<'static>) -> Cow<'static, str> {
+        match &self.span_name {
+            Some(Hook(f)) => f(metadata),
+            None => Cow::Borrowed(metadata.name()),
+        }
+    }
+
+    pub fn with_field_formatter<F>(self, field_formatter: F) -> Self
+    where
+        F: Fn(&Field, &dyn fmt::Debug, &mut dyn fmt::Write) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            field_formatter: Some(Hook(Arc::new(field_formatter))),
+            ..self
+        }
+    }
+
+    pub fn with_section_marker<M>(self, marker: M) -> Self
+    where
+        M: SectionMarker + 'static,
+    {
+        Self {
+            section_marker: Some(Hook(Arc::new(marker))),
+            ..self
+        }
+    }
+
+    /// The marker that delimits the output of root spans, if any.
+    pub(crate) fn section_marker(&self) -> Option<&dyn SectionMarker> {
+        match &self.section_marker {
+            Some(Hook(marker)) => Some(&**marker),
+            None if self.github_groups => Some(&GitHubActions),
+            None => None,
+        }
+    }
+
+    /// Writes the value of a span or event field.
+    pub(crate) fn format_field(&self, field: &Field, value: &dyn fmt::Debug, buf: &mut String) {
+        let start = buf.len();
+        let formatted = match &self.field_formatter {
+            Some(Hook(f)) => f(field, value, buf),
+            None => false,
+        };
+        if !formatted {
+            buf.truncate(start);
+            write!(buf, "{:?}", value).unwrap();
+        }
+        if self.sanitize_ansi.unwrap_or(!self.ansi) {
+            strip_ansi(buf, start);
+        }
+        if self.sanitize_fields {
+            if field.name() != "message" {
+                escape_newlines(buf, start, self.field_newlines);
             }
+            sanitize(buf, start);
         }
-        if self.render_thread_names {
-            if let Some(name) = std::thread::current().name() {
-                if self.render_thread_ids {
-                    buf.push(':');
+    }
+
+    /// Applies a comma separated list of options such as `indent=4,lines,targets,retrace`.
+    ///
+    /// Flags can be given as `name`, `name=true` or `name=false`. Unknown options and invalid
+    /// values are ignored, so a typo in an environment variable does not crash the program. This
+    /// includes values that the builder would reject, such as `wraparound=0` or `indent=0`
+    /// together with `lines`.
+    pub fn with_options(mut self, options: &str) -> Self {
+        let indent_amount = self.indent_amount;
+        for option in options.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim())),
+                None => (option, None),
+            };
+            let flag = match value {
+                None | Some("true") | Some("1") => Some(true),
+                Some("false") | Some("0") => Some(false),
+                Some(_) => None,
+            };
+            let number = value.and_then(|v| v.parse::<usize>().ok());
+            match (name, flag, number) {
+                ("indent", _, Some(n)) => self.indent_amount = n,
+                ("wraparound", _, Some(n)) if n > 0 => self.wraparound = n,
+                ("width", _, Some(n)) => self.width = Some(n),
+                ("retrace_depth", _, Some(n)) if n > 0 => self.retrace_depth = n,
+                ("max_events_per_span", _, Some(n)) => self.max_events_per_span = n,
+                ("field_budget_bytes", _, Some(n)) => self.field_budget = n,
+                ("wall_clock_width", _, Some(n)) => self.wall_clock_width = n,
+                ("elapsed_width", _, Some(n)) => self.elapsed_width = n,
+                ("ansi", Some(b), _) => self = self.with_ansi(b),
+                ("ansi", _, _) if value == Some("auto") => self.ansi_mode = AnsiMode::Auto,
+                ("lines", Some(b), _) => self.indent_lines = b,
+                ("targets", Some(b), _) => self.targets = b,
+                ("thread_ids", Some(b), _) => self.render_thread_ids = b,
+                ("thread_names", Some(b), _) => self.render_thread_names = b,
+                ("continuation_thread_prefix", Some(b), _) => self.continuation_thread_prefix = b,
+                ("verbose_entry", Some(b), _) => self.verbose_entry = b,
+                ("verbose_exit", Some(b), _) => self.verbose_exit = b,
+                ("retrace", Some(b), _) => self.span_retrace = b,
+                ("deferred", Some(b), _) => self.deferred_spans = b,
+                ("span_modes", Some(b), _) => self.span_modes = b,
+                ("bracketed_fields", Some(b), _) => self.bracketed_fields = b,
+                ("bracketed_event_fields", Some(b), _) => self.bracketed_event_fields = b,
+                ("thread_migrations", Some(b), _) => self.thread_migrations = b,
+                ("span_tags", Some(b), _) => self.span_tags = b,
+                ("enter_counts", Some(b), _) => self.enter_counts = b,
+                ("short_levels", Some(b), _) => self.short_levels = b,
+                ("level_from", _, _) => {
+                    if let Some(level) = value.and_then(|level| level.parse().ok()) {
+                        self.level_from = Some(level);
+                    }
                 }
-                buf.push_str(name);
+                ("span_levels", Some(b), _) => self.span_levels = b,
+                ("event_deltas", Some(b), _) => self.event_deltas = b,
+                ("elapsed_from_root", Some(b), _) => self.elapsed_from_root = b,
+                ("github_groups", Some(b), _) => self.github_groups = b,
+                ("sanitize_fields", Some(b), _) => self.sanitize_fields = b,
+                ("sanitize_ansi", Some(b), _) => self.sanitize_ansi = Some(b),
+                ("field_newlines", _, _) => {
+                    if let Some(newlines) = value.and_then(Newlines::from_name) {
+                        self.field_newlines = newlines;
+                    }
+                }
+                ("markdown", Some(b), _) => self = self.with_markdown(b),
+                ("critical_path", Some(b), _) => self.critical_path = b,
+                ("tree_stats", Some(b), _) => self.tree_stats = b,
+                ("deterministic", Some(b), _) => self.deterministic_output = b,
+                ("guide_gradient", Some(b), _) => self.guide_gradient = b,
+                ("basic_colors", Some(b), _) => self.basic_colors = b,
+                ("lenient_spans", Some(b), _) => self.lenient_spans = b,
+                ("pair_markers", Some(b), _) => self.pair_markers = b,
+                ("switch_markers", Some(b), _) => self.switch_markers = b,
+                ("retrace_elapsed", Some(b), _) => self.retrace_elapsed = b,
+                ("span_time_range", Some(b), _) => self.span_time_range = b,
+                ("error_backtraces", Some(b), _) => self.error_backtraces = b,
+                ("force_backtraces", Some(b), _) => self.force_backtraces = b,
+                ("header_every", _, Some(n)) => self.header_every = n,
+                #[cfg(feature = "opentelemetry")]
+                ("otel_ids", Some(b), _) => self.otel_ids = b,
+                ("severity_prefix", _, _) => {
+                    if let Some(prefix) = value.and_then(SeverityPrefix::from_name) {
+                        self.severity_prefix = prefix;
+                    }
+                }
+                ("root_separator", Some(false), _) => self.root_separator = None,
+                ("root_separator", _, _) => {
+                    if let Some(separator) = value.and_then(Separator::from_name) {
+                        self.root_separator = Some(separator);
+                    }
+                }
+                ("event_divider", Some(false), _) => self.divider_level = None,
+                ("event_divider", _, _) => {
+                    let (level, divider) = match value.map(|v| v.split_once(':')) {
+                        Some(Some((level, "header"))) => (Some(level), Divider::Header),
+                        Some(Some((level, "rule"))) => (Some(level), Divider::Rule),
+                        Some(Some(_)) => (None, self.divider),
+                        Some(None) => (value, Divider::Rule),
+                        None => (Some("error"), Divider::Rule),
+                    };
+                    if let Some(level) = level.and_then(|level| level.parse().ok()) {
+                        self.divider_level = Some(level);
+                        self.divider = divider;
+                    }
+                }
+                ("message_position", _, _) => {
+                    if let Some(position) = value.and_then(MessagePosition::from_name) {
+                        self.message_position = position;
+                    }
+                }
+                ("theme", _, _) => {
+                    if let Some(theme) = value.and_then(Theme::from_name) {
+                        self.theme = theme;
+                    }
+                }
+                ("columns", Some(false), _) => self.columns = None,
+                ("columns", Some(true), _) => self.columns = Some(Columns::default()),
+                ("columns", None, _) => self.columns = value.map(|d| Columns::new(d.to_owned())),
+                ("time_display", _, _) => {
+                    if let Some(display) = value.and_then(TimeDisplay::from_name) {
+                        self.time_display = display;
+                    }
+                }
+                ("ascii", Some(b), _) => {
+                    self.glyphs = if b {
+                        Glyphs::ascii()
+                    } else {
+                        Glyphs::unicode()
+                    }
+                }
+                _ => {}
+            }
+        }
+        if self.indent_lines && self.indent_amount == 0 {
+            self.indent_amount = indent_amount.max(1);
+        }
+        self
+    }
+
+    pub(crate) fn prefix(&self) -> Rc<str> {
+        self.thread_label(self.render_thread_ids, self.render_thread_names)
+    }
+
+    /// The label of the current thread, with its alias instead of its id for deterministic
+    /// output.
+    pub(crate) fn thread_label(&self, ids: bool, names: bool) -> Rc<str> {
+        if !self.deterministic_output {
+            return thread_label(ids, names);
+        }
+        let alias = self.thread_aliases.get(std::thread::current().id());
+        let mut buf = String::new();
+        write_thread_label(&mut buf, ids, Some(alias), names);
+        buf.into()
+    }
+
+    /// The most verbose level recorded for `target`: that of the longest matching prefix in
+    /// `target_filter`, or `max_level` if there is none. Prefixes match whole path segments, so
+    /// `hyper` matches `hyper::proto` but not `hyperlocal`.
+    pub(crate) fn max_level_of(&self, target: &str) -> Option<Level> {
+        self.target_filter
+            .iter()
+            .filter(|(prefix, _)| {
+                target
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|&(_, level)| level)
+            .or(self.max_level)
+    }
+
+    /// The duration to render for `elapsed`, which is zero for deterministic output.
+    pub(crate) fn shown_duration(&self, elapsed: Duration) -> Duration {
+        if self.deterministic_output {
+            Duration::ZERO
+        } else {
+            elapsed
+        }
+    }
+}
+
+/// Sequential aliases of the threads seen by a layer, rendered instead of thread ids for
+/// deterministic output.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ThreadAliases(Arc<Mutex<HashMap<ThreadId, usize>>>);
+
+impl ThreadAliases {
+    fn get(&self, thread: ThreadId) -> usize {
+        let mut aliases = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        let next = aliases.len() + 1;
+        *aliases.entry(thread).or_insert(next)
+    }
+}
+
+/// Replaces the digits written to `buf` after `start` with zeros.
+pub(crate) fn mask_digits(buf: &mut String, start: usize) {
+    if buf[start..].bytes().any(|b| b.is_ascii_digit()) {
+        let masked: String = buf[start..]
+            .chars()
+            .map(|c| if c.is_ascii_digit() { '0' } else { c })
+            .collect();
+        buf.truncate(start);
+        buf.push_str(&masked);
+    }
+}
+
+/// The number of characters `s` takes up on a terminal, ignoring ANSI escape sequences.
+pub(crate) fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip to the end of the control sequence, e.g. `\x1b[2m`
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+thread_local! {
+    /// The labels of the current thread, indexed by whether they contain the id and the name.
+    static THREAD_LABELS: RefCell<[Option<Rc<str>>; 4]> = RefCell::default();
+}
+
+/// The label of the current thread, computed once per thread as threads can't be renamed.
+pub(crate) fn thread_label(ids: bool, names: bool) -> Rc<str> {
+    THREAD_LABELS.with(|labels| {
+        let slot = &mut labels.borrow_mut()[usize::from(ids) << 1 | usize::from(names)];
+        slot.get_or_insert_with(|| {
+            let mut buf = String::new();
+            write_thread_label(&mut buf, ids, None, names);
+            buf.into()
+        })
+        .clone()
+    })
+}
+
+/// Writes the id and/or name of the current thread, e.g. `7:tokio-worker`. The id is replaced
+/// by `alias` if there is one.
+fn write_thread_label(buf: &mut String, ids: bool, alias: Option<usize>, names: bool) {
+    let start = buf.len();
+    if let (true, Some(alias)) = (ids, alias) {
+        write!(buf, "{}", alias).unwrap();
+    } else if ids {
+        write!(buf, "{:?}", std::thread::current().id()).unwrap();
+        if buf.ends_with(')') {
+            buf.truncate(buf.len() - 1);
+        }
+        if buf[start..].starts_with("ThreadId(") {
+            buf.drain(start..start + "ThreadId(".len());
+        }
+    }
+    if names {
+        if let Some(name) = std::thread::current().name() {
+            if ids {
+                buf.push(':');
             }
+            buf.push_str(name);
         }
-        buf
     }
 }
 
@@ -167,26 +1637,82 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             ansi: true,
+            ansi_mode: AnsiMode::Always,
             indent_lines: false,
             indent_amount: 2,
             targets: false,
             render_thread_ids: false,
             render_thread_names: false,
+            continuation_thread_prefix: true,
             wraparound: usize::MAX,
+            width: None,
             verbose_entry: false,
             verbose_exit: false,
             span_retrace: false,
+            retrace_depth: usize::MAX,
             bracketed_fields: false,
+            bracketed_event_fields: false,
             deferred_spans: false,
             span_modes: false,
+            thread_migrations: false,
+            span_tags: false,
+            enter_counts: false,
+            max_events_per_span: usize::MAX,
+            quiet_until: None,
+            short_levels: false,
+            level_from: None,
+            span_levels: false,
+            event_deltas: false,
+            elapsed_from_root: false,
+            time_display: TimeDisplay::Both,
+            wall_clock_width: 0,
+            elapsed_width: 0,
+            severity_prefix: SeverityPrefix::None,
+            write_error_policy: WriteErrorPolicy::Ignore,
+            sanitize_fields: true,
+            field_newlines: Newlines::Escape,
+            sanitize_ansi: None,
+            markdown: false,
+            #[cfg(feature = "opentelemetry")]
+            otel_ids: false,
+            field_budget: usize::MAX,
+            github_groups: false,
+            critical_path: false,
+            tree_stats: false,
+            deterministic_output: false,
+            lenient_spans: false,
+            pair_markers: false,
+            columns: None,
+            switch_markers: false,
+            retrace_elapsed: false,
+            span_time_range: false,
+            error_backtraces: false,
+            force_backtraces: false,
+            header_every: 0,
+            root_separator: None,
+            divider_level: None,
+            divider: Divider::Rule,
+            message_position: MessagePosition::First,
+            thread_aliases: ThreadAliases::default(),
+            max_level: None,
+            target_filter: Vec::new(),
+            glyphs: Glyphs::unicode(),
+            guide_gradient: false,
+            theme: Theme::default(),
+            basic_colors: false,
+            span_name: None,
+            field_formatter: None,
+            section_marker: None,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Buffers {
     pub current_buf: String,
     pub indent_buf: String,
+    /// The complete lines rendered by the current layer callback, written in one go
+    pub record: String,
 
     /// The last seen span of this layer
     ///
@@ -194,6 +1720,29 @@ pub struct Buffers {
     /// without the spans entering and exiting beforehand. This happens for multithreaded code
     /// and instrumented futures
     pub current_span: Option<span::Id>,
+
+    /// Tags handed out to the currently open spans
+    pub(crate) span_tags: SpanTags,
+
+    /// The indentation drawn for previous lines
+    indents: IndentCache,
+
+    /// Spare buffers to take the place of lines that are held back
+    pool: Vec<String>,
+}
+
+/// Buffers that grew larger than this are shrunk again, so that a single huge record does not
+/// keep its memory around forever.
+const MAX_RETAINED_CAPACITY: usize = 16 * 1024;
+
+/// How many spare buffers are kept in [`Buffers::pool`].
+const POOL_SIZE: usize = 16;
+
+fn clamp_capacity(buf: &mut String) {
+    if buf.capacity() > MAX_RETAINED_CAPACITY {
+        buf.clear();
+        buf.shrink_to(MAX_RETAINED_CAPACITY);
+    }
 }
 
 impl Buffers {
@@ -201,15 +1750,53 @@ impl Buffers {
         Self {
             current_buf: String::new(),
             indent_buf: String::new(),
+            record: String::new(),
             current_span: None,
+            span_tags: SpanTags::default(),
+            indents: IndentCache::default(),
+            pool: Vec::new(),
+        }
+    }
+
+    /// Takes the rendered lines out of `current_buf`, e.g. to hold them back.
+    pub(crate) fn take_current_buf(&mut self) -> String {
+        let spare = self.pool.pop().unwrap_or_default();
+        mem::replace(&mut self.current_buf, spare)
+    }
+
+    /// Returns a buffer taken with [`take_current_buf`](Self::take_current_buf) to the pool.
+    pub(crate) fn recycle(&mut self, mut buf: String) {
+        if self.pool.len() < POOL_SIZE {
+            buf.clear();
+            clamp_capacity(&mut buf);
+            self.pool.push(buf);
         }
     }
 
-    pub fn flush_current_buf(&mut self, mut writer: impl io::Write) {
-        write!(writer, "{}", &self.current_buf).unwrap();
+    /// Shrinks the buffers after a callback if they grew unusually large.
+    pub(crate) fn trim(&mut self) {
+        clamp_capacity(&mut self.current_buf);
+        clamp_capacity(&mut self.indent_buf);
+        clamp_capacity(&mut self.record);
+    }
+
+    /// Moves the finished lines of `current_buf` to the record.
+    pub fn flush_current_buf(&mut self) {
+        self.record.push_str(&self.current_buf);
         self.current_buf.clear();
     }
 
+    /// Writes all lines of the record with a single `write_all`, so that writers which
+    /// interleave the output of several sources never split them.
+    pub fn flush_record(&mut self, mut writer: impl io::Write) -> io::Result<()> {
+        if self.record.is_empty() {
+            return Ok(());
+        }
+        let result = writer.write_all(self.record.as_bytes());
+        self.record.clear();
+        result
+    }
+
     pub fn flush_indent_buf(&mut self) {
         self.current_buf.push_str(&self.indent_buf);
         self.indent_buf.clear();
@@ -217,21 +1804,22 @@ impl Buffers {
 
     pub(crate) fn indent_current(&mut self, indent: usize, config: &Config, style: SpanMode) {
         let prefix = config.prefix();
+        let indent_lines = config.indent_lines && !config.markdown;
 
         // Render something when wraparound occurs so the user is aware of it
-        if config.indent_lines {
+        if indent_lines {
             self.current_buf.push('\n');
 
             match style {
-                SpanMode::Close { .. } | SpanMode::PostClose => {
-                    if indent > 0 && (indent + 1) % config.wraparound == 0 {
-                        self.indent_buf.push_str(&prefix);
-                        for _ in 0..(indent % config.wraparound * config.indent_amount) {
-                            self.indent_buf.push_str(LINE_HORIZ);
-                        }
-                        self.indent_buf.push_str(LINE_OPEN);
-                        self.indent_buf.push('\n');
+                SpanMode::Close { .. } | SpanMode::PostClose
+                    if indent > 0 && (indent + 1) % config.wraparound == 0 =>
+                {
+                    self.indent_buf.push_str(&prefix);
+                    for _ in 0..(indent % config.wraparound * config.indent_amount) {
+                        self.indent_buf.push_str(config.glyphs.horizontal);
                     }
+                    self.indent_buf.push_str(config.glyphs.open);
+                    self.indent_buf.push('\n');
                 }
                 _ => {}
             }
@@ -241,8 +1829,8 @@ impl Buffers {
             &self.current_buf,
             &mut self.indent_buf,
             indent % config.wraparound,
-            config.indent_amount,
-            config.indent_lines,
+            config,
+            &mut self.indents,
             &prefix,
             style,
         );
@@ -251,17 +1839,17 @@ impl Buffers {
         self.flush_indent_buf();
 
         // Render something when wraparound occurs so the user is aware of it
-        if config.indent_lines {
+        if indent_lines {
             match style {
-                SpanMode::PreOpen { .. } | SpanMode::Open { .. } => {
-                    if indent > 0 && (indent + 1) % config.wraparound == 0 {
-                        self.current_buf.push_str(&prefix);
-                        for _ in 0..(indent % config.wraparound * config.indent_amount) {
-                            self.current_buf.push_str(LINE_HORIZ);
-                        }
-                        self.current_buf.push_str(LINE_CLOSE);
-                        self.current_buf.push('\n');
+                SpanMode::PreOpen | SpanMode::Open { .. }
+                    if indent > 0 && (indent + 1) % config.wraparound == 0 =>
+                {
+                    self.current_buf.push_str(&prefix);
+                    for _ in 0..(indent % config.wraparound * config.indent_amount) {
+                        self.current_buf.push_str(config.glyphs.horizontal);
                     }
+                    self.current_buf.push_str(config.glyphs.close);
+                    self.current_buf.push('\n');
                 }
                 _ => {}
             }
@@ -269,43 +1857,239 @@ impl Buffers {
     }
 }
 
+/// Hands out short tags to open spans.
+///
+/// Tags of closed spans are recycled, always preferring the lowest free tag, so that tags stay
+/// short even in long running processes.
+#[derive(Debug, Default)]
+pub(crate) struct SpanTags {
+    next: usize,
+    free: BTreeSet<usize>,
+}
+
+impl SpanTags {
+    pub(crate) fn allocate(&mut self) -> usize {
+        self.free.pop_first().unwrap_or_else(|| {
+            self.next += 1;
+            self.next - 1
+        })
+    }
+
+    pub(crate) fn release(&mut self, tag: usize) {
+        self.free.insert(tag);
+    }
+}
+
+/// Writes a tag such as `[a3]`: one or more letters followed by a single digit.
+pub(crate) fn write_span_tag(buf: &mut String, tag: usize) {
+    let mut letters = [0u8; 16];
+    let mut len = 0;
+    let mut n = tag / 10;
+    loop {
+        letters[len] = b'a' + (n % 26) as u8;
+        len += 1;
+        n /= 26;
+        if n == 0 {
+            break;
+        }
+        n -= 1;
+    }
+    buf.push('[');
+    for &letter in letters[..len].iter().rev() {
+        buf.push(letter as char);
+    }
+    write!(buf, "{}]", tag % 10).unwrap();
+}
+
 pub struct FmtEvent<'a> {
     pub bufs: &'a mut Buffers,
     pub comma: bool,
+    pub config: &'a Config,
+    /// Where the fields start in `current_buf`
+    pub start: usize,
+    /// The message, if it was recorded out of its place and is written by `finish`
+    pub message: Option<String>,
+    /// The span trace captured by an error field of the event
+    #[cfg(feature = "tracing-error")]
+    pub span_trace: Option<tracing_error::SpanTrace>,
 }
 
 impl<'a> Visit for FmtEvent<'a> {
+    #[cfg(feature = "tracing-error")]
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        use tracing_error::ExtractSpanTrace;
+
+        // `TracedError` exposes its span trace as its source
+        let mut source = Some(value);
+        while let (None, Some(error)) = (&self.span_trace, source) {
+            self.span_trace = error.span_trace().cloned();
+            source = error.source();
+        }
+        self.record_debug(field, &format_args!("{}", value));
+    }
+
     fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
-        let buf = &mut self.bufs.current_buf;
-        let comma = if self.comma { "," } else { "" };
-        match field.name() {
-            "message" => {
-                write!(buf, "{} {:?}", comma, value).unwrap();
-                self.comma = true;
-            }
-            // Skip fields that are actually log metadata that have already been handled
-            #[cfg(feature = "tracing-log")]
-            name if name.starts_with("log.") => {}
-            name => {
-                write!(buf, "{} {}={:?}", comma, name, value).unwrap();
-                self.comma = true;
+        // Skip fields that are actually log metadata that have already been handled
+        #[cfg(feature = "tracing-log")]
+        if field.name().starts_with("log.") {
+            return;
+        }
+        let config = self.config;
+        let in_place = match config.message_position {
+            _ if config.bracketed_event_fields => false,
+            MessagePosition::First => self.bufs.current_buf.len() == self.start,
+            MessagePosition::Last => false,
+        };
+        if field.name() == "message" && !in_place {
+            let mut message = String::new();
+            config.format_field(field, value, &mut message);
+            self.message = Some(message);
+            return;
+        }
+        write_event_field(
+            &mut self.bufs.current_buf,
+            config,
+            &mut self.comma,
+            field.name(),
+            |buf| config.format_field(field, value, buf),
+        );
+    }
+}
+
+impl FmtEvent<'_> {
+    /// Brackets the fields if enabled and writes the message if it was recorded out of its place.
+    pub fn finish(&mut self) {
+        let message = self.message.take();
+        finish_event_fields(
+            &mut self.bufs.current_buf,
+            self.start,
+            self.config,
+            &mut self.comma,
+            message.as_deref(),
+        );
+    }
+}
+
+/// Brackets the fields of an event written to `buf` from `start` on, if enabled, and writes
+/// its `message` that was left out in front of or behind them.
+pub(crate) fn finish_event_fields(
+    buf: &mut String,
+    start: usize,
+    config: &Config,
+    comma: &mut bool,
+    message: Option<&str>,
+) {
+    let bracketed = config.bracketed_event_fields && !config.markdown;
+    let has_fields = buf.len() > start;
+    if bracketed && has_fields {
+        // Behind the space in front of the first field
+        buf.insert(start + 1, '{');
+        buf.push('}');
+    }
+    let Some(message) = message else {
+        return;
+    };
+    match config.message_position {
+        MessagePosition::First => {
+            let mut head = String::new();
+            write_event_field(&mut head, config, &mut false, "message", |buf| {
+                buf.push_str(message)
+            });
+            if has_fields && !bracketed && !config.markdown {
+                head.push(',');
             }
+            buf.insert_str(start, &head);
+        }
+        MessagePosition::Last => {
+            let mut separate = *comma && !bracketed;
+            write_event_field(buf, config, &mut separate, "message", |buf| {
+                buf.push_str(message)
+            });
+        }
+    }
+    *comma = true;
+}
+
+/// Writes a field of an event, whose value is appended by `value`. `comma` tracks whether
+/// the next field needs to be separated from the previous ones.
+pub(crate) fn write_event_field(
+    buf: &mut String,
+    config: &Config,
+    comma: &mut bool,
+    name: &str,
+    value: impl FnOnce(&mut String),
+) {
+    let separator = if *comma { "," } else { "" };
+    match name {
+        "message" => {
+            write!(buf, "{} ", if config.markdown { "" } else { separator }).unwrap();
+            value(buf);
+            *comma = true;
+        }
+        name if config.markdown => {
+            buf.push(' ');
+            write_code(buf, |buf| {
+                buf.push_str(name);
+                buf.push('=');
+                value(buf);
+            });
         }
+        name => {
+            write!(buf, "{} {}=", separator, name).unwrap();
+            value(buf);
+            *comma = true;
+        }
+    }
+}
+
+/// Writes the text written by `write` as inline Markdown code.
+pub(crate) fn write_code(buf: &mut String, write: impl FnOnce(&mut String)) {
+    let start = buf.len();
+    buf.push('`');
+    write(buf);
+    if buf[start + 1..].contains('`') {
+        // A longer run of backticks delimits code containing backticks
+        buf.insert_str(start + 1, "` ");
+        buf.push_str(" ``");
+    } else {
+        buf.push('`');
     }
 }
 
-pub struct ColorLevel<'a>(pub &'a Level);
+pub struct ColorLevel<'a>(pub &'a Level, pub &'a Theme);
 
 impl<'a> fmt::Display for ColorLevel<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self.0 {
-            Level::TRACE => Color::Purple.bold().paint("TRACE"),
-            Level::DEBUG => Color::Blue.bold().paint("DEBUG"),
-            Level::INFO => Color::Green.bold().paint(" INFO"),
-            Level::WARN => Color::Rgb(252, 234, 160).bold().paint(" WARN"), // orange
-            Level::ERROR => Color::Red.bold().paint("ERROR"),
-        }
-        .fmt(f)
+        let text = match *self.0 {
+            Level::TRACE => "TRACE",
+            Level::DEBUG => "DEBUG",
+            Level::INFO => " INFO",
+            Level::WARN => " WARN",
+            Level::ERROR => "ERROR",
+        };
+        paint(self.1.level(self.0), text).fmt(f)
+    }
+}
+
+/// The name of a level, as `Level`'s `Display` would write it.
+pub(crate) fn level_name(level: &Level) -> &'static str {
+    match *level {
+        Level::TRACE => "TRACE",
+        Level::DEBUG => "DEBUG",
+        Level::INFO => "INFO",
+        Level::WARN => "WARN",
+        Level::ERROR => "ERROR",
+    }
+}
+
+/// The single letter abbreviation of a level.
+pub(crate) fn short_level(level: &Level) -> &'static str {
+    match *level {
+        Level::TRACE => "T",
+        Level::DEBUG => "D",
+        Level::INFO => "I",
+        Level::WARN => "W",
+        Level::ERROR => "E",
     }
 }
 
@@ -325,62 +2109,167 @@ pub(crate) fn write_span_mode(buf: &mut String, style: SpanMode) {
     buf.push_str(": ")
 }
 
-fn indent_block_with_lines(
-    lines: &[&str],
-    buf: &mut String,
-    indent: usize,
-    // width of one level of indent
-    indent_amount: usize,
-    prefix: &str,
-    style: SpanMode,
-) {
-    let indent_spaces = indent * indent_amount;
+/// The indentation in front of the lines of a block, without the thread prefix.
+#[derive(Debug)]
+struct Indent {
+    /// In front of the first line, which may connect to the parent's guide
+    first: String,
+    /// In front of the other lines
+    rest: String,
+}
 
-    if lines.is_empty() {
-        return;
-    } else if indent_spaces == 0 {
-        for line in lines {
-            buf.push_str(prefix);
-            // The first indent is special, we only need to print open/close and nothing else
-            if indent == 0 {
-                match style {
-                    SpanMode::Open { .. } => buf.push_str(LINE_OPEN),
-                    SpanMode::Retrace { .. } => buf.push_str(LINE_OPEN),
-                    SpanMode::Close { .. } => buf.push_str(LINE_CLOSE),
-                    SpanMode::PreOpen { .. } | SpanMode::PostClose => {}
-                    SpanMode::Event => {}
-                }
-            }
-            buf.push_str(line);
-            buf.push('\n');
+/// Indentation strings drawn before, by depth and style.
+#[derive(Debug, Default)]
+pub(crate) struct IndentCache {
+    /// The options the cached indentation was drawn with, as this cache may be shared by
+    /// layers with different options
+    options: Option<(bool, bool, usize, Glyphs, bool, bool)>,
+    indents: HashMap<(usize, SpanMode), Indent>,
+}
+
+impl IndentCache {
+    fn get(&mut self, indent: usize, config: &Config, style: SpanMode) -> &Indent {
+        let gradient = config.guide_gradient && config.ansi && config.indent_lines;
+        let options = (
+            config.markdown,
+            config.indent_lines,
+            config.indent_amount,
+            config.glyphs,
+            gradient,
+            config.basic_colors,
+        );
+        if self.options != Some(options) {
+            self.options = Some(options);
+            self.indents.clear();
         }
+        self.indents.entry((indent, style)).or_insert_with(|| {
+            let mut drawn = draw_indent(indent, config, style);
+            if gradient && !config.markdown {
+                let style = if config.basic_colors {
+                    basic_guide_style(indent)
+                } else {
+                    guide_style(indent, terminal::supports_truecolor())
+                };
+                paint_guides(&mut drawn.first, style);
+                paint_guides(&mut drawn.rest, style);
+            }
+            drawn
+        })
+    }
+}
+
+/// The color of the guides at `depth`, rotating through the hues by depth. Without
+/// `truecolor` the nearest color of the 256 color palette is used.
+fn guide_style(depth: usize, truecolor: bool) -> Style {
+    const HUE_STEP: usize = 25;
+    let hue = (180 + depth * HUE_STEP) % 360;
+    let (r, g, b) = hsv_to_rgb(hue as f32, 0.6, 0.9);
+    if truecolor {
+        Color::Rgb(r, g, b).normal()
+    } else {
+        // The 6×6×6 color cube starts at 16
+        let level = |c: u8| (u16::from(c) * 5 + 127) / 255;
+        let index = 16 + 36 * level(r) + 6 * level(g) + level(b);
+        Color::Fixed(index as u8).normal()
+    }
+}
+
+/// The color of the guides at `depth` with only the basic ANSI colors, rotating through the
+/// same hues as [`guide_style`].
+fn basic_guide_style(depth: usize) -> Style {
+    const HUES: [Color; 6] = [
+        Color::Cyan,
+        Color::Blue,
+        Color::Purple,
+        Color::Red,
+        Color::Yellow,
+        Color::Green,
+    ];
+    HUES[depth % HUES.len()].normal()
+}
+
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (u8, u8, u8) {
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let channel = |c: f32| ((c + m) * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+/// Paints the guides of an indentation, leaving the leading spaces alone.
+fn paint_guides(indent: &mut String, style: Style) {
+    let start = indent.len() - indent.trim_start_matches(' ').len();
+    let end = indent.trim_end_matches(' ').len();
+    if start >= end {
         return;
     }
+    let painted = format!("{}", paint(style, &indent[start..end]));
+    indent.replace_range(start..end, &painted);
+}
 
-    let mut s = String::with_capacity(indent_spaces + prefix.len());
-    s.push_str(prefix);
+fn draw_indent(indent: usize, config: &Config, style: SpanMode) -> Indent {
+    let indent_amount = config.indent_amount;
+    let indent_spaces = indent * indent_amount;
+    if config.markdown {
+        // Markdown only nests lists that are indented by at least two spaces
+        let indent = "  ".repeat(indent);
+        return Indent {
+            first: format!("{}- ", indent),
+            rest: format!("{}  ", indent),
+        };
+    }
+    if !config.indent_lines {
+        let indent = " ".repeat(indent_spaces + 1);
+        return Indent {
+            first: indent.clone(),
+            rest: indent,
+        };
+    }
+    let glyphs = &config.glyphs;
 
-    for _ in 0..(indent_spaces - indent_amount) {
-        s.push(' ');
+    if indent_spaces == 0 {
+        // The first indent is special, we only need to print open/close and nothing else
+        let glyph = match style {
+            _ if indent != 0 => "",
+            SpanMode::Open { .. } => glyphs.open,
+            SpanMode::Retrace { .. } => glyphs.open,
+            SpanMode::Close { .. } => glyphs.close,
+            SpanMode::PreOpen | SpanMode::PostClose => "",
+            SpanMode::Event => "",
+        };
+        return Indent {
+            first: glyph.to_owned(),
+            rest: glyph.to_owned(),
+        };
     }
 
+    let s = " ".repeat(indent_spaces - indent_amount);
+
     // draw branch
-    buf.push_str(&s);
+    let mut buf = s.clone();
 
     match style {
         SpanMode::PreOpen => {
-            buf.push(LINE_OPEN2);
+            buf.push_str(glyphs.open_corner);
             for _ in 1..(indent_amount / 2) {
-                buf.push_str(LINE_HORIZ);
+                buf.push_str(glyphs.horizontal);
             }
-            buf.push_str(LINE_OPEN);
+            buf.push_str(glyphs.open);
         }
         SpanMode::Open { verbose: false } | SpanMode::Retrace { verbose: false } => {
-            buf.push(LINE_OPEN2);
+            buf.push_str(glyphs.open_corner);
             for _ in 1..indent_amount {
-                buf.push_str(LINE_HORIZ);
+                buf.push_str(glyphs.horizontal);
             }
-            buf.push_str(LINE_OPEN);
+            buf.push_str(glyphs.open);
         }
         SpanMode::Open { verbose: true } | SpanMode::Retrace { verbose: true } => {
             buf.push(' ');
@@ -389,24 +2278,24 @@ fn indent_block_with_lines(
             }
             // We don't have the space for fancy rendering at single space indent.
             if indent_amount > 1 {
-                buf.push(LINE_OPEN2);
+                buf.push_str(glyphs.open_corner);
             }
             for _ in (indent_amount / 2)..(indent_amount - 1) {
-                buf.push_str(LINE_HORIZ);
+                buf.push_str(glyphs.horizontal);
             }
             // We don't have the space for fancy rendering at single space indent.
             if indent_amount > 1 {
-                buf.push_str(LINE_OPEN);
+                buf.push_str(glyphs.open);
             } else {
                 buf.push(' ');
             }
         }
         SpanMode::Close { verbose: false } => {
-            buf.push(LINE_CLOSE2);
+            buf.push_str(glyphs.close_corner);
             for _ in 1..indent_amount {
-                buf.push_str(LINE_HORIZ);
+                buf.push_str(glyphs.horizontal);
             }
-            buf.push_str(LINE_CLOSE);
+            buf.push_str(glyphs.close);
         }
         SpanMode::Close { verbose: true } => {
             buf.push(' ');
@@ -415,68 +2304,70 @@ fn indent_block_with_lines(
             }
             // We don't have the space for fancy rendering at single space indent.
             if indent_amount > 1 {
-                buf.push(LINE_CLOSE2);
+                buf.push_str(glyphs.close_corner);
             }
             for _ in (indent_amount / 2)..(indent_amount - 1) {
-                buf.push_str(LINE_HORIZ);
+                buf.push_str(glyphs.horizontal);
             }
             // We don't have the space for fancy rendering at single space indent.
             if indent_amount > 1 {
-                buf.push_str(LINE_CLOSE);
+                buf.push_str(glyphs.close);
             } else {
                 buf.push(' ');
             }
         }
         SpanMode::PostClose => {
-            buf.push(LINE_CLOSE2);
+            buf.push_str(glyphs.close_corner);
             for _ in 1..(indent_amount / 2) {
-                buf.push_str(LINE_HORIZ);
+                buf.push_str(glyphs.horizontal);
             }
-            buf.push_str(LINE_CLOSE);
+            buf.push_str(glyphs.close);
         }
-        SpanMode::Event => {
-            buf.push_str(LINE_BRANCH);
+        SpanMode::Event => match glyphs.event {
+            Some(event) => {
+                buf.push_str(event);
+                // keep the event aligned with its continuation lines
+                let width = visible_width(event);
+                for _ in width..indent_amount {
+                    buf.push(' ');
+                }
+            }
+            None => {
+                buf.push_str(glyphs.branch);
 
-            // add `indent_amount - 1` horizontal lines before the span/event
-            for _ in 0..(indent_amount - 1) {
-                buf.push_str(LINE_HORIZ);
+                // add `indent_amount - 1` horizontal lines before the span/event
+                for _ in 0..(indent_amount - 1) {
+                    buf.push_str(glyphs.horizontal);
+                }
             }
-        }
+        },
     }
-    buf.push_str(lines[0]);
-    buf.push('\n');
 
     // add the rest of the indentation, since we don't want to draw horizontal lines
     // for subsequent lines
-    for i in 0..indent_amount {
-        if i % indent_amount == 0 {
-            s.push_str(LINE_VERT);
-        } else {
-            s.push(' ');
+    let mut rest = s;
+    match glyphs.continuation {
+        Some(continuation) => rest.push_str(continuation),
+        None => {
+            rest.push_str(glyphs.vertical);
+            for _ in 1..indent_amount {
+                rest.push(' ');
+            }
         }
     }
 
-    // add all of the actual content, with each line preceded by the indent string
-    for line in &lines[1..] {
-        buf.push_str(&s);
-        buf.push_str(line);
-        buf.push('\n');
-    }
+    Indent { first: buf, rest }
 }
 
 fn indent_block(
     block: &str,
     buf: &mut String,
     mut indent: usize,
-    indent_amount: usize,
-    indent_lines: bool,
+    config: &Config,
+    indents: &mut IndentCache,
     prefix: &str,
     style: SpanMode,
 ) {
-    let lines: Vec<&str> = block.lines().collect();
-    let indent_spaces = indent * indent_amount;
-    buf.reserve(block.len() + (lines.len() * indent_spaces));
-
     // The PreOpen and PostClose need to match up with the indent of the entered child span one more indent
     // deep
     match style {
@@ -486,16 +2377,32 @@ fn indent_block(
         _ => (),
     }
 
-    if indent_lines {
-        indent_block_with_lines(&lines, buf, indent, indent_amount, prefix, style);
+    let indent = indents.get(indent, config, style);
+    let mut lines = block.lines();
+    let Some(mut first) = lines.next() else {
+        return;
+    };
+    if config.markdown {
+        // The list marker already separates the item from the indentation
+        first = first.trim_start();
+    }
+    // Most blocks are a single line
+    buf.reserve(prefix.len() + indent.first.len() + block.len() + 1);
+    buf.push_str(prefix);
+    buf.push_str(&indent.first);
+    buf.push_str(first);
+    buf.push('\n');
+    let blank;
+    let prefix = if config.continuation_thread_prefix {
+        prefix
     } else {
-        let indent_str = String::from(" ").repeat(indent_spaces);
-        for line in lines {
-            buf.push_str(prefix);
-            buf.push(' ');
-            buf.push_str(&indent_str);
-            buf.push_str(line);
-            buf.push('\n');
-        }
+        blank = " ".repeat(visible_width(prefix));
+        &blank
+    };
+    for line in lines {
+        buf.push_str(prefix);
+        buf.push_str(&indent.rest);
+        buf.push_str(line);
+        buf.push('\n');
     }
 }
@@ -0,0 +1,209 @@
+//! Latency histograms of spans, per callsite.
+//!
+//! Unlike the [statistics](crate::stats) of spans, histograms keep the distribution of the
+//! times between opening and closing spans, so percentiles can be read from them. Durations
+//! are sorted into log-linear buckets, which keeps the memory per callsite bounded and the
+//! error of a percentile below 6.25%.
+//!
+//! ```
+//! use tracing_subscriber::{layer::SubscriberExt, Registry};
+//! use tracing_tree::{histogram::Histograms, HierarchicalLayer};
+//!
+//! let histograms = Histograms::new();
+//! let layer = HierarchicalLayer::default().with_latency_histograms(histograms.clone());
+//! let subscriber = Registry::default().with(layer);
+//! // ... install the subscriber and do some work ...
+//!
+//! if let Some(request) = histograms.get("request") {
+//!     println!("p50 {:?}, p99 {:?}", request.percentile(50.0), request.percentile(99.0));
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fmt::Write as _,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use tracing_core::{callsite, Metadata};
+
+/// The number of buckets per power of two, values below are counted exactly.
+const SUB_BUCKETS: u64 = 16;
+const SUB_BUCKET_BITS: u32 = SUB_BUCKETS.trailing_zeros();
+
+/// A handle to the latency histograms collected by a [`HierarchicalLayer`].
+///
+/// Clones share the same histograms, so a clone can be handed to
+/// [`HierarchicalLayer::with_latency_histograms`] while the original is kept to read them.
+///
+/// [`HierarchicalLayer`]: crate::HierarchicalLayer
+/// [`HierarchicalLayer::with_latency_histograms`]: crate::HierarchicalLayer::with_latency_histograms
+#[derive(Debug, Clone, Default)]
+pub struct Histograms {
+    inner: Arc<Mutex<HashMap<callsite::Identifier, Histogram>>>,
+}
+
+/// The distribution of the durations of all closed spans of one callsite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Histogram {
+    /// The name of the span.
+    pub name: &'static str,
+    /// The target of the span.
+    pub target: &'static str,
+    /// How many spans have been closed.
+    pub count: u64,
+    /// The longest time between opening and closing a span.
+    pub max: Duration,
+    /// The number of durations per bucket, only as long as the largest bucket in use
+    buckets: Vec<u64>,
+}
+
+impl Histogram {
+    fn new(name: &'static str, target: &'static str) -> Self {
+        Self {
+            name,
+            target,
+            count: 0,
+            max: Duration::ZERO,
+            buckets: Vec::new(),
+        }
+    }
+
+    /// The duration that `percentile` percent of the spans did not exceed, e.g. `99.0` for
+    /// the p99 latency. Returns zero if no span has been closed.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * self.count as f64).ceil() as u64;
+        let rank = rank.max(1);
+        let mut seen = 0;
+        for (index, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let nanos = Duration::from_nanos(bucket_max(index));
+                return nanos.min(self.max);
+            }
+        }
+        self.max
+    }
+
+    fn record(&mut self, elapsed: Duration) {
+        let index = bucket(u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX));
+        if self.buckets.len() <= index {
+            self.buckets.resize(index + 1, 0);
+        }
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.max = self.max.max(elapsed);
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        if self.buckets.len() < other.buckets.len() {
+            self.buckets.resize(other.buckets.len(), 0);
+        }
+        for (count, other) in self.buckets.iter_mut().zip(&other.buckets) {
+            *count += other;
+        }
+        self.count += other.count;
+        self.max = self.max.max(other.max);
+    }
+}
+
+/// The bucket counting `nanos`.
+fn bucket(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS {
+        return nanos as usize;
+    }
+    let exponent = 63 - nanos.leading_zeros();
+    let shift = exponent - SUB_BUCKET_BITS;
+    let sub_bucket = (nanos >> shift) - SUB_BUCKETS;
+    ((u64::from(shift) + 1) * SUB_BUCKETS + sub_bucket) as usize
+}
+
+/// The largest number of nanoseconds counted by the bucket at `index`.
+fn bucket_max(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let sub_bucket = index % SUB_BUCKETS;
+    let min = (SUB_BUCKETS + sub_bucket) << shift;
+    min + ((1 << shift) - 1)
+}
+
+impl Histograms {
+    /// Creates an empty set of histograms.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The histograms of every callsite seen so far, with the slowest p99 first.
+    pub fn snapshot(&self) -> Vec<Histogram> {
+        let mut histograms: Vec<_> = self.lock().values().cloned().collect();
+        histograms.sort_by(|a, b| {
+            b.percentile(99.0)
+                .cmp(&a.percentile(99.0))
+                .then(a.name.cmp(b.name))
+        });
+        histograms
+    }
+
+    /// The histograms of all callsites with the given span name combined.
+    pub fn get(&self, name: &str) -> Option<Histogram> {
+        let inner = self.lock();
+        let mut matching = inner.values().filter(|histogram| histogram.name == name);
+        let mut histogram = matching.next()?.clone();
+        for other in matching {
+            histogram.merge(other);
+        }
+        Some(histogram)
+    }
+
+    /// Forgets all durations recorded so far.
+    pub fn reset(&self) {
+        self.lock().clear();
+    }
+
+    pub(crate) fn record(&self, metadata: &'static Metadata<'static>, elapsed: Duration) {
+        let mut inner = self.lock();
+        inner
+            .entry(metadata.callsite())
+            .or_insert_with(|| Histogram::new(metadata.name(), metadata.target()))
+            .record(elapsed);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<callsite::Identifier, Histogram>> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub(crate) fn render(&self, buf: &mut String) {
+        let histograms = self.snapshot();
+        if histograms.is_empty() {
+            return;
+        }
+        writeln!(
+            buf,
+            "  span latencies:\n  {:>8} {:>10} {:>10} {:>10} {:>10} name",
+            "count", "p50", "p90", "p99", "max"
+        )
+        .unwrap();
+        for histogram in histograms {
+            writeln!(
+                buf,
+                "  {:>8} {:>10.2?} {:>10.2?} {:>10.2?} {:>10.2?} {}",
+                histogram.count,
+                histogram.percentile(50.0),
+                histogram.percentile(90.0),
+                histogram.percentile(99.0),
+                histogram.max,
+                histogram.name
+            )
+            .unwrap();
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Writing and reading JSON by hand, for the machine readable outputs.
+
+use std::{fmt::Write as _, iter::Peekable, str::CharIndices};
+
+/// Writes `s` as a JSON string.
+pub(crate) fn string(buf: &mut String, s: &str) {
+    buf.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if c.is_control() => write!(buf, "\\u{:04x}", c as u32).unwrap(),
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+}
+
+/// A JSON value, limited to what the outputs of this crate contain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Value {
+    String(String),
+    Number(u64),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// The member `key` of an object.
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Parses `s` as a single JSON value. Only strings, unsigned integers, arrays and objects are
+/// supported.
+pub(crate) fn parse(s: &str) -> Result<Value, &'static str> {
+    let mut parser = Parser {
+        chars: s.char_indices().peekable(),
+    };
+    let value = parser.value()?;
+    parser.whitespace();
+    match parser.chars.next() {
+        None => Ok(value),
+        Some(_) => Err("trailing characters after the value"),
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn whitespace(&mut self) {
+        while let Some((_, ' ' | '\t' | '\n' | '\r')) = self.chars.peek() {
+            self.chars.next();
+        }
+    }
+
+    fn next(&mut self) -> Result<char, &'static str> {
+        self.chars
+            .next()
+            .map(|(_, c)| c)
+            .ok_or("unexpected end of input")
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), &'static str> {
+        self.whitespace();
+        if self.next()? == expected {
+            Ok(())
+        } else {
+            Err("unexpected character")
+        }
+    }
+
+    /// Whether a list continues after an element, consuming the separator or `end`.
+    fn more(&mut self, end: char) -> Result<bool, &'static str> {
+        self.whitespace();
+        match self.next()? {
+            ',' => Ok(true),
+            c if c == end => Ok(false),
+            _ => Err("expected a comma"),
+        }
+    }
+
+    /// Consumes `end` if the list is empty.
+    fn empty(&mut self, end: char) -> bool {
+        self.whitespace();
+        match self.chars.peek() {
+            Some(&(_, c)) if c == end => {
+                self.chars.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, &'static str> {
+        self.whitespace();
+        match self.chars.peek().map(|&(_, c)| c) {
+            Some('"') => self.string().map(Value::String),
+            Some('0'..='9') => self.number().map(Value::Number),
+            Some('[') => {
+                self.chars.next();
+                let mut values = Vec::new();
+                if !self.empty(']') {
+                    loop {
+                        values.push(self.value()?);
+                        if !self.more(']')? {
+                            break;
+                        }
+                    }
+                }
+                Ok(Value::Array(values))
+            }
+            Some('{') => {
+                self.chars.next();
+                let mut members = Vec::new();
+                if !self.empty('}') {
+                    loop {
+                        self.whitespace();
+                        let key = self.string()?;
+                        self.expect(':')?;
+                        members.push((key, self.value()?));
+                        if !self.more('}')? {
+                            break;
+                        }
+                    }
+                }
+                Ok(Value::Object(members))
+            }
+            Some(_) => Err("unsupported value"),
+            None => Err("unexpected end of input"),
+        }
+    }
+
+    fn number(&mut self) -> Result<u64, &'static str> {
+        let mut n: u64 = 0;
+        while let Some(&(_, c @ '0'..='9')) = self.chars.peek() {
+            self.chars.next();
+            n = n
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(u64::from(c as u8 - b'0')))
+                .ok_or("number out of range")?;
+        }
+        Ok(n)
+    }
+
+    fn string(&mut self) -> Result<String, &'static str> {
+        if self.next()? != '"' {
+            return Err("expected a string");
+        }
+        let mut s = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Ok(s),
+                '\\' => match self.next()? {
+                    '"' => s.push('"'),
+                    '\\' => s.push('\\'),
+                    '/' => s.push('/'),
+                    'b' => s.push('\u{8}'),
+                    'f' => s.push('\u{c}'),
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    'u' => {
+                        let mut c = self.hex()?;
+                        if (0xdc00..0xe000).contains(&c) {
+                            return Err("unpaired surrogate");
+                        }
+                        if (0xd800..0xdc00).contains(&c) {
+                            // The high half of a surrogate pair, the low half follows
+                            if self.next()? != '\\' || self.next()? != 'u' {
+                                return Err("unpaired surrogate");
+                            }
+                            let low = self.hex()?;
+                            if !(0xdc00..0xe000).contains(&low) {
+                                return Err("unpaired surrogate");
+                            }
+                            c = 0x10000 + ((c - 0xd800) << 10) + (low - 0xdc00);
+                        }
+                        s.push(char::from_u32(c).ok_or("invalid unicode escape")?);
+                    }
+                    _ => return Err("invalid escape"),
+                },
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn hex(&mut self) -> Result<u32, &'static str> {
+        let mut c = 0;
+        for _ in 0..4 {
+            let digit = self.next()?.to_digit(16).ok_or("invalid unicode escape")?;
+            c = c * 16 + digit;
+        }
+        Ok(c)
+    }
+}
@@ -1,26 +1,87 @@
+mod builder;
+#[cfg(feature = "chrome")]
+pub mod chrome;
+mod clock;
+#[cfg(feature = "csv")]
+mod csv;
+mod fields;
+pub mod flame;
 pub(crate) mod format;
+pub mod histogram;
+mod json;
+pub mod mermaid;
+pub mod metrics;
+mod mirror;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+mod output;
+mod paint;
+#[cfg(feature = "perfetto")]
+pub mod perfetto;
+pub mod render;
+pub mod section;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+mod summary;
+mod terminal;
+pub mod testing;
 pub mod time;
+pub mod tree;
+pub mod writer;
 
-use crate::time::FormatTime;
-use format::{write_span_mode, Buffers, ColorLevel, Config, FmtEvent, SpanMode};
+pub use builder::{ConfigError, HierarchicalLayerBuilder};
+pub use format::{
+    AnsiMode, Columns, Config, Divider, Glyphs, MessagePosition, Newlines, Separator,
+    SeverityPrefix, Theme, TimeDisplay, WriteErrorPolicy,
+};
+pub use nu_ansi_term;
+#[cfg(feature = "sqlite")]
+pub use rusqlite;
+
+use crate::{
+    clock::Instant,
+    fields::Fields,
+    flame::FoldedStacks,
+    histogram::Histograms,
+    metrics::{MetricsEvent, MetricsKind},
+    mirror::Mirror,
+    output::Output,
+    paint::paint,
+    section::SectionMarker,
+    stats::SpanStats,
+    summary::Summary,
+    time::{FormatTime, TimeContext},
+    writer::LevelWriters,
+};
+use format::{
+    level_name, mask_digits, short_level, syslog_priority, visible_width, write_code,
+    write_span_mode, write_span_tag, Buffers, ColorLevel, FmtEvent, Hook, SpanMode, SpanTags,
+};
 
-use nu_ansi_term::{Color, Style};
+use nu_ansi_term::Style;
 use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    borrow::Cow,
+    cell::Cell,
+    collections::VecDeque,
     fmt::{self, Write},
-    io::{self, IsTerminal},
+    io,
     iter::Fuse,
     mem,
+    ops::{Deref, DerefMut},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Mutex,
+        Arc, Mutex, MutexGuard,
     },
-    thread::LocalKey,
-    time::Instant,
+    thread::{self, LocalKey, ThreadId},
+    time::Duration,
 };
 use tracing_core::{
     field::{Field, Visit},
     span::{Attributes, Id},
-    Event, Subscriber,
+    subscriber::Interest,
+    Event, Level, LevelFilter, Metadata, Subscriber,
 };
 #[cfg(feature = "tracing-log")]
 use tracing_log::NormalizeEvent;
@@ -30,28 +91,89 @@ use tracing_subscriber::{
     registry::{LookupSpan, ScopeFromRoot, SpanRef},
 };
 
+/// How many events `quiet_until_error` holds back per span. Older held events are dropped and
+/// only counted, so that long-lived spans don't grow without bounds.
+const MAX_HELD_EVENTS: usize = 1024;
+
 // Span extension data
 pub(crate) struct Data {
     start: Instant,
-    kvs: Vec<(&'static str, String)>,
+    kvs: Fields,
     written: bool,
+    /// The thread on which activity of this span was last observed.
+    thread: ThreadId,
+    /// The short tag assigned to this span if `span_tags` is enabled.
+    tag: Option<usize>,
+    /// How often this span has been entered.
+    enter_count: usize,
+    /// The number of events which occurred directly inside of this span.
+    events: usize,
+    /// Rendered events held back by `quiet_until_error`, the most recent `MAX_HELD_EVENTS`.
+    held: VecDeque<String>,
+    /// The number of held events dropped to make room for more recent ones.
+    held_dropped: usize,
+    /// When the last event directly inside of this span occurred.
+    last_event: Instant,
+    /// The time spent in closed child spans, tracked for folded stacks.
+    children: Duration,
+    /// The slowest closed child span and the names of the spans on its critical path, from
+    /// the innermost span up to the child.
+    critical: Option<(Duration, Vec<Cow<'static, str>>)>,
+    /// The shape of the closed child spans, tracked for tree stats.
+    shape: TreeShape,
+    /// The wall-clock time the span was opened at, formatted by the timer, if
+    /// `span_time_range` is enabled.
+    opened_at: Option<String>,
+}
+
+/// The shape of the closed spans below a span.
+#[derive(Debug, Default, Clone, Copy)]
+struct TreeShape {
+    spans: usize,
+    /// The deepest nesting, with zero for no spans at all
+    depth: usize,
+    events: usize,
 }
 
 impl Data {
-    pub fn new(attrs: &Attributes<'_>, written: bool) -> Self {
+    pub fn new(attrs: &Attributes<'_>, written: bool, config: &Config) -> Self {
+        let start = Instant::now();
         let mut span = Self {
-            start: Instant::now(),
-            kvs: Vec::new(),
+            start,
+            kvs: Fields::default(),
             written,
+            thread: thread::current().id(),
+            tag: None,
+            enter_count: 0,
+            events: 0,
+            held: VecDeque::new(),
+            held_dropped: 0,
+            last_event: start,
+            children: Duration::ZERO,
+            critical: None,
+            shape: TreeShape::default(),
+            opened_at: None,
         };
-        attrs.record(&mut span);
+        attrs.record(&mut SpanFields {
+            fields: &mut span.kvs,
+            config,
+        });
         span
     }
 }
 
-impl Visit for Data {
+/// Records the fields of a span into its [`Data`].
+struct SpanFields<'a> {
+    fields: &'a mut Fields,
+    config: &'a Config,
+}
+
+impl Visit for SpanFields<'_> {
     fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
-        self.kvs.push((field.name(), format!("{:?}", value)))
+        let config = self.config;
+        self.fields.push(field.name(), config.field_budget, |buf| {
+            config.format_field(field, value, buf)
+        });
     }
 }
 
@@ -61,10 +183,13 @@ where
     W: for<'writer> MakeWriter<'writer> + 'static,
     FT: FormatTime,
 {
-    make_writer: W,
+    output: Output<W>,
     bufs: Mutex<Buffers>,
     config: Config,
     timer: FT,
+    /// The subscriber this layer is part of, to look up the OpenTelemetry context of spans
+    #[cfg(feature = "opentelemetry")]
+    dispatch: std::sync::OnceLock<tracing_core::dispatcher::WeakDispatch>,
 }
 
 impl Default for HierarchicalLayer {
@@ -75,17 +200,59 @@ impl Default for HierarchicalLayer {
 
 impl HierarchicalLayer<fn() -> io::Stderr> {
     pub fn new(indent_amount: usize) -> Self {
-        let ansi = io::stderr().is_terminal();
         let config = Config {
-            ansi,
+            ansi_mode: AnsiMode::Auto,
             indent_amount,
             ..Default::default()
-        };
+        }
+        .detect_ansi(Some(terminal::supports_colors(&io::stderr())));
         Self {
-            make_writer: io::stderr,
+            output: Output::new(io::stderr),
             bufs: Mutex::new(Buffers::new()),
             config,
             timer: (),
+            #[cfg(feature = "opentelemetry")]
+            dispatch: Default::default(),
+        }
+    }
+
+    /// A preset for reading output in a terminal: indent lines, colors (if stderr is a
+    /// terminal), bracketed fields and reprinting of the parent span on entry and exit.
+    pub fn pretty() -> Self {
+        Self::new(2)
+            .with_indent_lines(true)
+            .with_bracketed_fields(true)
+            .with_verbose_entry(true)
+            .with_verbose_exit(true)
+    }
+
+    /// A preset for dense output: whitespace indentation and single letter levels.
+    pub fn compact() -> Self {
+        Self::new(2).with_short_levels(true)
+    }
+
+    /// A preset for CI logs: no colors, ASCII indent lines and a fixed width of 80 columns
+    /// instead of the width of the terminal, so that the layout does not depend on the machine
+    /// running the job.
+    pub fn ci() -> Self {
+        Self::new(2)
+            .with_ansi(false)
+            .with_indent_lines(true)
+            .with_glyphs(Glyphs::ascii())
+            .with_width(80)
+    }
+
+    /// Builds a layer configured by the `TRACING_TREE` environment variable, so binaries can
+    /// expose tuning of the tree output without wiring every option to a flag.
+    ///
+    /// The variable holds a comma separated list of options, e.g.
+    /// `TRACING_TREE=indent=4,lines,targets,retrace,deferred`. See
+    /// [`with_options`](Self::with_options) for the accepted options.
+    pub fn from_env() -> Self {
+        let layer = Self::default();
+        match std::env::var("TRACING_TREE") {
+            Ok(options) => layer.with_options(&options),
+            Err(_) => layer,
         }
     }
 }
@@ -95,7 +262,222 @@ where
     W: for<'writer> MakeWriter<'writer> + 'static,
     FT: FormatTime,
 {
+    /// Applies a comma separated list of options, as accepted by [`from_env`](Self::from_env).
+    ///
+    /// Flags are given as `name`, `name=true` or `name=false`, numeric options as `name=4`.
+    /// Unknown options and invalid values are ignored.
+    ///
+    /// | option | builder method |
+    /// |---|---|
+    /// | `indent=N` | [`with_indent_amount`](Self::with_indent_amount) |
+    /// | `wraparound=N` | [`with_wraparound`](Self::with_wraparound) |
+    /// | `width=N` | [`with_width`](Self::with_width) |
+    /// | `retrace_depth=N` | [`with_retrace_depth`](Self::with_retrace_depth) |
+    /// | `max_events_per_span=N` | [`with_max_events_per_span`](Self::with_max_events_per_span) |
+    /// | `field_budget_bytes=N` | [`with_field_budget_bytes`](Self::with_field_budget_bytes) |
+    /// | `wall_clock_width=N` | [`with_wall_clock_width`](Self::with_wall_clock_width) |
+    /// | `elapsed_width=N` | [`with_elapsed_width`](Self::with_elapsed_width) |
+    /// | `ansi` | [`with_ansi`](Self::with_ansi) |
+    /// | `ansi=auto` | [`with_ansi_mode`](Self::with_ansi_mode) with [`AnsiMode::Auto`] |
+    /// | `lines` | [`with_indent_lines`](Self::with_indent_lines) |
+    /// | `targets` | [`with_targets`](Self::with_targets) |
+    /// | `thread_ids` | [`with_thread_ids`](Self::with_thread_ids) |
+    /// | `thread_names` | [`with_thread_names`](Self::with_thread_names) |
+    /// | `continuation_thread_prefix` | [`with_continuation_thread_prefix`](Self::with_continuation_thread_prefix) |
+    /// | `verbose_entry` | [`with_verbose_entry`](Self::with_verbose_entry) |
+    /// | `verbose_exit` | [`with_verbose_exit`](Self::with_verbose_exit) |
+    /// | `retrace` | [`with_span_retrace`](Self::with_span_retrace) |
+    /// | `deferred` | [`with_deferred_spans`](Self::with_deferred_spans) |
+    /// | `span_modes` | [`with_span_modes`](Self::with_span_modes) |
+    /// | `bracketed_fields` | [`with_bracketed_fields`](Self::with_bracketed_fields) |
+    /// | `bracketed_event_fields` | [`with_bracketed_event_fields`](Self::with_bracketed_event_fields) |
+    /// | `thread_migrations` | [`with_thread_migrations`](Self::with_thread_migrations) |
+    /// | `span_tags` | [`with_span_tags`](Self::with_span_tags) |
+    /// | `enter_counts` | [`with_enter_counts`](Self::with_enter_counts) |
+    /// | `short_levels` | [`with_short_levels`](Self::with_short_levels) |
+    /// | `level_from=<level>` | [`with_level_from`](Self::with_level_from) |
+    /// | `span_levels` | [`with_span_levels`](Self::with_span_levels) |
+    /// | `event_deltas` | [`with_event_deltas`](Self::with_event_deltas) |
+    /// | `elapsed_from_root` | [`with_elapsed_from_root`](Self::with_elapsed_from_root) |
+    /// | `github_groups` | [`with_github_groups`](Self::with_github_groups) |
+    /// | `sanitize_fields` | [`with_sanitize_fields`](Self::with_sanitize_fields) |
+    /// | `sanitize_ansi` | [`with_sanitize_ansi`](Self::with_sanitize_ansi) |
+    /// | `field_newlines=keep\|escape\|symbols` | [`with_field_newlines`](Self::with_field_newlines) |
+    /// | `markdown` | [`with_markdown`](Self::with_markdown) |
+    /// | `critical_path` | [`with_critical_path`](Self::with_critical_path) |
+    /// | `tree_stats` | [`with_tree_stats`](Self::with_tree_stats) |
+    /// | `deterministic` | [`with_deterministic_output`](Self::with_deterministic_output) |
+    /// | `guide_gradient` | [`with_guide_gradient`](Self::with_guide_gradient) |
+    /// | `lenient_spans` | [`with_lenient_spans`](Self::with_lenient_spans) |
+    /// | `pair_markers` | [`with_pair_markers`](Self::with_pair_markers) |
+    /// | `columns[=<delimiter>]` | [`with_columns`](Self::with_columns) |
+    /// | `switch_markers` | [`with_switch_markers`](Self::with_switch_markers) |
+    /// | `retrace_elapsed` | [`with_retrace_elapsed`](Self::with_retrace_elapsed) |
+    /// | `span_time_range` | [`with_span_time_range`](Self::with_span_time_range) |
+    /// | `error_backtraces` | [`with_error_backtraces`](Self::with_error_backtraces) |
+    /// | `force_backtraces` | [`with_force_backtraces`](Self::with_force_backtraces) |
+    /// | `header_every=N` | [`with_header_every`](Self::with_header_every) |
+    /// | `root_separator=blank\|rule\|<char>` | [`with_root_separator`](Self::with_root_separator) |
+    /// | `event_divider[=<level>[:rule\|header]]` | [`with_event_divider`](Self::with_event_divider) |
+    /// | `message_position=first\|last` | [`with_message_position`](Self::with_message_position) |
+    /// | `otel_ids` (feature `opentelemetry`) | [`with_otel_ids`](Self::with_otel_ids) |
+    /// | `time_display=both\|elapsed_first\|wall_clock\|elapsed\|none` | [`with_time_display`](Self::with_time_display) |
+    /// | `severity_prefix=none\|systemd` | [`with_severity_prefix`](Self::with_severity_prefix) |
+    /// | `basic_colors` | [`with_basic_colors`](Self::with_basic_colors) |
+    /// | `theme=dark\|light` | [`with_theme`](Self::with_theme) |
+    /// | `ascii` | [`with_glyphs`](Self::with_glyphs) with [`Glyphs::ascii`] |
+    pub fn with_options(self, options: &str) -> Self {
+        let config = self.config.clone().with_options(options);
+        self.with_config(config)
+    }
+
+    /// Whether to collect statistics over the lifetime of the layer and print a summary
+    /// block with the number of events per level, the number of spans and the slowest spans
+    /// when the layer is dropped.
+    ///
+    /// A layer installed as the global default is never dropped, use
+    /// [`report`](Self::report) to print the summary in that case.
+    pub fn with_summary_report(self, enabled: bool) -> Self {
+        let mut output = self.output;
+        output.summary = enabled.then(|| Mutex::new(Summary::default()));
+        Self { output, ..self }
+    }
+
+    /// Aggregates the timings of closed spans per callsite into `stats`. Keep a clone of
+    /// `stats` to read the statistics. If the
+    /// [summary report](Self::with_summary_report) is enabled, it includes the statistics.
+    pub fn with_span_stats(self, stats: SpanStats) -> Self {
+        let mut output = self.output;
+        output.span_stats = Some(stats);
+        Self { output, ..self }
+    }
+
+    /// Records the timings of closed spans per callsite in latency `histograms`. Keep a clone
+    /// of `histograms` to read percentiles from them. If the
+    /// [summary report](Self::with_summary_report) is enabled, it includes the percentiles.
+    pub fn with_latency_histograms(self, histograms: Histograms) -> Self {
+        let mut output = self.output;
+        output.histograms = Some(histograms);
+        Self { output, ..self }
+    }
+
+    /// Calls `hook` for every closed span and every event, e.g. to feed counters and
+    /// histograms of a metrics library from the same layer. The hook is also called for events
+    /// that are not rendered because of [`with_max_events_per_span`](Self::with_max_events_per_span).
+    pub fn with_metrics_hook<F>(self, hook: F) -> Self
+    where
+        F: Fn(MetricsEvent<'_>) + Send + Sync + 'static,
+    {
+        let mut output = self.output;
+        output.metrics_hook = Some(Hook(Arc::new(hook)));
+        Self { output, ..self }
+    }
+
+    /// Aggregates the time spent in closed spans per stack of spans into `stacks`, to be
+    /// rendered as a flamegraph. Keep a clone of `stacks` to write them.
+    pub fn with_folded_stacks(self, stacks: FoldedStacks) -> Self {
+        let mut output = self.output;
+        output.folded_stacks = Some(stacks);
+        Self { output, ..self }
+    }
+
+    /// Writes every closed span and every event to `trace` as well, in the Chrome trace event
+    /// format. Keep a clone of `trace` to flush it.
+    #[cfg(feature = "chrome")]
+    pub fn with_chrome_trace(self, trace: chrome::ChromeTrace) -> Self {
+        let mut output = self.output;
+        output.chrome_trace = Some(trace);
+        Self { output, ..self }
+    }
+
+    /// Writes every closed span and every event to `trace` as well, as Perfetto trace packets.
+    /// Keep a clone of `trace` to flush it.
+    #[cfg(feature = "perfetto")]
+    pub fn with_perfetto_trace(self, trace: perfetto::PerfettoTrace) -> Self {
+        let mut output = self.output;
+        output.perfetto_trace = Some(trace);
+        Self { output, ..self }
+    }
+
+    /// Writes the records of the tree to `make_writer` as well, as JSON lines for machines.
+    ///
+    /// Each line holds one span opening, event or span closing, with its level, target, the path
+    /// of span names from the root, its fields and the time elapsed since its span was opened.
+    /// Unlike a separate layer, the mirror sees exactly the same spans as the tree. Events are
+    /// mirrored even if the tree leaves them out, e.g. because of
+    /// [`with_max_events_per_span`](Self::with_max_events_per_span).
+    pub fn with_structured_mirror<M>(self, make_writer: M) -> Self
+    where
+        M: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        let mut output = self.output;
+        output.mirror = Some(Mirror::new(make_writer));
+        Self { output, ..self }
+    }
+
+    /// Inserts every span and event into the database of `sink` as well, to query them with SQL
+    /// after the fact. Keep a clone of `sink` to flush or query the database. See
+    /// [`sqlite`] for the schema.
+    #[cfg(feature = "sqlite")]
+    pub fn with_sqlite_sink(self, sink: sqlite::SqliteSink) -> Self {
+        let mut output = self.output;
+        output.sqlite_sink = Some(sink);
+        Self { output, ..self }
+    }
+
+    /// Writes every event to `make_writer` as well, as a row of comma separated values for
+    /// triaging traces in a spreadsheet.
+    ///
+    /// The columns are `level,target,path,elapsed_us,message,fields`, where `path` holds the
+    /// names of the spans around the event separated by `:` and `fields` the other fields as
+    /// `name=value` pairs. The header row is written before the first event. Like the
+    /// [structured mirror](Self::with_structured_mirror), the export sees every event, even if
+    /// the tree leaves it out.
+    #[cfg(feature = "csv")]
+    pub fn with_csv_export<M>(self, make_writer: M) -> Self
+    where
+        M: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        let mut output = self.output;
+        output.csv_export = Some(csv::CsvExport::new(make_writer));
+        Self { output, ..self }
+    }
+
+    /// Flushes the underlying writer, e.g. a `BufWriter` around a file. This also happens
+    /// automatically when the layer is dropped.
+    pub fn flush(&self) -> io::Result<()> {
+        self.output.flush()
+    }
+
+    /// Prints the summary block enabled by [`with_summary_report`](Self::with_summary_report)
+    /// right away. Does nothing if the summary report is disabled.
+    pub fn report(&self) {
+        self.output.write_summary();
+    }
+
+    /// Replaces all options of the layer with `config`.
+    ///
+    /// This allows frameworks embedding tracing-tree to pass around and combine configurations
+    /// instead of replaying a chain of `with_*` calls.
+    pub fn with_config(self, config: Config) -> Self {
+        let config = match (self.config.ansi_mode, config.ansi_mode) {
+            (AnsiMode::Auto, _) | (_, AnsiMode::Always | AnsiMode::Never) => config,
+            (_, AnsiMode::Auto) => config.detect_ansi(terminal::is_terminal::<W>()),
+        };
+        Self { config, ..self }
+    }
+
     /// Enables terminal colors, boldness and italics.
+    ///
+    /// By default colors are enabled if the writer is stdout or stderr and attached to a
+    /// terminal, and detected again when the writer is replaced. Other writers, such as files
+    /// or buffers, get no colors. Calling this method turns the detection off, see
+    /// [`with_ansi_mode`](Self::with_ansi_mode) to turn it on again.
+    ///
+    /// With the `anstream` feature, styles are rendered with `anstyle` and the detection follows
+    /// the rules of `anstream`, which also honor `NO_COLOR` and `CLICOLOR_FORCE`. The default
+    /// stderr writer then translates colors for Windows consoles without ANSI support; use
+    /// `with_writer(anstream::stdout)` for the same on stdout.
     pub fn with_ansi(self, ansi: bool) -> Self {
         Self {
             config: self.config.with_ansi(ansi),
@@ -103,18 +485,48 @@ where
         }
     }
 
+    /// Sets whether colors are used. When switching to [`AnsiMode::Auto`] they are detected from
+    /// the current writer right away, and again whenever it is replaced.
+    pub fn with_ansi_mode(self, ansi_mode: AnsiMode) -> Self {
+        let config = self.config.clone().with_ansi_mode(ansi_mode);
+        self.with_config(config)
+    }
+
+    /// Writes the output to `make_writer` instead of stderr.
+    ///
+    /// Unless colors were set with [`with_ansi`](Self::with_ansi), they are detected again
+    /// for the new writer. Stdout and stderr are recognized by the name of the writer type,
+    /// which the compiler does not guarantee to be stable, so this is only a best-effort
+    /// guess; any writer that is not recognized gets no colors. Call `with_ansi(true)` or
+    /// `with_ansi(false)` after this method to force colors on or off.
     pub fn with_writer<W2>(self, make_writer: W2) -> HierarchicalLayer<W2, FT>
     where
         W2: for<'writer> MakeWriter<'writer>,
     {
         HierarchicalLayer {
-            make_writer,
-            config: self.config,
+            output: self.output.with_writer(make_writer),
+            config: self.config.detect_ansi(terminal::is_terminal::<W2>()),
             bufs: self.bufs,
             timer: self.timer,
+            #[cfg(feature = "opentelemetry")]
+            dispatch: self.dispatch,
         }
     }
 
+    /// Writes `ERROR` and `WARN` events to `severe` and everything else to `rest`, e.g.
+    /// `with_level_writers(io::stderr, io::stdout)`. See [`LevelWriters`] for other thresholds.
+    pub fn with_level_writers<A, B>(
+        self,
+        severe: A,
+        rest: B,
+    ) -> HierarchicalLayer<LevelWriters<A, B>, FT>
+    where
+        A: for<'writer> MakeWriter<'writer>,
+        B: for<'writer> MakeWriter<'writer>,
+    {
+        self.with_writer(LevelWriters::new(severe, rest))
+    }
+
     pub fn with_indent_amount(self, indent_amount: usize) -> Self {
         let config = Config {
             indent_amount,
@@ -134,10 +546,12 @@ where
     /// Specifies how to measure and format time at which event has occurred.
     pub fn with_timer<FT2: FormatTime>(self, timer: FT2) -> HierarchicalLayer<W, FT2> {
         HierarchicalLayer {
-            make_writer: self.make_writer,
+            output: self.output,
             config: self.config,
             bufs: self.bufs,
             timer,
+            #[cfg(feature = "opentelemetry")]
+            dispatch: self.dispatch,
         }
     }
 
@@ -168,6 +582,21 @@ where
         }
     }
 
+    /// Whether to repeat the thread ids and names in front of the continuation lines of
+    /// multi-line messages. If disabled, they are replaced by spaces so that the continuation
+    /// lines stay aligned with the first line. Enabled by default.
+    ///
+    /// The glyph in front of the continuation lines is set with
+    /// [`Glyphs::with_continuation`].
+    pub fn with_continuation_thread_prefix(self, continuation_thread_prefix: bool) -> Self {
+        Self {
+            config: self
+                .config
+                .with_continuation_thread_prefix(continuation_thread_prefix),
+            ..self
+        }
+    }
+
     /// Resets the indentation to zero after `wraparound` indentation levels.
     /// This is helpful if you expect very deeply nested spans as otherwise the indentation
     /// just runs out of your screen.
@@ -178,6 +607,19 @@ where
         }
     }
 
+    /// Sets the width of the output in columns. By default the `COLUMNS` environment variable
+    /// is used or else the width of the terminal stderr is attached to. There is no `SIGWINCH`
+    /// handler; the terminal is instead queried again at most once a second to follow resizes.
+    ///
+    /// The width currently only sizes the rule of [`Separator::Rule`]. Wrapping and indentation
+    /// do not depend on it.
+    pub fn with_width(self, width: usize) -> Self {
+        Self {
+            config: self.config.with_width(width),
+            ..self
+        }
+    }
+
     /// Whether to print the currently active span's message again before entering a new span.
     /// This helps if the entry to the current span was quite a while back (and with scrolling
     /// upwards in logs).
@@ -209,6 +651,62 @@ where
         }
     }
 
+    /// Whether to print the time elapsed since a span was opened when `span_retrace` prints it
+    /// again, e.g. `retrace t1 (+2s 300ms)`, so that a retraced span can be told apart from a
+    /// fresh one.
+    pub fn with_retrace_elapsed(self, retrace_elapsed: bool) -> Self {
+        Self {
+            config: self.config.with_retrace_elapsed(retrace_elapsed),
+            ..self
+        }
+    }
+
+    /// Whether to print a line like `⋯ (switching from server>conn#2)` when `span_retrace` or
+    /// `deferred_spans` switches to a span in another branch of the tree, naming the span that
+    /// was left, so that jumps between concurrent subtrees are not mistaken for nesting.
+    pub fn with_switch_markers(self, switch_markers: bool) -> Self {
+        Self {
+            config: self.config.with_switch_markers(switch_markers),
+            ..self
+        }
+    }
+
+    /// Limits how many spans `span_retrace` reprints when switching between spans. Only the
+    /// `depth` spans nearest to the newly entered span are retraced instead of the whole path
+    /// from the common ancestor. Spans that have not been printed yet are always opened.
+    pub fn with_retrace_depth(self, depth: usize) -> Self {
+        Self {
+            config: self.config.with_retrace_depth(depth),
+            ..self
+        }
+    }
+
+    /// Prints at most `max` events directly inside of each span. Further events are dropped and
+    /// summarized as `… 412 more events suppressed` when the span closes, so a hot loop inside
+    /// a single span doesn't flood the output.
+    pub fn with_max_events_per_span(self, max: usize) -> Self {
+        Self {
+            config: self.config.with_max_events_per_span(max),
+            ..self
+        }
+    }
+
+    /// Holds back `DEBUG` and `TRACE` events less severe than `trigger` inside of each span.
+    ///
+    /// The held events are only written (in order and properly indented) if an event at or
+    /// above the `trigger` level occurs in the same span before it closes. Otherwise they are
+    /// dropped, and the span's close is preceded by a `… 12 quiet events dropped` summary. At
+    /// most the 1024 most recent events are held per span, older ones are only counted.
+    ///
+    /// This keeps the output of healthy requests short while still showing the full story of
+    /// failing ones.
+    pub fn with_quiet_until_error(self, trigger: Level) -> Self {
+        Self {
+            config: self.config.with_quiet_until_error(Some(trigger)),
+            ..self
+        }
+    }
+
     /// Defers printing span opening until an event is generated within the span.
     ///
     /// Avoids printing empty spans with no generated events.
@@ -227,183 +725,1401 @@ where
         }
     }
 
-    /// Whether to print `{}` around the fields when printing a span.
-    /// This can help visually distinguish fields from the rest of the message.
-    pub fn with_bracketed_fields(self, bracketed_fields: bool) -> Self {
+    /// Whether to abbreviate levels to a single letter (`T`, `D`, `I`, `W`, `E`).
+    pub fn with_short_levels(self, short_levels: bool) -> Self {
         Self {
-            config: self.config.with_bracketed_fields(bracketed_fields),
+            config: self.config.with_short_levels(short_levels),
             ..self
         }
     }
 
-    fn styled(&self, style: Style, text: impl AsRef<str>) -> String {
-        styled(self.config.ansi, style, text)
+    /// Only shows the level of events at or above `level`, e.g. `Level::WARN` to show the level
+    /// of warnings and errors but not of the routine `INFO`, `DEBUG` and `TRACE` events. The
+    /// level of the other events is left blank, so that their messages stay aligned.
+    pub fn with_level_from(self, level: Level) -> Self {
+        Self {
+            config: self.config.with_level_from(Some(level)),
+            ..self
+        }
     }
 
-    fn print_kvs<'a, I, V>(&self, buf: &mut impl fmt::Write, kvs: I) -> fmt::Result
-    where
-        I: IntoIterator<Item = (&'a str, V)>,
-        V: fmt::Display + 'a,
-    {
-        let mut kvs = kvs.into_iter();
-        if let Some((k, v)) = kvs.next() {
-            if k == "message" {
-                write!(buf, "{}", v)?;
-            } else {
-                write!(buf, "{}={}", k, v)?;
-            }
+    /// Whether to show the level of a span in front of its name on its open and close lines,
+    /// colored like the levels of events. This tells `TRACE` plumbing spans apart from `INFO`
+    /// spans of the business logic.
+    pub fn with_span_levels(self, span_levels: bool) -> Self {
+        Self {
+            config: self.config.with_span_levels(span_levels),
+            ..self
         }
-        for (k, v) in kvs {
-            write!(buf, ", {}={}", k, v)?;
+    }
+
+    /// Whether to show the time since the previous event in the same span (or since the span was
+    /// opened, for its first event) next to every event, e.g. `+12ms`.
+    ///
+    /// The deltas are shown in addition to the timer's elapsed time. Use the `()` timer to only show
+    /// the deltas.
+    pub fn with_event_deltas(self, event_deltas: bool) -> Self {
+        Self {
+            config: self.config.with_event_deltas(event_deltas),
+            ..self
         }
-        Ok(())
     }
 
-    /// Ensures that `new_span` and all its ancestors are properly printed before an event
-    fn write_retrace_span<'a, S>(
-        &self,
-        new_span: &SpanRef<'a, S>,
-        bufs: &mut Buffers,
-        ctx: &'a Context<S>,
-        pre_open: bool,
-    ) where
-        S: Subscriber + for<'new_span> LookupSpan<'new_span>,
-    {
-        // Also handle deferred spans along with retrace since deferred spans may need to print
-        // multiple spans at once as a whole tree can be deferred
-        //
-        // If a another event occurs right after a previous event in the same span, this will
-        // simply print nothing since the path to the common lowest ancestor is empty
-        // if self.config.span_retrace || self.config.deferred_spans {
-        let old_span_id = bufs.current_span.replace((new_span.id()).clone());
-        let old_span_id = old_span_id.as_ref();
-        let new_span_id = new_span.id();
+    /// Whether the elapsed time of an event is measured from the start of the outermost span it
+    /// is in, instead of its innermost span. This puts all events of a request on a single
+    /// timeline.
+    pub fn with_elapsed_from_root(self, elapsed_from_root: bool) -> Self {
+        Self {
+            config: self.config.with_elapsed_from_root(elapsed_from_root),
+            ..self
+        }
+    }
 
-        if Some(&new_span_id) != old_span_id {
-            let old_span = old_span_id.as_ref().and_then(|v| ctx.span(v));
-            let old_path = old_span.as_ref().map(scope_path).into_iter().flatten();
+    /// Which of the timer's wall-clock time and the time elapsed in the event's span are printed in
+    /// front of an event, and in which order. Defaults to [`TimeDisplay::Both`].
+    pub fn with_time_display(self, time_display: TimeDisplay) -> Self {
+        Self {
+            config: self.config.with_time_display(time_display),
+            ..self
+        }
+    }
 
-            let new_path = scope_path(new_span);
+    /// Pads the timer's wall-clock time in front of events to at least `width` characters, so that
+    /// the following columns line up even if the timer's output varies in length.
+    pub fn with_wall_clock_width(self, width: usize) -> Self {
+        Self {
+            config: self.config.with_wall_clock_width(width),
+            ..self
+        }
+    }
 
-            // Print the path from the common base of the two spans
-            let new_path = DifferenceIter::new(old_path, new_path, |v| v.id());
+    /// Right-aligns the elapsed time in front of events to at least `width` characters, so that
+    /// messages start at the same column regardless of the duration's unit. Events outside of any
+    /// span are padded with blanks instead.
+    pub fn with_elapsed_width(self, width: usize) -> Self {
+        Self {
+            config: self.config.with_elapsed_width(width),
+            ..self
+        }
+    }
 
-            for (i, span) in new_path.enumerate() {
-                // Mark traversed spans as *written*
-                let was_written = if let Some(data) = span.extensions_mut().get_mut::<Data>() {
-                    mem::replace(&mut data.written, true)
-                } else {
-                    // `on_new_span` was not called, before
-                    // Consider if this should panic instead, which is *technically* correct but is
-                    // bad behavior for a logging layer in production.
-                    false
-                };
+    /// Prepends a prefix like `<3>` to every line, so that systemd or syslog classify the lines
+    /// by severity even if they are written to plain stderr. Lines of spans use the span's level,
+    /// and retraced spans the level of the event that caused the retrace.
+    pub fn with_severity_prefix(self, prefix: SeverityPrefix) -> Self {
+        Self {
+            config: self.config.with_severity_prefix(prefix),
+            ..self
+        }
+    }
 
-                // Print the parent of the first span
-                let mut verbose = false;
-                if i == 0 && pre_open {
-                    if let Some(span) = span.parent() {
-                        verbose = true;
-                        self.write_span_info(&span, bufs, SpanMode::PreOpen);
-                    }
-                }
+    /// Decides what happens when writing to the writer fails, e.g. with a broken pipe after
+    /// `app | head` exits. The output is always dropped rather than panicking; by default the error
+    /// is ignored.
+    pub fn with_write_error_policy(self, policy: WriteErrorPolicy) -> Self {
+        Self {
+            config: self.config.with_write_error_policy(policy),
+            ..self
+        }
+    }
 
-                self.write_span_info(
-                    &span,
-                    bufs,
-                    if was_written {
-                        SpanMode::Retrace { verbose }
-                    } else {
-                        SpanMode::Open { verbose }
-                    },
-                )
-            }
+    /// Whether to escape control characters in span and event fields, which is enabled by default.
+    ///
+    /// Characters such as `\r`, backspace or the Unicode bidirectional overrides can corrupt the
+    /// terminal or make a line look like something it isn't. They are written as `\r` or
+    /// `\u{202e}` instead. Escape sequences are left alone, and line breaks and tabs are up to
+    /// [`with_field_newlines`](Self::with_field_newlines).
+    pub fn with_sanitize_fields(self, sanitize_fields: bool) -> Self {
+        Self {
+            config: self.config.with_sanitize_fields(sanitize_fields),
+            ..self
         }
     }
 
-    fn write_span_info<S>(&self, span: &SpanRef<S>, bufs: &mut Buffers, style: SpanMode)
-    where
-        S: Subscriber + for<'span> LookupSpan<'span>,
-    {
-        let ext = span.extensions();
-        let data = ext.get::<Data>().expect("span does not have data");
+    /// How line breaks and tabs in span and event fields other than the message are written.
+    ///
+    /// By default they are escaped as `\n`, `\r` and `\t`, as a value continuing on the
+    /// following lines would be mistaken for other lines of the tree. [`Newlines::Symbols`]
+    /// writes them as `␤`, `␍` and `␉` instead, and [`Newlines::Keep`] leaves them alone.
+    /// Messages keep their line breaks and continue on indented lines. Like the other
+    /// escapes, this is disabled by [`with_sanitize_fields(false)`](Self::with_sanitize_fields).
+    pub fn with_field_newlines(self, newlines: Newlines) -> Self {
+        Self {
+            config: self.config.with_field_newlines(newlines),
+            ..self
+        }
+    }
 
-        let mut current_buf = &mut bufs.current_buf;
+    /// Whether to strip escape sequences from messages and other span and event fields, so
+    /// that logged content can neither change the state of the terminal nor fake colored log
+    /// lines. By default they are stripped if [`ansi`](Self::with_ansi) is disabled, and kept
+    /// otherwise so that colored values still render.
+    pub fn with_sanitize_ansi(self, sanitize_ansi: bool) -> Self {
+        Self {
+            config: self.config.with_sanitize_ansi(Some(sanitize_ansi)),
+            ..self
+        }
+    }
 
-        if self.config.span_modes {
-            write_span_mode(current_buf, style)
+    /// Whether to render the tree as a nested Markdown list, with levels in bold and fields in
+    /// backticks, so that the output can be pasted into an issue or a pull request and stays
+    /// readable. Enabling this also disables colors.
+    pub fn with_markdown(self, markdown: bool) -> Self {
+        Self {
+            config: self.config.with_markdown(markdown),
+            ..self
         }
+    }
 
-        let indent = scope_path(span).skip(1).count();
+    /// Whether to render a shortened OpenTelemetry trace id like `trace_id=4bf92f35` on span open
+    /// lines, so the tree can be correlated with the traces sent to a backend.
+    ///
+    /// The id is read from the span extensions of
+    /// [`tracing-opentelemetry`](https://docs.rs/tracing-opentelemetry), so its layer must be added
+    /// to the subscriber before this one.
+    #[cfg(feature = "opentelemetry")]
+    pub fn with_otel_ids(self, otel_ids: bool) -> Self {
+        Self {
+            config: self.config.with_otel_ids(otel_ids),
+            ..self
+        }
+    }
 
-        let should_write = match style {
-            SpanMode::Open { .. } | SpanMode::Event => true,
-            // Print the parent of a new span again before entering the child
-            SpanMode::PreOpen { .. } if self.config.verbose_entry => true,
-            SpanMode::Close { verbose } => verbose,
-            // Generated if `span_retrace` is enabled
-            SpanMode::Retrace { .. } => true,
-            // Generated if `verbose_exit` is enabled
-            SpanMode::PostClose => true,
-            _ => false,
-        };
+    /// Caps the bytes of field values stored for each span, so that a span recording a huge
+    /// `Debug` dump does not keep it in memory while the span is open. Values beyond the budget are
+    /// cut off and end with `…`. The default is no limit.
+    pub fn with_field_budget_bytes(self, budget: usize) -> Self {
+        Self {
+            config: self.config.with_field_budget_bytes(budget),
+            ..self
+        }
+    }
 
-        if should_write {
-            if self.config.targets {
-                let target = span.metadata().target();
-                write!(
-                    &mut current_buf,
-                    "{}::",
-                    self.styled(Style::new().dimmed(), target,),
-                )
-                .expect("Unable to write to buffer");
-            }
+    /// Wraps the output of every root span in `::group::` and `::endgroup::` workflow commands, so
+    /// that each request or test becomes a collapsible section of a GitHub Actions log.
+    ///
+    /// The output is streamed rather than buffered, and GitHub Actions doesn't nest groups, so this
+    /// works best when root spans don't overlap, such as in a single-threaded test runner.
+    pub fn with_github_groups(self, github_groups: bool) -> Self {
+        Self {
+            config: self.config.with_github_groups(github_groups),
+            ..self
+        }
+    }
 
-            write!(
-                current_buf,
-                "{name}",
-                name = self.styled(Style::new().fg(Color::Green).bold(), span.metadata().name())
-            )
-            .unwrap();
-            if self.config.bracketed_fields {
-                write!(
-                    current_buf,
-                    "{}",
-                    self.styled(Style::new().fg(Color::Green).bold(), "{") // Style::new().fg(Color::Green).dimmed().paint("{")
+    /// Whether to print the critical path when a root span closes: starting at the root, the
+    /// slowest child span of every span, e.g. `critical path: server → query → parse (1s 200ms)`
+    /// with the time spent in the root span.
+    pub fn with_critical_path(self, critical_path: bool) -> Self {
+        Self {
+            config: self.config.with_critical_path(critical_path),
+            ..self
+        }
+    }
+
+    /// Whether to print the shape of a span tree when its root span closes: the number of spans
+    /// below the root, their maximum depth and the number of events in the whole tree. Helps to
+    /// spot runaway recursion and span leaks in long running trees.
+    pub fn with_tree_stats(self, tree_stats: bool) -> Self {
+        Self {
+            config: self.config.with_tree_stats(tree_stats),
+            ..self
+        }
+    }
+
+    /// Whether to make the output reproducible for snapshot tests: elapsed times and durations are
+    /// rendered as zero, the digits of wall-clock times as `0` and thread ids as sequential aliases
+    /// in the order the threads were first seen, so that golden files need no filters.
+    pub fn with_deterministic_output(self, deterministic_output: bool) -> Self {
+        Self {
+            config: self.config.with_deterministic_output(deterministic_output),
+            ..self
+        }
+    }
+
+    /// Whether to keep rendering spans that lack the data this layer records when they are
+    /// created, e.g. spans created while the layer was busy writing. Such spans are shown with
+    /// their name only and without an elapsed time. Otherwise rendering them panics.
+    pub fn with_lenient_spans(self, lenient_spans: bool) -> Self {
+        Self {
+            config: self.config.with_lenient_spans(lenient_spans),
+            ..self
+        }
+    }
+
+    /// Whether to end the open and close lines of a span with the same short marker like
+    /// `⟨#17⟩`, so that searching for the marker jumps between the two ends of a long subtree.
+    ///
+    /// Markers are made from span ids, which are reused once a span has closed, so the next
+    /// occurrence of a marker after an open line is always the close line of the same span. The
+    /// close line is marked even without `verbose_exit`.
+    pub fn with_pair_markers(self, pair_markers: bool) -> Self {
+        Self {
+            config: self.config.with_pair_markers(pair_markers),
+            ..self
+        }
+    }
+
+    /// Prints every event as a line of fixed-width columns instead of a tree, so that the output
+    /// can be processed with `cut` or `awk` while keeping the depth of each event:
+    ///
+    /// ```text
+    ///    0.004211s | 1:main       |  2 | INFO  | server:conn              | accepted | peer=1
+    /// ```
+    ///
+    /// Spans are not printed on lines of their own, the path column names the spans around
+    /// each event instead. See [`Columns`] for the layout.
+    pub fn with_columns(self, columns: Columns) -> Self {
+        Self {
+            config: self.config.with_columns(Some(columns)),
+            ..self
+        }
+    }
+
+    /// Whether to print the wall-clock times a span was opened and closed at on its close line,
+    /// e.g. `┘10:32:01.120 → 10:32:03.870`, to correlate spans with the logs of other systems.
+    /// The times are formatted by the [timer](Self::with_timer), so nothing is printed without
+    /// one. The close line is printed even without `verbose_exit`.
+    pub fn with_span_time_range(self, span_time_range: bool) -> Self {
+        Self {
+            config: self.config.with_span_time_range(span_time_range),
+            ..self
+        }
+    }
+
+    /// Whether to capture a backtrace when an `ERROR` event is recorded and print it as an
+    /// indented block below the event.
+    ///
+    /// Like [`Backtrace::capture`](std::backtrace::Backtrace::capture), this only captures
+    /// backtraces if the `RUST_LIB_BACKTRACE` or `RUST_BACKTRACE` environment variables enable
+    /// them, unless [`with_force_backtraces`](Self::with_force_backtraces) is enabled. The
+    /// backtrace starts with the frames of the layer itself.
+    pub fn with_error_backtraces(self, error_backtraces: bool) -> Self {
+        Self {
+            config: self.config.with_error_backtraces(error_backtraces),
+            ..self
+        }
+    }
+
+    /// Whether to capture the backtraces of
+    /// [`with_error_backtraces`](Self::with_error_backtraces) regardless of the environment
+    /// variables.
+    pub fn with_force_backtraces(self, force_backtraces: bool) -> Self {
+        Self {
+            config: self.config.with_force_backtraces(force_backtraces),
+            ..self
+        }
+    }
+
+    /// Prints the name and fields of a span again, dimmed and marked with `↺`, after every `n`
+    /// events inside of it, so that the span of a long run of events is still in sight. `0`,
+    /// the default, never prints them again.
+    pub fn with_header_every(self, n: usize) -> Self {
+        Self {
+            config: self.config.with_header_every(n),
+            ..self
+        }
+    }
+
+    /// Writes a blank line or a horizontal rule whenever a root span is opened after the first
+    /// one, so that independent requests are easy to tell apart in continuous output. A rule
+    /// spans the [`width`](Self::width) of the output, or 80 columns if it is unknown.
+    pub fn with_root_separator(self, separator: Separator) -> Self {
+        Self {
+            config: self.config.with_root_separator(Some(separator)),
+            ..self
+        }
+    }
+
+    /// Writes a divider below every event at or above `level`, e.g. `Level::ERROR`, so that
+    /// they are easy to spot when scrolling: a short dimmed rule, or the name and fields of the
+    /// event's span again.
+    pub fn with_event_divider(self, level: Level, divider: Divider) -> Self {
+        Self {
+            config: self.config.with_event_divider(Some(level), divider),
+            ..self
+        }
+    }
+
+    /// Whether the message of an event is printed before or after its other fields, regardless
+    /// of the order they were recorded in. By default it is printed first.
+    pub fn with_message_position(self, position: MessagePosition) -> Self {
+        Self {
+            config: self.config.with_message_position(position),
+            ..self
+        }
+    }
+
+    /// Disables spans and events more verbose than `level` when their callsites are registered,
+    /// so that they are rejected before any locking or formatting.
+    ///
+    /// Unlike wrapping the layer with a per-layer filter, this is a global filter: the spans and
+    /// events are disabled for the other layers of the subscriber as well.
+    pub fn with_max_level(self, level: Level) -> Self {
+        Self {
+            config: self.config.with_max_level(Some(level)),
+            ..self
+        }
+    }
+
+    /// Disables spans and events more verbose than the level given for the longest matching
+    /// prefix of their target, e.g. `[("hyper", Level::WARN), ("my_crate", Level::TRACE)]`.
+    /// Prefixes match whole path segments, and targets without a match fall back to
+    /// [`with_max_level`](Self::with_max_level).
+    ///
+    /// This is a global filter that works like `with_max_level`. Events inside of disabled
+    /// spans are indented as if the spans didn't exist.
+    pub fn with_target_filter<I, T>(self, filter: I) -> Self
+    where
+        I: IntoIterator<Item = (T, Level)>,
+        T: Into<String>,
+    {
+        let filter = filter
+            .into_iter()
+            .map(|(prefix, level)| (prefix.into(), level))
+            .collect();
+        Self {
+            config: self.config.with_target_filter(filter),
+            ..self
+        }
+    }
+
+    /// Specifies the characters used to draw the tree when `indent_lines` is enabled, e.g.
+    /// [`Glyphs::ascii`] for terminals without unicode support.
+    pub fn with_glyphs(self, glyphs: Glyphs) -> Self {
+        Self {
+            config: self.config.with_glyphs(glyphs),
+            ..self
+        }
+    }
+
+    /// Whether to color the lines of the tree with a gradient by depth, so that the depth of a
+    /// line in a tall tree can be seen at a glance. Requires `indent_lines` and `ansi`.
+    ///
+    /// The gradient uses 24-bit colors if the `COLORTERM` environment variable announces
+    /// `truecolor` support, or else the closest colors of the 256 color palette.
+    pub fn with_guide_gradient(self, guide_gradient: bool) -> Self {
+        Self {
+            config: self.config.with_guide_gradient(guide_gradient),
+            ..self
+        }
+    }
+
+    /// Specifies the styles used if `ansi` is enabled, e.g. [`Theme::light`] for terminals with a
+    /// light background, or to color or embolden the timestamps instead of dimming them.
+    pub fn with_theme(self, theme: Theme) -> Self {
+        Self {
+            config: self.config.with_theme(theme),
+            ..self
+        }
+    }
+
+    /// Whether to use only the 16 basic ANSI colors, for terminals and log viewers that don't
+    /// support 256 or 24-bit colors. The colors of the theme, such as the orange of `WARN`, are
+    /// replaced by the closest basic colors, and the guide gradient cycles through the basic hues.
+    pub fn with_basic_colors(self, basic_colors: bool) -> Self {
+        Self {
+            config: self.config.with_basic_colors(basic_colors),
+            ..self
+        }
+    }
+
+    /// Whether to print `{}` around the fields when printing a span.
+    /// This can help visually distinguish fields from the rest of the message.
+    pub fn with_bracketed_fields(self, bracketed_fields: bool) -> Self {
+        Self {
+            config: self.config.with_bracketed_fields(bracketed_fields),
+            ..self
+        }
+    }
+
+    /// Whether to print `{}` around the fields of an event, like
+    /// [`with_bracketed_fields`](Self::with_bracketed_fields) does for spans, so that they
+    /// stand apart from the message: `INFO done {status=200}`.
+    pub fn with_bracketed_event_fields(self, bracketed_event_fields: bool) -> Self {
+        Self {
+            config: self
+                .config
+                .with_bracketed_event_fields(bracketed_event_fields),
+            ..self
+        }
+    }
+
+    /// Whether to annotate span lines with `↷ moved to <thread>` when a span's activity comes
+    /// from a different thread than before. This makes work-stealing executors visible without
+    /// having to render the thread ids on every line.
+    pub fn with_thread_migrations(self, thread_migrations: bool) -> Self {
+        Self {
+            config: self.config.with_thread_migrations(thread_migrations),
+            ..self
+        }
+    }
+
+    /// Whether to give every printed span a short tag like `[a3]`, shown on its own lines and in
+    /// front of every event inside of it. Tags of closed spans are reused.
+    ///
+    /// This is a cheaper alternative to `span_retrace` for telling apart the events of heavily
+    /// interleaved spans.
+    pub fn with_span_tags(self, span_tags: bool) -> Self {
+        Self {
+            config: self.config.with_span_tags(span_tags),
+            ..self
+        }
+    }
+
+    /// Whether to annotate span lines with `(enter #5)` once a span has been entered more than
+    /// once, e.g. by a loop or by polling an instrumented future repeatedly. This tells a
+    /// retraced span instance apart from a new span with the same name.
+    pub fn with_enter_counts(self, enter_counts: bool) -> Self {
+        Self {
+            config: self.config.with_enter_counts(enter_counts),
+            ..self
+        }
+    }
+
+    /// Rewrites span names before they are displayed, e.g. to shorten the long names
+    /// `#[instrument]` generates for generic functions. The default is to display
+    /// `metadata.name()` verbatim.
+    pub fn with_span_name_fn<F>(self, span_name: F) -> Self
+    where
+        F: Fn(&'static Metadata<'static>) -> Cow<'static, str> + Send + Sync + 'static,
+    {
+        Self {
+            config: self.config.with_span_name_fn(span_name),
+            ..self
+        }
+    }
+
+    /// Customizes how field values of spans and events are written, e.g. to render a
+    /// `duration_ns` field as human readable time or to shorten hashes.
+    ///
+    /// The callback writes the value of `field` to the output and returns `true`, or returns
+    /// `false` to fall back to the value's `Debug` representation.
+    pub fn with_field_formatter<F>(self, field_formatter: F) -> Self
+    where
+        F: Fn(&Field, &dyn fmt::Debug, &mut dyn fmt::Write) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            config: self.config.with_field_formatter(field_formatter),
+            ..self
+        }
+    }
+
+    /// Delimits the output of every root span with the control lines of a CI system, so that
+    /// each request or test becomes a collapsible section of the log. See the
+    /// [`section`] module for the built-in markers.
+    ///
+    /// Like [`with_github_groups`](Self::with_github_groups), this works best when root spans
+    /// don't overlap.
+    pub fn with_section_marker<M>(self, marker: M) -> Self
+    where
+        M: SectionMarker + 'static,
+    {
+        Self {
+            config: self.config.with_section_marker(marker),
+            ..self
+        }
+    }
+
+    /// The current options of the layer.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The width of the output in columns, or `None` if it is neither set with
+    /// [`with_width`](Self::with_width) nor detected.
+    pub fn width(&self) -> Option<usize> {
+        self.config.output_width()
+    }
+
+    /// The number of failed writes counted with [`WriteErrorPolicy::CountAndIgnore`].
+    pub fn write_errors(&self) -> usize {
+        self.output.write_errors.load(Ordering::Relaxed)
+    }
+
+    /// Whether terminal colors, boldness and italics are enabled.
+    pub fn ansi(&self) -> bool {
+        self.config.ansi
+    }
+
+    /// The amount of characters to indent by per nesting level.
+    pub fn indent_amount(&self) -> usize {
+        self.config.indent_amount
+    }
+
+    /// Whether an ascii art tree is rendered instead of just whitespace indentation.
+    pub fn indent_lines(&self) -> bool {
+        self.config.indent_lines
+    }
+
+    /// Whether event and span targets are rendered.
+    pub fn targets(&self) -> bool {
+        self.config.targets
+    }
+
+    /// Whether thread ids are rendered at the beginning of every line.
+    pub fn thread_ids(&self) -> bool {
+        self.config.render_thread_ids
+    }
+
+    /// Whether thread names are rendered at the beginning of every line.
+    pub fn thread_names(&self) -> bool {
+        self.config.render_thread_names
+    }
+
+    /// After how many indentation levels the indentation wraps around to zero.
+    pub fn wraparound(&self) -> usize {
+        self.config.wraparound
+    }
+
+    /// Whether the parent span is printed again before entering a child span.
+    pub fn verbose_entry(&self) -> bool {
+        self.config.verbose_entry
+    }
+
+    /// Whether the parent span is printed again after leaving a child span.
+    pub fn verbose_exit(&self) -> bool {
+        self.config.verbose_exit
+    }
+
+    /// Whether spans are retraced when another span was entered in the meantime.
+    pub fn is_retracing(&self) -> bool {
+        self.config.span_retrace
+    }
+
+    /// Whether printing a span is deferred until an event is generated within it.
+    pub fn is_deferred(&self) -> bool {
+        self.config.deferred_spans
+    }
+
+    /// Whether lines are prefixed with their span mode, such as `open` or `close`.
+    pub fn span_modes(&self) -> bool {
+        self.config.span_modes
+    }
+
+    /// Whether span fields are surrounded by `{}`.
+    pub fn bracketed_fields(&self) -> bool {
+        self.config.bracketed_fields
+    }
+
+    fn styled<T: fmt::Display>(&self, style: Style, text: T) -> Styled<T> {
+        styled(self.config.ansi, style, text)
+    }
+
+    /// Appends `text` to `buf`, painted if colors are enabled. Plain text is copied over
+    /// directly instead of going through the formatting machinery.
+    fn push_styled(&self, buf: &mut String, style: Style, text: &str) {
+        push_styled(self.config.ansi, buf, style, text)
+    }
+
+    /// Records the current thread as the last one `span` was active on and, if it differs from
+    /// the previous one, writes a migration annotation to `buf`.
+    fn write_thread_migration<S>(&self, span: &SpanRef<S>, buf: &mut String)
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        if !self.config.thread_migrations {
+            return;
+        }
+        let current = thread::current().id();
+        let moved = match span.extensions_mut().get_mut::<Data>() {
+            Some(data) => mem::replace(&mut data.thread, current) != current,
+            None => false,
+        };
+        if moved {
+            buf.push(' ');
+            write_styled(
+                self.config.ansi,
+                self.config.theme().secondary,
+                buf,
+                |buf| {
+                    buf.push_str("↷ moved to ");
+                    buf.push_str(&self.config.thread_label(true, true));
+                },
+            );
+        }
+    }
+
+    /// Writes the tag of `span` followed by a space, assigning a new tag if it has none yet.
+    fn write_span_tag<S>(&self, span: &SpanRef<S>, tags: &mut SpanTags, buf: &mut String)
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        if !self.config.span_tags {
+            return;
+        }
+        let mut ext = span.extensions_mut();
+        let Some(data) = ext.get_mut::<Data>() else {
+            return;
+        };
+        let tag = *data.tag.get_or_insert_with(|| tags.allocate());
+        write_styled(
+            self.config.ansi,
+            self.config.theme().secondary,
+            buf,
+            |buf| write_span_tag(buf, tag),
+        );
+        buf.push(' ');
+    }
+
+    /// Writes the wall-clock times a span was opened and closed at.
+    fn write_time_range(&self, time_range: &str, buf: &mut String) {
+        // Spans without fields already end with a space
+        if !buf.is_empty() && !buf.ends_with(' ') {
+            buf.push(' ');
+        }
+        match self.config.theme().wall_clock {
+            Some(style) => self.push_styled(buf, style, time_range),
+            None => buf.push_str(time_range),
+        }
+    }
+
+    /// The current wall-clock time, formatted by the timer for a line of `metadata` nested in
+    /// `depth` spans.
+    fn wall_clock(&self, metadata: &Metadata<'_>, depth: usize) -> String {
+        let mut time = String::new();
+        self.timer
+            .format_time_with(&TimeContext::new(metadata, depth), &mut time)
+            .expect("Unable to write time to buffer");
+        if self.config.deterministic_output {
+            mask_digits(&mut time, 0);
+        }
+        time
+    }
+
+    /// Writes the marker shared by the open and close lines of the span `id`.
+    fn write_pair_marker(&self, id: &Id, buf: &mut String) {
+        // Spans without fields already end with a space
+        if !buf.is_empty() && !buf.ends_with(' ') {
+            buf.push(' ');
+        }
+        write_styled(
+            self.config.ansi,
+            self.config.theme().secondary,
+            buf,
+            |buf| {
+                write!(buf, "⟨#{}⟩", id.into_u64()).unwrap();
+            },
+        );
+    }
+
+    /// Appends a backtrace of the current thread to the event in `buf`, one frame line per line,
+    /// if backtraces are enabled.
+    fn write_backtrace(&self, buf: &mut String) {
+        let backtrace = if self.config.force_backtraces {
+            Backtrace::force_capture()
+        } else {
+            Backtrace::capture()
+        };
+        if backtrace.status() != BacktraceStatus::Captured {
+            return;
+        }
+        for line in backtrace.to_string().lines() {
+            buf.push('\n');
+            self.push_styled(buf, self.config.theme().secondary, line);
+        }
+    }
+
+    /// Writes `event` as a line of `columns`.
+    fn write_columns<S>(
+        &self,
+        columns: &Columns,
+        event: &Event<'_>,
+        metadata: &Metadata<'_>,
+        span: Option<&SpanRef<S>>,
+        buf: &mut String,
+    ) where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let pad = |buf: &mut String, start: usize, width: usize| {
+            let padding = width.saturating_sub(visible_width(&buf[start..]));
+            write!(buf, "{:padding$}", "", padding = padding).unwrap();
+        };
+        let delimiter = &*columns.delimiter;
+        let depth = span.into_iter().flat_map(scope_path).count();
+
+        let start = buf.len();
+        self.timer
+            .format_time_with(&TimeContext::new(metadata, depth), &mut *buf)
+            .expect("Unable to write time to buffer");
+        if self.config.deterministic_output {
+            mask_digits(buf, start);
+        }
+        pad(buf, start, self.config.wall_clock_width);
+        buf.push_str(delimiter);
+
+        let start = buf.len();
+        buf.push_str(&self.config.thread_label(true, true));
+        pad(buf, start, columns.thread_width);
+        buf.push_str(delimiter);
+
+        write!(buf, "{:>2}", depth).unwrap();
+        buf.push_str(delimiter);
+
+        let start = buf.len();
+        write_event_level(&self.config, buf, metadata.level());
+        pad(buf, start, 5);
+        buf.push_str(delimiter);
+
+        let start = buf.len();
+        for (i, span) in span.into_iter().flat_map(scope_path).enumerate() {
+            if i > 0 {
+                buf.push(':');
+            }
+            buf.push_str(&self.config.span_name(span.metadata()));
+        }
+        pad(buf, start, columns.path_width);
+        buf.push_str(delimiter);
+
+        let mut message = String::new();
+        let mut others = String::new();
+        fields::flatten(event, &self.config, |name, value| {
+            if name == "message" {
+                message.push_str(value);
+                return;
+            }
+            if !others.is_empty() {
+                others.push(' ');
+            }
+            write!(others, "{}={}", name, value).unwrap();
+        });
+        buf.push_str(&message);
+        buf.push_str(delimiter);
+        buf.push_str(&others);
+
+        let end = buf.trim_end().len();
+        buf.truncate(end);
+        buf.push('\n');
+    }
+
+    /// Ensures that `new_span` and all its ancestors are properly printed before an event
+    fn write_retrace_span<'a, S>(
+        &self,
+        new_span: &SpanRef<'a, S>,
+        bufs: &mut Buffers,
+        ctx: &'a Context<S>,
+        pre_open: bool,
+    ) where
+        S: Subscriber + for<'new_span> LookupSpan<'new_span>,
+    {
+        // Also handle deferred spans along with retrace since deferred spans may need to print
+        // multiple spans at once as a whole tree can be deferred
+        //
+        // If a another event occurs right after a previous event in the same span, this will
+        // simply print nothing since the path to the common lowest ancestor is empty
+        // if self.config.span_retrace || self.config.deferred_spans {
+        let old_span_id = bufs.current_span.replace((new_span.id()).clone());
+        let old_span_id = old_span_id.as_ref();
+        let new_span_id = new_span.id();
+
+        if Some(&new_span_id) != old_span_id {
+            let old_span = old_span_id.as_ref().and_then(|v| ctx.span(v));
+            let old_path = old_span.as_ref().map(scope_path).into_iter().flatten();
+
+            let new_path = scope_path(new_span);
+
+            // Print the path from the common base of the two spans
+            let new_path: Vec<_> = DifferenceIter::new(old_path, new_path, |v| v.id()).collect();
+
+            if let Some(old_span) = &old_span {
+                if self.config.switch_markers && !is_related(old_span, new_span) {
+                    let common = scope_path(new_span).count() - new_path.len();
+                    self.write_switch_marker(old_span, common, bufs);
+                }
+            }
+
+            // Only the nearest `retrace_depth` spans are retraced, but spans that have never been
+            // written still need to be opened.
+            let skip = new_path.len().saturating_sub(self.config.retrace_depth);
+            let mut first = true;
+
+            for (i, span) in new_path.into_iter().enumerate() {
+                // Mark traversed spans as *written*
+                let was_written = if let Some(data) = span.extensions_mut().get_mut::<Data>() {
+                    mem::replace(&mut data.written, true)
+                } else {
+                    // `on_new_span` was not called, before
+                    // Consider if this should panic instead, which is *technically* correct but is
+                    // bad behavior for a logging layer in production.
+                    false
+                };
+
+                if i < skip && was_written {
+                    continue;
+                }
+
+                // Print the parent of the first span
+                let mut verbose = false;
+                if mem::replace(&mut first, false) && pre_open {
+                    if let Some(span) = span.parent() {
+                        verbose = true;
+                        self.write_span_info(&span, bufs, SpanMode::PreOpen);
+                    }
+                }
+
+                self.write_span_info(
+                    &span,
+                    bufs,
+                    if was_written {
+                        SpanMode::Retrace { verbose }
+                    } else {
+                        SpanMode::Open { verbose }
+                    },
                 )
-                .unwrap();
-            } else {
-                write!(current_buf, " ").unwrap();
             }
-            self.print_kvs(&mut current_buf, data.kvs.iter().map(|(k, v)| (*k, v)))
+        }
+    }
+
+    /// Writes a line that separates the output of two root spans.
+    fn write_separator(&self, separator: Separator, buf: &mut String) {
+        if let Separator::Rule(c) = separator {
+            let width = self.config.output_width().unwrap_or(80);
+            let rule: String = std::iter::repeat(c).take(width).collect();
+            self.push_styled(buf, self.config.theme().secondary, &rule);
+        }
+        buf.push('\n');
+    }
+
+    /// Writes the divider below an event inside of `span`.
+    fn write_divider<S>(&self, span: Option<&SpanRef<S>>, indent: usize, bufs: &mut Buffers)
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        match (self.config.divider, span) {
+            (Divider::Header, Some(span)) => self.write_header_reminder(span, bufs),
+            _ => {
+                bufs.current_buf.push(' ');
+                self.push_styled(
+                    &mut bufs.current_buf,
+                    self.config.theme().secondary,
+                    "────────",
+                );
+                bufs.indent_current(indent, &self.config, SpanMode::Event);
+                bufs.flush_current_buf();
+            }
+        }
+    }
+
+    /// Writes the name and fields of `span` again at the indentation of its events.
+    fn write_header_reminder<S>(&self, span: &SpanRef<S>, bufs: &mut Buffers)
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let mut header = String::from("↺ ");
+        header.push_str(&self.config.span_name(span.metadata()));
+        if let Some(data) = span.extensions().get::<Data>() {
+            if !data.kvs.is_empty() {
+                header.push(' ');
+                print_kvs(&mut header, data.kvs.iter()).unwrap();
+            }
+        }
+        bufs.current_buf.push(' ');
+        self.push_styled(
+            &mut bufs.current_buf,
+            self.config.theme().secondary,
+            &header,
+        );
+
+        let deindent = if self.config.indent_lines || self.config.markdown {
+            0
+        } else {
+            1
+        };
+        bufs.indent_current(
+            scope_path(span).count().saturating_sub(deindent),
+            &self.config,
+            SpanMode::Event,
+        );
+        bufs.flush_current_buf();
+    }
+
+    /// Writes a line telling that the output switches away from `old_span`, at the depth of
+    /// the `common` ancestors of the old and the new span.
+    fn write_switch_marker<S>(&self, old_span: &SpanRef<S>, common: usize, bufs: &mut Buffers)
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let mut marker = String::from("⋯ (switching from ");
+        for (i, span) in scope_path(old_span).enumerate() {
+            if i > 0 {
+                marker.push('>');
+            }
+            marker.push_str(&self.config.span_name(span.metadata()));
+        }
+        write!(marker, "#{})", old_span.id().into_u64()).unwrap();
+        bufs.current_buf.push(' ');
+        self.push_styled(
+            &mut bufs.current_buf,
+            self.config.theme().secondary,
+            &marker,
+        );
+
+        let deindent = if self.config.indent_lines || self.config.markdown {
+            0
+        } else {
+            1
+        };
+        bufs.indent_current(
+            common.saturating_sub(deindent),
+            &self.config,
+            SpanMode::Event,
+        );
+        bufs.flush_current_buf();
+    }
+
+    fn write_span_info<S>(&self, span: &SpanRef<S>, bufs: &mut Buffers, style: SpanMode)
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        // Columns have no lines for spans
+        if self.config.columns.is_some() {
+            return;
+        }
+
+        if let (None, SpanMode::Open { .. }) = (span.parent(), style) {
+            let root_opened = self.output.root_opened.swap(true, Ordering::Relaxed);
+            if let (Some(separator), true) = (self.config.root_separator, root_opened) {
+                self.write_separator(separator, &mut bufs.record);
+            }
+        }
+
+        if let (Some(marker), None, SpanMode::Open { .. }) =
+            (self.config.section_marker(), span.parent(), style)
+        {
+            marker
+                .open(&self.section_name(span), &mut bufs.record)
                 .unwrap();
-            if self.config.bracketed_fields {
-                write!(
+        }
+
+        if self.config.span_modes {
+            write_span_mode(&mut bufs.current_buf, style)
+        }
+
+        let indent = scope_path(span).skip(1).count();
+
+        let should_write = match style {
+            SpanMode::Open { .. } | SpanMode::Event => true,
+            // Print the parent of a new span again before entering the child
+            SpanMode::PreOpen if self.config.verbose_entry => true,
+            SpanMode::Close { verbose } => verbose,
+            // Generated if `span_retrace` is enabled
+            SpanMode::Retrace { .. } => true,
+            // Generated if `verbose_exit` is enabled
+            SpanMode::PostClose => true,
+            _ => false,
+        };
+
+        if should_write {
+            self.write_span_tag(span, &mut bufs.span_tags, &mut bufs.current_buf);
+        }
+
+        // Looking up the trace id locks the extensions of the span
+        #[cfg(feature = "opentelemetry")]
+        let trace_id = match style {
+            SpanMode::Open { .. } | SpanMode::Retrace { .. }
+                if should_write && self.config.otel_ids =>
+            {
+                self.dispatch
+                    .get()
+                    .and_then(|dispatch| otel::short_trace_id(dispatch, &span.id()))
+            }
+            _ => None,
+        };
+
+        let ext = span.extensions();
+        let data = ext.get::<Data>();
+        if data.is_none() && !self.config.lenient_spans {
+            panic!("span does not have data");
+        }
+
+        let current_buf = &mut bufs.current_buf;
+
+        let pair_marker = match style {
+            SpanMode::Open { .. } | SpanMode::Close { .. } if self.config.pair_markers => {
+                Some(span.id())
+            }
+            _ => None,
+        };
+        let time_range = match (style, data) {
+            (SpanMode::Close { .. }, Some(data)) if self.config.span_time_range => data
+                .opened_at
+                .as_deref()
+                .filter(|opened_at| !opened_at.is_empty())
+                .map(|opened_at| {
+                    let closed_at = self.wall_clock(span.metadata(), indent);
+                    format!("{} → {}", opened_at, closed_at)
+                }),
+            _ => None,
+        };
+
+        if should_write {
+            write_span_head(
+                &self.config,
+                current_buf,
+                span.metadata().level(),
+                span.metadata().target(),
+                &self.config.span_name(span.metadata()),
+                data.into_iter().flat_map(|data| data.kvs.iter()),
+            );
+            #[cfg(feature = "opentelemetry")]
+            if let Some(trace_id) = trace_id {
+                // Spans without fields already end with a space
+                if !current_buf.ends_with(' ') {
+                    current_buf.push(' ');
+                }
+                write_styled(
+                    self.config.ansi,
+                    self.config.theme().secondary,
                     current_buf,
-                    "{}",
-                    self.styled(Style::new().fg(Color::Green).bold(), "}") // Style::new().dimmed().paint("}")
-                )
-                .unwrap();
+                    |buf| {
+                        buf.push_str("trace_id=");
+                        buf.push_str(&trace_id);
+                    },
+                );
+            }
+            if let Some(time_range) = &time_range {
+                self.write_time_range(time_range, current_buf);
+            }
+            if let Some(id) = &pair_marker {
+                self.write_pair_marker(id, current_buf);
+            }
+            if let (SpanMode::Retrace { .. }, Some(data)) = (style, data) {
+                if self.config.retrace_elapsed {
+                    let elapsed = self.config.shown_duration(data.start.elapsed());
+                    // Spans without fields already end with a space
+                    if !current_buf.ends_with(' ') {
+                        current_buf.push(' ');
+                    }
+                    write!(
+                        current_buf,
+                        "{}",
+                        self.styled(
+                            self.config.theme().secondary,
+                            format_args!("(+{})", time::human_duration(elapsed))
+                        )
+                    )
+                    .unwrap();
+                }
+            }
+            let enter_count = data.map_or(0, |data| data.enter_count);
+            if self.config.enter_counts && enter_count > 1 {
+                if let SpanMode::Open { .. } | SpanMode::Retrace { .. } = style {
+                    write!(
+                        current_buf,
+                        " {}",
+                        self.styled(
+                            self.config.theme().secondary,
+                            format_args!("(enter #{})", enter_count)
+                        )
+                    )
+                    .unwrap();
+                }
+            }
+            drop(ext);
+            self.write_thread_migration(span, current_buf);
+        } else {
+            if let Some(time_range) = &time_range {
+                self.write_time_range(time_range, current_buf);
+            }
+            if let Some(id) = &pair_marker {
+                self.write_pair_marker(id, current_buf);
+            }
+        }
+
+        bufs.indent_current(indent, &self.config, style);
+        bufs.flush_current_buf()
+    }
+
+    /// Appends the spans captured in `span_trace` to the event in `current_buf`, innermost
+    /// first, as if they were events one level deeper.
+    #[cfg(feature = "tracing-error")]
+    fn write_span_trace(
+        &self,
+        span_trace: &tracing_error::SpanTrace,
+        bufs: &mut Buffers,
+        indent: usize,
+    ) {
+        let mut block = bufs.take_current_buf();
+        span_trace.with_spans(|metadata, fields| {
+            let buf = &mut bufs.current_buf;
+            buf.push_str(" in ");
+            self.push_styled(
+                buf,
+                self.config.theme().span_name,
+                &self.config.span_name(metadata),
+            );
+            if !fields.is_empty() {
+                buf.push(' ');
+                buf.push_str(fields);
+            }
+            bufs.indent_current(indent, &self.config, SpanMode::Event);
+            block.push_str(&bufs.current_buf);
+            bufs.current_buf.clear();
+            true
+        });
+        let empty = mem::replace(&mut bufs.current_buf, block);
+        bufs.recycle(empty);
+    }
+
+    /// The name of the CI log section of a root span, made up of its name and fields.
+    fn section_name<S>(&self, span: &SpanRef<S>) -> String
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let mut name = self.config.span_name(span.metadata()).into_owned();
+        if let Some(data) = span.extensions().get::<Data>() {
+            if !data.kvs.is_empty() {
+                name.push(' ');
+                print_kvs(&mut name, data.kvs.iter()).unwrap();
+            }
+        }
+        name
+    }
+
+    /// Writes the lines rendered by the current callback, including the retraced spans and
+    /// released held events that lead up to an event.
+    fn write_record(&self, bufs: &mut Buffers, metadata: &Metadata<'_>) {
+        if self.config.severity_prefix == SeverityPrefix::Systemd {
+            let prefix = format!("<{}>", syslog_priority(metadata.level()));
+            let mut record = String::with_capacity(bufs.record.len());
+            for line in bufs.record.split_inclusive('\n') {
+                record.push_str(&prefix);
+                record.push_str(line);
+            }
+            bufs.record = record;
+        }
+        #[cfg(feature = "anstream")]
+        let result = if self.output.auto_stderr {
+            // Translates the escape sequences for Windows consoles without ANSI support
+            bufs.flush_record(anstream::AutoStream::always(io::stderr()))
+        } else {
+            bufs.flush_record(self.output.make_writer.make_writer_for(metadata))
+        };
+        #[cfg(not(feature = "anstream"))]
+        let result = bufs.flush_record(self.output.make_writer.make_writer_for(metadata));
+        if let Err(err) = result {
+            match &self.config.write_error_policy {
+                WriteErrorPolicy::Ignore => {}
+                WriteErrorPolicy::CountAndIgnore => {
+                    self.output.write_errors.fetch_add(1, Ordering::Relaxed);
+                }
+                WriteErrorPolicy::Callback(callback) => callback(&err),
+            }
+        }
+    }
+
+    /// Writes the events held back by `quiet_until_error` in their original order.
+    fn write_held_events<S>(&self, span: &SpanRef<S>, bufs: &mut Buffers)
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let (held, dropped) = match span.extensions_mut().get_mut::<Data>() {
+            Some(data) => (mem::take(&mut data.held), mem::take(&mut data.held_dropped)),
+            None => return,
+        };
+        if dropped > 0 {
+            let summary = format!("… {} earlier quiet events dropped", dropped);
+            self.write_summary_line(span, bufs, summary);
+        }
+        for line in held {
+            bufs.record.push_str(&line);
+            bufs.recycle(line);
+        }
+    }
+
+    /// Writes a summary of the events dropped because of `max_events_per_span` or
+    /// `quiet_until_error`.
+    fn write_suppressed_events<S>(&self, span: &SpanRef<S>, bufs: &mut Buffers)
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let (events, held) = match span.extensions_mut().get_mut::<Data>() {
+            Some(data) => (
+                data.events,
+                mem::take(&mut data.held).len() + mem::take(&mut data.held_dropped),
+            ),
+            None => return,
+        };
+        if held > 0 {
+            self.write_summary_line(span, bufs, format!("… {} quiet events dropped", held));
+        }
+        if let Some(suppressed) = events
+            .checked_sub(self.config.max_events_per_span)
+            .filter(|&n| n > 0)
+        {
+            let summary = format!("… {} more events suppressed", suppressed);
+            self.write_summary_line(span, bufs, summary);
+        }
+    }
+
+    /// Writes a dimmed line at the indentation of the events inside of `span`.
+    fn write_summary_line<S>(&self, span: &SpanRef<S>, bufs: &mut Buffers, summary: String)
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        write!(
+            bufs.current_buf,
+            " {}",
+            self.styled(self.config.theme().secondary, summary)
+        )
+        .expect("Unable to write to buffer");
+
+        let deindent = if self.config.indent_lines || self.config.markdown {
+            0
+        } else {
+            1
+        };
+        let indent = scope_path(span).count() - deindent;
+        bufs.indent_current(indent, &self.config, SpanMode::Event);
+        bufs.flush_current_buf()
+    }
+
+    /// Adds the closed `span` to the shape of its parent. Returns the shape of the whole tree
+    /// for root spans.
+    fn track_tree_shape<S>(&self, span: &SpanRef<S>) -> Option<TreeShape>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let mut shape = {
+            let ext = span.extensions();
+            let data = ext.get::<Data>()?;
+            TreeShape {
+                events: data.shape.events + data.events,
+                ..data.shape
             }
+        };
+        let Some(parent) = span.parent() else {
+            return Some(shape);
+        };
+        if let Some(parent) = parent.extensions_mut().get_mut::<Data>() {
+            shape.spans += 1;
+            shape.depth += 1;
+            let parent = &mut parent.shape;
+            parent.spans += shape.spans;
+            parent.depth = parent.depth.max(shape.depth);
+            parent.events += shape.events;
         }
+        None
+    }
 
-        bufs.indent_current(indent, &self.config, style);
-        let writer = self.make_writer.make_writer();
-        bufs.flush_current_buf(writer)
+    /// Writes a dimmed line about a whole tree after its root span closed.
+    fn write_root_line(&self, bufs: &mut Buffers, line: &str) {
+        self.push_styled(&mut bufs.current_buf, self.config.theme().secondary, line);
+        bufs.indent_current(0, &self.config, SpanMode::Event);
+        bufs.flush_current_buf();
     }
 
-    fn write_timestamp<S>(&self, span: SpanRef<S>, buf: &mut String)
+    /// Hands the critical path of the closed `span` to its parent if it is the parent's
+    /// slowest child so far. Returns the time spent in `span` and its critical path for root
+    /// spans.
+    fn track_critical_path<S>(
+        &self,
+        span: &SpanRef<S>,
+    ) -> Option<(Duration, Vec<Cow<'static, str>>)>
     where
         S: Subscriber + for<'span> LookupSpan<'span>,
     {
-        let ext = span.extensions();
-        let data = ext
-            .get::<Data>()
-            .expect("Data cannot be found in extensions");
+        let (elapsed, mut path) = {
+            let mut ext = span.extensions_mut();
+            let data = ext.get_mut::<Data>()?;
+            let path = data.critical.take().map(|(_, path)| path);
+            (data.start.elapsed(), path.unwrap_or_default())
+        };
+        path.push(self.config.span_name(span.metadata()));
+        let Some(parent) = span.parent() else {
+            return Some((elapsed, path));
+        };
+        if let Some(parent) = parent.extensions_mut().get_mut::<Data>() {
+            if parent
+                .critical
+                .as_ref()
+                .map_or(true, |(slowest, _)| elapsed > *slowest)
+            {
+                parent.critical = Some((elapsed, path));
+            }
+        }
+        None
+    }
 
-        self.timer
-            .style_timestamp(self.config.ansi, data.start.elapsed(), buf)
-            .unwrap()
+    /// Writes the time elapsed in `span`, if the event occurred in the context of a span.
+    fn write_elapsed<S>(
+        &self,
+        time_context: &TimeContext<'_>,
+        span: Option<&SpanRef<S>>,
+        buf: &mut String,
+    ) where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let width = self.config.elapsed_width;
+        let start = buf.len();
+        match span {
+            Some(span) => self.write_timestamp(time_context, span, buf),
+            None if width > 0 => {}
+            None => return,
+        }
+        let padding = width.saturating_sub(visible_width(&buf[start..]));
+        buf.insert_str(start, &" ".repeat(padding));
+        buf.push(' ');
+    }
+
+    fn write_timestamp<S>(
+        &self,
+        time_context: &TimeContext<'_>,
+        span: &SpanRef<S>,
+        buf: &mut String,
+    ) where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let start = |span: &SpanRef<S>| {
+            let start = span.extensions().get::<Data>().map(|data| data.start);
+            if start.is_none() && !self.config.lenient_spans {
+                panic!("Data cannot be found in extensions");
+            }
+            start
+        };
+        let start = match span.scope().from_root().next() {
+            Some(root) if self.config.elapsed_from_root => start(&root),
+            _ => start(span),
+        };
+        // The elapsed time of spans without data is unknown
+        let Some(start) = start else {
+            return;
+        };
+        let elapsed = self.config.shown_duration(start.elapsed());
+
+        match self.config.theme().elapsed {
+            Some(style) => {
+                write_styled(self.config.ansi, style, buf, |buf| {
+                    self.timer
+                        .style_timestamp_with(time_context, false, elapsed, buf)
+                        .unwrap()
+                });
+            }
+            None => self
+                .timer
+                .style_timestamp_with(time_context, self.config.ansi, elapsed, buf)
+                .unwrap(),
+        }
+    }
+
+    /// The buffers to render the current callback into.
+    ///
+    /// Retracing and span tags depend on what other threads wrote, so these modes render into
+    /// buffers shared by all threads. Otherwise every thread renders into its own buffers and
+    /// threads only synchronize in the writer.
+    fn lock_buffers(&self) -> BuffersGuard<'_> {
+        if self.config.span_retrace || self.config.deferred_spans || self.config.span_tags {
+            BuffersGuard::Shared(self.bufs.lock().unwrap())
+        } else {
+            BuffersGuard::Local(Some(LOCAL_BUFFERS.with(Cell::take).unwrap_or_default()))
+        }
+    }
+
+    /// Whether spans and events of a callsite pass the `max_level` and `target_filter` filters.
+    fn is_enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.config
+            .max_level_of(metadata.target())
+            .map_or(true, |max_level| *metadata.level() <= max_level)
     }
 
     fn is_recursive() -> Option<RecursiveGuard> {
@@ -420,11 +2136,189 @@ where
     }
 }
 
-fn styled(ansi: bool, style: Style, text: impl AsRef<str>) -> String {
+/// Text that is painted when it is written, if colors are enabled.
+struct Styled<T> {
+    ansi: bool,
+    style: Style,
+    text: T,
+}
+
+impl<T: fmt::Display> fmt::Display for Styled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ansi {
+            paint(self.style, &self.text).fmt(f)
+        } else {
+            self.text.fmt(f)
+        }
+    }
+}
+
+fn styled<T: fmt::Display>(ansi: bool, style: Style, text: T) -> Styled<T> {
+    Styled { ansi, style, text }
+}
+
+/// Appends `text` to `buf`, painted if colors are enabled. Plain text is copied over directly
+/// instead of going through the formatting machinery.
+fn push_styled(ansi: bool, buf: &mut String, style: Style, text: &str) {
     if ansi {
-        style.paint(text.as_ref()).to_string()
+        write!(buf, "{}", styled(true, style, text)).expect("Unable to write to buffer");
+    } else {
+        buf.push_str(text);
+    }
+}
+
+fn print_kvs<'a, I, V>(buf: &mut impl fmt::Write, kvs: I) -> fmt::Result
+where
+    I: IntoIterator<Item = (&'a str, V)>,
+    V: fmt::Display,
+{
+    let mut kvs = kvs.into_iter();
+    if let Some((k, v)) = kvs.next() {
+        if k == "message" {
+            write!(buf, "{}", v)?;
+        } else {
+            write!(buf, "{}={}", k, v)?;
+        }
+    }
+    for (k, v) in kvs {
+        write!(buf, ", {}={}", k, v)?;
+    }
+    Ok(())
+}
+
+/// Writes the level, target, name and fields of a span, shared by the layer and the renderer
+/// of captured traces.
+fn write_span_head<'a, V: fmt::Display>(
+    config: &Config,
+    buf: &mut String,
+    level: &Level,
+    target: &str,
+    name: &str,
+    kvs: impl Iterator<Item = (&'a str, V)>,
+) {
+    if config.span_levels {
+        write_level(config, buf, level);
+        buf.push(' ');
+    }
+    if config.targets {
+        push_styled(config.ansi, buf, config.theme().secondary, target);
+        buf.push_str("::");
+    }
+
+    push_styled(config.ansi, buf, config.theme().span_name, name);
+    if config.markdown {
+        for (k, v) in kvs {
+            buf.push(' ');
+            write_code(buf, |buf| write!(buf, "{}={}", k, v).unwrap());
+        }
+    } else {
+        if config.bracketed_fields {
+            push_styled(config.ansi, buf, config.theme().span_name, "{");
+        } else {
+            buf.push(' ');
+        }
+        print_kvs(buf, kvs).unwrap();
+        if config.bracketed_fields {
+            push_styled(config.ansi, buf, config.theme().span_name, "}");
+        }
+    }
+}
+
+/// Writes the level of an event.
+fn write_level(config: &Config, buf: &mut String, level: &Level) {
+    if config.markdown {
+        buf.push_str("**");
+    }
+    if config.short_levels {
+        push_styled(
+            config.ansi,
+            buf,
+            config.theme().level(level),
+            short_level(level),
+        );
+    } else if config.ansi {
+        write!(buf, "{}", ColorLevel(level, &config.theme())).expect("Unable to write to buffer");
     } else {
-        text.as_ref().to_string()
+        buf.push_str(level_name(level));
+    }
+    if config.markdown {
+        buf.push_str("**");
+    }
+}
+
+/// Writes the level of an event, or as many spaces if it is less severe than `level_from`.
+fn write_event_level(config: &Config, buf: &mut String, level: &Level) {
+    let start = buf.len();
+    write_level(config, buf, level);
+    if config.level_from.is_some_and(|from| *level > from) {
+        let width = visible_width(&buf[start..]);
+        buf.truncate(start);
+        if !config.markdown {
+            write!(buf, "{:width$}", "", width = width).unwrap();
+        }
+    }
+}
+
+/// Paints whatever `write` appends to `buf`, if colors are enabled.
+fn write_styled(ansi: bool, style: Style, buf: &mut String, write: impl FnOnce(&mut String)) {
+    let ansi = ansi && !style.is_plain();
+    if ansi {
+        write!(buf, "{}", paint::prefix(style)).unwrap();
+    }
+    write(buf);
+    if ansi {
+        write!(buf, "{}", paint::suffix(style)).unwrap();
+    }
+}
+
+thread_local! {
+    /// The buffers of the current thread, taken out while a callback renders into them.
+    static LOCAL_BUFFERS: Cell<Option<Box<Buffers>>> = const { Cell::new(None) };
+}
+
+enum BuffersGuard<'a> {
+    Shared(MutexGuard<'a, Buffers>),
+    /// Only `None` while being dropped
+    Local(Option<Box<Buffers>>),
+}
+
+impl Deref for BuffersGuard<'_> {
+    type Target = Buffers;
+
+    fn deref(&self) -> &Buffers {
+        match self {
+            BuffersGuard::Shared(bufs) => bufs,
+            BuffersGuard::Local(bufs) => bufs.as_deref().unwrap(),
+        }
+    }
+}
+
+impl DerefMut for BuffersGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Buffers {
+        match self {
+            BuffersGuard::Shared(bufs) => bufs,
+            BuffersGuard::Local(bufs) => bufs.as_deref_mut().unwrap(),
+        }
+    }
+}
+
+impl Drop for BuffersGuard<'_> {
+    fn drop(&mut self) {
+        match self {
+            BuffersGuard::Shared(bufs) => bufs.trim(),
+            BuffersGuard::Local(local) => {
+                let Some(mut bufs) = local.take() else {
+                    return;
+                };
+                // Keep the allocations around for the next callback, but not what a panicking
+                // callback left behind
+                bufs.current_buf.clear();
+                bufs.indent_buf.clear();
+                bufs.record.clear();
+                bufs.trim();
+                LOCAL_BUFFERS.with(|local| local.set(Some(bufs)));
+            }
+        }
     }
 }
 
@@ -443,6 +2337,32 @@ where
     W: for<'writer> MakeWriter<'writer> + 'static,
     FT: FormatTime + 'static,
 {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if self.is_enabled(metadata) {
+            Interest::always()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        self.is_enabled(metadata)
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        let max_level = self.config.max_level?;
+        let levels = self.config.target_filter.iter().map(|&(_, level)| level);
+        levels
+            .chain(Some(max_level))
+            .max()
+            .map(LevelFilter::from_level)
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    fn on_register_dispatch(&self, subscriber: &tracing_core::Dispatch) {
+        let _ = self.dispatch.set(subscriber.downgrade());
+    }
+
     fn on_new_span(&self, attrs: &Attributes, id: &Id, ctx: Context<S>) {
         let Some(_guard) = Self::is_recursive() else {
             return;
@@ -451,7 +2371,20 @@ where
         let span = ctx.span(id).expect("in new_span but span does not exist");
 
         if span.extensions().get::<Data>().is_none() {
-            let data = Data::new(attrs, !self.config.deferred_spans);
+            let mut data = Data::new(attrs, !self.config.deferred_spans, &self.config);
+            if self.config.span_time_range {
+                let depth = scope_path(&span).skip(1).count();
+                data.opened_at = Some(self.wall_clock(span.metadata(), depth));
+            }
+            if let Some(mirror) = &self.output.mirror {
+                let path = scope_path(&span).map(|span| span.name());
+                mirror.span(span.metadata(), path, &data.kvs, None);
+            }
+            #[cfg(feature = "sqlite")]
+            if let Some(sink) = &self.output.sqlite_sink {
+                let parent = span.parent().map(|parent| parent.id());
+                sink.open_span(id, parent.as_ref(), span.metadata(), &data.kvs);
+            }
             span.extensions_mut().insert(data);
         }
 
@@ -460,7 +2393,7 @@ where
             return;
         }
 
-        let bufs = &mut *self.bufs.lock().unwrap();
+        let bufs = &mut *self.lock_buffers();
 
         if self.config.span_retrace {
             self.write_retrace_span(&span, bufs, &ctx, self.config.verbose_entry);
@@ -480,6 +2413,7 @@ where
                 },
             );
         }
+        self.write_record(bufs, span.metadata());
     }
 
     fn on_event(&self, event: &Event<'_>, ctx: Context<S>) {
@@ -491,79 +2425,253 @@ where
         let span_id = span.id();
         let span = span_id.and_then(|id| ctx.span(id));
 
-        let mut guard = self.bufs.lock().unwrap();
+        #[cfg(feature = "tracing-log")]
+        let normalized_meta = event.normalized_metadata();
+        #[cfg(feature = "tracing-log")]
+        let metadata = normalized_meta.as_ref().unwrap_or_else(|| event.metadata());
+        #[cfg(not(feature = "tracing-log"))]
+        let metadata = event.metadata();
+
+        if let Some(summary) = &self.output.summary {
+            summary.lock().unwrap().record_event(metadata.level());
+        }
+        #[cfg(feature = "chrome")]
+        if let Some(trace) = &self.output.chrome_trace {
+            trace.event(metadata, event);
+        }
+        #[cfg(feature = "perfetto")]
+        if let Some(trace) = &self.output.perfetto_trace {
+            let track = span.as_ref().and_then(|span| {
+                let root = scope_path(span).next()?;
+                Some(trace.track(&root))
+            });
+            trace.event(track, metadata, event);
+        }
+        if let Some(hook) = &self.output.metrics_hook {
+            (hook.0)(MetricsEvent {
+                kind: MetricsKind::Event,
+                name: metadata.name(),
+                target: metadata.target(),
+                level: *metadata.level(),
+                duration: None,
+                depth: span.iter().flat_map(scope_path).count(),
+            });
+        }
+        let elapsed = || {
+            span.as_ref().and_then(|span| {
+                let ext = span.extensions();
+                Some(ext.get::<Data>()?.start.elapsed())
+            })
+        };
+        if let Some(mirror) = &self.output.mirror {
+            let path = span.iter().flat_map(scope_path).map(|span| span.name());
+            mirror.event(metadata, path, event, &self.config, elapsed());
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(sink) = &self.output.sqlite_sink {
+            sink.event(span_id, metadata, event, &self.config);
+        }
+        #[cfg(feature = "csv")]
+        if let Some(export) = &self.output.csv_export {
+            let path = span.iter().flat_map(scope_path).map(|span| span.name());
+            export.event(metadata, path, event, &self.config, elapsed());
+        }
+
+        let mut delta = None;
+        let mut events = 0;
+        if let Some(span) = &span {
+            if let Some(data) = span.extensions_mut().get_mut::<Data>() {
+                data.events += 1;
+                events = data.events;
+                if data.events > self.config.max_events_per_span {
+                    return;
+                }
+                if self.config.event_deltas {
+                    let now = Instant::now();
+                    delta = Some(now - data.last_event);
+                    data.last_event = now;
+                }
+            }
+        }
+
+        if let Some(columns) = &self.config.columns {
+            let mut guard = self.lock_buffers();
+            let bufs = &mut *guard;
+            self.write_columns(
+                columns,
+                event,
+                metadata,
+                span.as_ref(),
+                &mut bufs.current_buf,
+            );
+            bufs.flush_current_buf();
+            self.write_record(bufs, metadata);
+            return;
+        }
+
+        // Low severity events are rendered into their span instead of being written right away
+        let hold = match (&span, self.config.quiet_until) {
+            (Some(_), Some(trigger)) => {
+                *metadata.level() > Level::INFO && *metadata.level() > trigger
+            }
+            _ => false,
+        };
+
+        let mut guard = self.lock_buffers();
         let bufs = &mut *guard;
 
         if let Some(new_span) = &span {
-            if self.config.span_retrace || self.config.deferred_spans {
+            if !hold && (self.config.span_retrace || self.config.deferred_spans) {
                 self.write_retrace_span(new_span, bufs, &ctx, self.config.verbose_entry);
             }
         }
 
+        if let (Some(span), Some(trigger)) = (&span, self.config.quiet_until) {
+            if *metadata.level() <= trigger {
+                self.write_held_events(span, bufs);
+            }
+        }
+
+        if let Some(span) = &span {
+            let every = self.config.header_every;
+            if !hold && every > 0 && events > every && (events - 1) % every == 0 {
+                self.write_header_reminder(span, bufs);
+            }
+        }
+
         let mut event_buf = &mut bufs.current_buf;
 
         // Time.
 
-        {
+        let depth = ctx.event_scope(event).map_or(0, |scope| scope.count());
+        let time_context = TimeContext::new(metadata, depth);
+        let display = self.config.time_display;
+        if display == TimeDisplay::ElapsedFirst {
+            self.write_elapsed(&time_context, span.as_ref(), event_buf);
+        }
+        if display.wall_clock() {
             let prev_buffer_len = event_buf.len();
 
-            self.timer
-                .format_time(&mut event_buf)
-                .expect("Unable to write time to buffer");
+            match self.config.theme().wall_clock {
+                Some(style) if self.config.ansi => {
+                    let mut time = String::new();
+                    self.timer
+                        .format_time_with(&time_context, &mut time)
+                        .expect("Unable to write time to buffer");
+                    if self.config.deterministic_output {
+                        mask_digits(&mut time, 0);
+                    }
+                    write!(event_buf, "{}", paint(style, time)).expect("Unable to write to buffer");
+                }
+                _ => {
+                    self.timer
+                        .format_time_with(&time_context, &mut event_buf)
+                        .expect("Unable to write time to buffer");
+                    if self.config.deterministic_output {
+                        mask_digits(event_buf, prev_buffer_len);
+                    }
+                }
+            }
 
             // Something was written to the buffer, pad it with a space.
-            if prev_buffer_len < event_buf.len() {
-                write!(event_buf, " ").expect("Unable to write to buffer");
+            let width = visible_width(&event_buf[prev_buffer_len..]);
+            if width > 0 || self.config.wall_clock_width > 0 {
+                let padding = self.config.wall_clock_width.saturating_sub(width);
+                write!(event_buf, "{:padding$} ", "", padding = padding)
+                    .expect("Unable to write to buffer");
             }
         }
-
-        let deindent = if self.config.indent_lines { 0 } else { 1 };
-        // printing the indentation
-        let indent = ctx
-            .event_scope(event)
-            .map(|scope| scope.count() - deindent)
-            .unwrap_or(0);
-
-        // check if this event occurred in the context of a span.
-        // if it has, get the start time of this span.
-        if let Some(span) = span {
-            self.write_timestamp(span, event_buf);
-            event_buf.push(' ');
+        if display.elapsed() && display != TimeDisplay::ElapsedFirst {
+            self.write_elapsed(&time_context, span.as_ref(), event_buf);
+        }
+        if let Some(delta) = delta {
+            let delta = time::human_duration(self.config.shown_duration(delta));
+            write!(
+                event_buf,
+                "{} ",
+                self.styled(self.config.theme().secondary, format_args!("+{}", delta))
+            )
+            .expect("Unable to write to buffer");
         }
 
-        #[cfg(feature = "tracing-log")]
-        let normalized_meta = event.normalized_metadata();
-        #[cfg(feature = "tracing-log")]
-        let metadata = normalized_meta.as_ref().unwrap_or_else(|| event.metadata());
-        #[cfg(not(feature = "tracing-log"))]
-        let metadata = event.metadata();
-
-        let level = metadata.level();
-        let level = if self.config.ansi {
-            ColorLevel(level).to_string()
+        let deindent = if self.config.indent_lines || self.config.markdown {
+            0
         } else {
-            level.to_string()
+            1
         };
+        // printing the indentation
+        let indent = depth.saturating_sub(deindent);
 
-        write!(&mut event_buf, "{level}", level = level).expect("Unable to write to buffer");
+        if let Some(span) = &span {
+            self.write_span_tag(span, &mut bufs.span_tags, event_buf);
+        }
+
+        write_event_level(&self.config, event_buf, metadata.level());
 
         if self.config.targets {
-            let target = metadata.target();
-            write!(
-                &mut event_buf,
-                " {}",
-                self.styled(Style::new().dimmed(), target,),
-            )
-            .expect("Unable to write to buffer");
+            event_buf.push(' ');
+            self.push_styled(event_buf, self.config.theme().secondary, metadata.target());
         }
 
-        let mut visitor = FmtEvent { comma: false, bufs };
+        let mut visitor = FmtEvent {
+            comma: false,
+            start: bufs.current_buf.len(),
+            bufs,
+            config: &self.config,
+            message: None,
+            #[cfg(feature = "tracing-error")]
+            span_trace: None,
+        };
         event.record(&mut visitor);
+        visitor.finish();
+        if let Some(span) = &span {
+            self.write_thread_migration(span, &mut visitor.bufs.current_buf);
+        }
+        if self.config.error_backtraces && *metadata.level() == Level::ERROR {
+            self.write_backtrace(&mut visitor.bufs.current_buf);
+        }
         visitor
             .bufs
             .indent_current(indent, &self.config, SpanMode::Event);
-        let writer = self.make_writer.make_writer();
-        bufs.flush_current_buf(writer)
+        #[cfg(feature = "tracing-error")]
+        if let Some(span_trace) = visitor.span_trace {
+            self.write_span_trace(&span_trace, bufs, indent + 1);
+        }
+
+        if hold {
+            let line = bufs.take_current_buf();
+            if let Some(span) = &span {
+                if let Some(data) = span.extensions_mut().get_mut::<Data>() {
+                    if data.held.len() == MAX_HELD_EVENTS {
+                        if let Some(oldest) = data.held.pop_front() {
+                            bufs.recycle(oldest);
+                        }
+                        data.held_dropped += 1;
+                    }
+                    data.held.push_back(line);
+                }
+            }
+            return;
+        }
+
+        bufs.flush_current_buf();
+        if let Some(level) = self.config.divider_level {
+            if *metadata.level() <= level {
+                self.write_divider(span.as_ref(), indent, bufs);
+            }
+        }
+        self.write_record(bufs, metadata);
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<S>) {
+        if !self.config.enter_counts {
+            return;
+        }
+        let span = ctx.span(id).expect("in on_enter but span does not exist");
+        let mut ext = span.extensions_mut();
+        if let Some(data) = ext.get_mut::<Data>() {
+            data.enter_count += 1;
+        }
     }
 
     fn on_close(&self, id: Id, ctx: Context<S>) {
@@ -571,19 +2679,101 @@ where
             return;
         };
 
-        let bufs = &mut *self.bufs.lock().unwrap();
+        let bufs = &mut *self.lock_buffers();
 
         let span = ctx.span(&id).expect("invalid span in on_close");
 
-        // Span was not printed, so don't print an exit
-        if self.config.deferred_spans
-            && span.extensions().get::<Data>().map(|v| v.written) != Some(true)
-        {
-            return;
+        // Looking up the track locks the extensions of the root span
+        #[cfg(feature = "perfetto")]
+        let perfetto = self.output.perfetto_trace.as_ref().map(|trace| {
+            let root = scope_path(&span)
+                .next()
+                .expect("a span is part of its scope");
+            (trace, trace.track(&root))
+        });
+
+        if let Some(data) = span.extensions().get::<Data>() {
+            let elapsed = data.start.elapsed();
+            if let Some(summary) = &self.output.summary {
+                let name = self.config.span_name(span.metadata());
+                summary.lock().unwrap().record_span(name, elapsed);
+            }
+            if let Some(span_stats) = &self.output.span_stats {
+                span_stats.record(span.metadata(), elapsed);
+            }
+            if let Some(histograms) = &self.output.histograms {
+                histograms.record(span.metadata(), elapsed);
+            }
+            if let Some(hook) = &self.output.metrics_hook {
+                let metadata = span.metadata();
+                (hook.0)(MetricsEvent {
+                    kind: MetricsKind::SpanClose,
+                    name: metadata.name(),
+                    target: metadata.target(),
+                    level: *metadata.level(),
+                    duration: Some(elapsed),
+                    depth: scope_path(&span).count() - 1,
+                });
+            }
+            #[cfg(feature = "perfetto")]
+            if let Some((trace, track)) = perfetto {
+                trace.span(track, span.metadata(), &data.kvs, data.start, elapsed);
+            }
+            if let Some(mirror) = &self.output.mirror {
+                let path = scope_path(&span).map(|span| span.name());
+                mirror.span(span.metadata(), path, &data.kvs, Some(elapsed));
+            }
+            #[cfg(feature = "sqlite")]
+            if let Some(sink) = &self.output.sqlite_sink {
+                sink.close_span(&id, elapsed.as_micros());
+            }
+            if let Some(stacks) = &self.output.folded_stacks {
+                let names = scope_path(&span).map(|span| span.name());
+                stacks.record(names, elapsed.saturating_sub(data.children));
+                if let Some(parent) = span.parent() {
+                    if let Some(parent) = parent.extensions_mut().get_mut::<Data>() {
+                        parent.children += elapsed;
+                    }
+                }
+            }
+            #[cfg(feature = "chrome")]
+            if let Some(trace) = &self.output.chrome_trace {
+                trace.span(span.metadata(), &data.kvs, data.start, elapsed, data.thread);
+            }
+        }
+
+        let critical_path = if self.config.critical_path {
+            self.track_critical_path(&span)
+        } else {
+            None
+        };
+        let tree_shape = if self.config.tree_stats {
+            self.track_tree_shape(&span)
+        } else {
+            None
+        };
+
+        // Span was not printed, so don't print an exit, unless it has to tell about the events
+        // held back inside of it
+        if self.config.deferred_spans {
+            let (written, held) = span
+                .extensions()
+                .get::<Data>()
+                .map_or((false, false), |data| {
+                    (data.written, !data.held.is_empty() || data.held_dropped > 0)
+                });
+            if !written {
+                if !held {
+                    return;
+                }
+                self.write_retrace_span(&span, bufs, &ctx, self.config.verbose_entry);
+            }
         }
 
         // self.write_retrace_span(&span, bufs, &ctx);
 
+        self.write_suppressed_events(&span, bufs);
+
         self.write_span_info(
             &span,
             bufs,
@@ -592,6 +2782,31 @@ where
             },
         );
 
+        if let Some((elapsed, path)) = critical_path {
+            let path: Vec<_> = path.into_iter().rev().collect();
+            let line = format!(
+                "critical path: {} ({})",
+                path.join(" → "),
+                time::human_duration(self.config.shown_duration(elapsed))
+            );
+            self.write_root_line(bufs, &line);
+        }
+        if let Some(shape) = tree_shape {
+            let line = format!(
+                "tree: {} spans, depth {}, {} events",
+                shape.spans, shape.depth, shape.events
+            );
+            self.write_root_line(bufs, &line);
+        }
+
+        if let Some(tag) = span
+            .extensions_mut()
+            .get_mut::<Data>()
+            .and_then(|data| data.tag.take())
+        {
+            bufs.span_tags.release(tag);
+        }
+
         if let Some(parent_span) = span.parent() {
             bufs.current_span = Some(parent_span.id());
             if self.config.verbose_exit {
@@ -599,7 +2814,12 @@ where
 
                 self.write_span_info(&parent_span, bufs, SpanMode::PostClose);
             }
+        } else if let Some(marker) = self.config.section_marker() {
+            marker
+                .close(&self.section_name(&span), &mut bufs.record)
+                .unwrap();
         }
+        self.write_record(bufs, span.metadata());
     }
 }
 
@@ -607,6 +2827,12 @@ fn scope_path<'a, R: LookupSpan<'a>>(span: &SpanRef<'a, R>) -> ScopeFromRoot<'a,
     span.scope().from_root()
 }
 
+/// Whether one of the spans contains the other, so that switching between them moves up or down
+/// a single branch of the tree.
+fn is_related<'a, R: LookupSpan<'a>>(a: &SpanRef<'a, R>, b: &SpanRef<'a, R>) -> bool {
+    a.scope().any(|span| span.id() == b.id()) || b.scope().any(|span| span.id() == a.id())
+}
+
 /// Runs `A` and `B` side by side and only yields items present in `B`
 struct DifferenceIter<L, R, F> {
     left: Fuse<L>,
@@ -0,0 +1,113 @@
+//! Rendering captured [`SpanTree`]s as [Mermaid](https://mermaid.js.org) diagrams.
+//!
+//! Combined with [`Builder::on_root`], every root span can be written out as a diagram as soon
+//! as it closes:
+//!
+//! ```
+//! use tracing::info_span;
+//! use tracing_subscriber::{layer::SubscriberExt, Registry};
+//! use tracing_tree::{mermaid, tree::Builder};
+//!
+//! let builder = Builder::new().on_root(|tree| println!("{}", mermaid::gantt(tree)));
+//! let subscriber = Registry::default().with(builder);
+//! tracing::subscriber::with_default(subscriber, || {
+//!     info_span!("server").in_scope(|| info_span!("conn").in_scope(|| {}));
+//! });
+//! ```
+//!
+//! [`Builder::on_root`]: crate::tree::Builder::on_root
+
+use std::fmt::Write as _;
+
+use crate::tree::SpanTree;
+
+/// Renders `tree` as a top-down `flowchart`, with one node per span, labeled with its name
+/// and fields.
+///
+/// ```text
+/// flowchart TD
+///     s0["server port=8080"]
+///     s1["conn peer=1"]
+///     s0 --> s1
+/// ```
+pub fn flowchart(tree: &SpanTree) -> String {
+    let mut buf = String::from("flowchart TD\n");
+    let mut edges = String::new();
+    let mut next = 0;
+    write_node(tree, &mut next, &mut buf, &mut edges);
+    buf.push_str(&edges);
+    buf
+}
+
+/// Writes the node of `span` and its descendants, returning the id of `span`.
+fn write_node(span: &SpanTree, next: &mut usize, nodes: &mut String, edges: &mut String) -> usize {
+    let id = *next;
+    *next += 1;
+    write!(nodes, "    s{}[\"", id).unwrap();
+    label(nodes, span.name);
+    for (name, value) in &span.fields {
+        nodes.push(' ');
+        label(nodes, name);
+        nodes.push('=');
+        label(nodes, value);
+    }
+    nodes.push_str("\"]\n");
+    for child in span.spans() {
+        let child_id = write_node(child, next, nodes, edges);
+        writeln!(edges, "    s{} --> s{}", id, child_id).unwrap();
+    }
+    id
+}
+
+/// Escapes text inside of a quoted flowchart label.
+fn label(buf: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '"' => buf.push_str("#quot;"),
+            '\n' | '\r' => buf.push(' '),
+            c => buf.push(c),
+        }
+    }
+}
+
+/// Renders `tree` as a `gantt` chart, with one bar per span from when it was opened to when
+/// it was closed, in milliseconds since the root span was opened.
+///
+/// Bars are at least one millisecond long, so that fast spans stay visible.
+///
+/// ```text
+/// gantt
+///     dateFormat x
+///     axisFormat %S.%L
+///     section server
+///     server :s0, 0, 12
+///     conn :s1, 2, 10
+/// ```
+pub fn gantt(tree: &SpanTree) -> String {
+    let mut buf = String::from("gantt\n    dateFormat x\n    axisFormat %S.%L\n    section ");
+    task_name(&mut buf, tree.name);
+    buf.push('\n');
+    let mut next = 0;
+    write_task(tree, &mut next, &mut buf);
+    buf
+}
+
+fn write_task(span: &SpanTree, next: &mut usize, buf: &mut String) {
+    let start = span.start.as_millis();
+    let end = (span.start + span.duration).as_millis().max(start + 1);
+    buf.push_str("    ");
+    task_name(buf, span.name);
+    writeln!(buf, " :s{}, {}, {}", next, start, end).unwrap();
+    *next += 1;
+    for child in span.spans() {
+        write_task(child, next, buf);
+    }
+}
+
+/// Writes a task or section name, which ends at the first colon or line break.
+fn task_name(buf: &mut String, text: &str) {
+    buf.extend(text.chars().map(|c| match c {
+        ':' | ';' | '#' | '\n' | '\r' => ' ',
+        c => c,
+    }));
+}
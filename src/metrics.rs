@@ -0,0 +1,33 @@
+//! What the metrics hook of a layer is called with.
+
+use std::time::Duration;
+
+use tracing_core::Level;
+
+/// Whether a [`MetricsEvent`] is about a span or an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetricsKind {
+    /// A span was closed.
+    SpanClose,
+    /// An event occurred.
+    Event,
+}
+
+/// A closed span or an event, passed to the hook of
+/// [`HierarchicalLayer::with_metrics_hook`](crate::HierarchicalLayer::with_metrics_hook).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MetricsEvent<'a> {
+    /// Whether a span was closed or an event occurred.
+    pub kind: MetricsKind,
+    /// The name of the span or event.
+    pub name: &'a str,
+    /// The target of the span or event.
+    pub target: &'a str,
+    /// The level of the span or event.
+    pub level: Level,
+    /// The time between opening and closing a span, `None` for events.
+    pub duration: Option<Duration>,
+    /// The number of spans around the span or event.
+    pub depth: usize,
+}
@@ -0,0 +1,139 @@
+//! JSON lines of the records rendered in the tree, for machines.
+
+use std::{
+    fmt::{self, Write as _},
+    io::Write as _,
+    time::Duration,
+};
+
+use tracing_core::{Event, Metadata};
+use tracing_subscriber::fmt::{writer::BoxMakeWriter, MakeWriter};
+
+use crate::{
+    fields::{self, Fields},
+    format::Config,
+    json::string,
+};
+
+/// The writer of [`HierarchicalLayer::with_structured_mirror`].
+///
+/// Every line is one JSON object with a `type` of `span_open`, `event` or `span_close`, the
+/// `level` and `target` of the span or event, and the `path` of span names from the root.
+/// Spans carry their `name`, and events and closed spans the `elapsed` microseconds since
+/// their span was opened. Field values are formatted as they are in the tree.
+///
+/// [`HierarchicalLayer::with_structured_mirror`]: crate::HierarchicalLayer::with_structured_mirror
+pub(crate) struct Mirror {
+    make_writer: BoxMakeWriter,
+}
+
+impl fmt::Debug for Mirror {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mirror").finish_non_exhaustive()
+    }
+}
+
+impl Mirror {
+    pub(crate) fn new<M>(make_writer: M) -> Self
+    where
+        M: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        Self {
+            make_writer: BoxMakeWriter::new(make_writer),
+        }
+    }
+
+    /// Writes the opening (`elapsed` is `None`) or closing of a span.
+    pub(crate) fn span<'a>(
+        &self,
+        metadata: &Metadata<'_>,
+        path: impl Iterator<Item = &'a str>,
+        fields: &Fields,
+        elapsed: Option<Duration>,
+    ) {
+        let kind = if elapsed.is_some() {
+            "span_close"
+        } else {
+            "span_open"
+        };
+        let mut buf = header(kind, metadata, path);
+        buf.push_str(",\"name\":");
+        string(&mut buf, metadata.name());
+        if elapsed.is_none() {
+            buf.push_str(",\"fields\":{");
+            for (i, (name, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    buf.push(',');
+                }
+                string(&mut buf, name);
+                buf.push(':');
+                string(&mut buf, value);
+            }
+            buf.push('}');
+        }
+        self.write(metadata, buf, elapsed);
+    }
+
+    /// Writes an event that occurred `elapsed` after its span was opened.
+    pub(crate) fn event<'a>(
+        &self,
+        metadata: &Metadata<'_>,
+        path: impl Iterator<Item = &'a str>,
+        event: &Event<'_>,
+        config: &Config,
+        elapsed: Option<Duration>,
+    ) {
+        let mut buf = header("event", metadata, path);
+        buf.push_str(",\"fields\":{");
+        let mut comma = false;
+        fields::flatten(event, config, |name, value| {
+            if comma {
+                buf.push(',');
+            }
+            comma = true;
+            string(&mut buf, name);
+            buf.push(':');
+            string(&mut buf, value);
+        });
+        buf.push('}');
+        self.write(metadata, buf, elapsed);
+    }
+
+    fn write(&self, metadata: &Metadata<'_>, mut buf: String, elapsed: Option<Duration>) {
+        if let Some(elapsed) = elapsed {
+            write!(buf, ",\"elapsed\":{}", elapsed.as_micros()).unwrap();
+        }
+        buf.push_str("}\n");
+        // The mirror is a side channel, failing to write it must not affect the tree
+        let _ = self
+            .make_writer
+            .make_writer_for(metadata)
+            .write_all(buf.as_bytes());
+    }
+
+    pub(crate) fn flush(&self) -> std::io::Result<()> {
+        self.make_writer.make_writer().flush()
+    }
+}
+
+/// Starts the object of a line, with the members every line has.
+fn header<'a>(kind: &str, metadata: &Metadata<'_>, path: impl Iterator<Item = &'a str>) -> String {
+    let mut buf = String::new();
+    write!(
+        buf,
+        "{{\"type\":\"{}\",\"level\":\"{}\",\"target\":",
+        kind,
+        metadata.level()
+    )
+    .unwrap();
+    string(&mut buf, metadata.target());
+    buf.push_str(",\"path\":[");
+    for (i, name) in path.enumerate() {
+        if i > 0 {
+            buf.push(',');
+        }
+        string(&mut buf, name);
+    }
+    buf.push(']');
+    buf
+}
@@ -0,0 +1,23 @@
+//! Reading the OpenTelemetry context that `tracing-opentelemetry` attaches to spans.
+
+use opentelemetry::trace::{TraceContextExt, TraceId};
+use tracing_core::{dispatcher::WeakDispatch, span::Id};
+
+/// How many hex digits of a trace id are rendered, like a short git hash.
+const SHORT_LEN: usize = 8;
+
+/// The first digits of the trace id of the span `id`, if it belongs to a valid trace.
+///
+/// This locks the extensions of the span, so they must not be borrowed by the caller.
+pub(crate) fn short_trace_id(dispatch: &WeakDispatch, id: &Id) -> Option<String> {
+    // The default dispatcher can't be used, as it is not available while it is dispatching
+    let dispatch = dispatch.upgrade()?;
+    let cx = tracing_opentelemetry::get_otel_context(id, &dispatch)?;
+    let trace_id = cx.span().span_context().trace_id();
+    if trace_id == TraceId::INVALID {
+        return None;
+    }
+    let mut hex = trace_id.to_string();
+    hex.truncate(SHORT_LEN);
+    Some(hex)
+}
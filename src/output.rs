@@ -0,0 +1,176 @@
+use std::{
+    io::{self, Write as _},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Mutex,
+    },
+};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+#[cfg(feature = "chrome")]
+use crate::chrome::ChromeTrace;
+#[cfg(feature = "csv")]
+use crate::csv::CsvExport;
+#[cfg(feature = "perfetto")]
+use crate::perfetto::PerfettoTrace;
+#[cfg(feature = "sqlite")]
+use crate::sqlite::SqliteSink;
+use crate::{
+    flame::FoldedStacks, format::Hook, histogram::Histograms, metrics::MetricsEvent,
+    mirror::Mirror, stats::SpanStats, summary::Summary,
+};
+
+pub(crate) type MetricsHookFn = dyn Fn(MetricsEvent<'_>) + Send + Sync;
+
+/// The writer of a layer, along with everything that has to be written when the layer is
+/// dropped.
+#[derive(Debug)]
+pub(crate) struct Output<W>
+where
+    W: for<'writer> MakeWriter<'writer> + 'static,
+{
+    pub(crate) make_writer: W,
+    /// Statistics printed by `report` and on drop, if enabled
+    pub(crate) summary: Option<Mutex<Summary>>,
+    /// Timing statistics per callsite, included in the summary
+    pub(crate) span_stats: Option<SpanStats>,
+    /// Latency histograms per callsite, included in the summary
+    pub(crate) histograms: Option<Histograms>,
+    /// Called for every closed span and event, if set
+    pub(crate) metrics_hook: Option<Hook<MetricsHookFn>>,
+    /// Self time per stack of spans, if enabled
+    pub(crate) folded_stacks: Option<FoldedStacks>,
+    /// A trace of the spans and events, if enabled
+    #[cfg(feature = "chrome")]
+    pub(crate) chrome_trace: Option<ChromeTrace>,
+    /// A Perfetto trace of the spans and events, if enabled
+    #[cfg(feature = "perfetto")]
+    pub(crate) perfetto_trace: Option<PerfettoTrace>,
+    /// JSON lines of the records, if enabled
+    pub(crate) mirror: Option<Mirror>,
+    /// Rows of comma separated values of the events, if enabled
+    #[cfg(feature = "csv")]
+    pub(crate) csv_export: Option<CsvExport>,
+    /// A database of the spans and events, if enabled
+    #[cfg(feature = "sqlite")]
+    pub(crate) sqlite_sink: Option<SqliteSink>,
+    /// Failed writes, if counted
+    pub(crate) write_errors: AtomicUsize,
+    /// Whether a root span was opened already, to separate the next one from it
+    pub(crate) root_opened: AtomicBool,
+    /// Whether records are written to stderr through `anstream`, which is the case for the
+    /// default writer `fn() -> io::Stderr`
+    #[cfg(feature = "anstream")]
+    pub(crate) auto_stderr: bool,
+}
+
+impl<W> Output<W>
+where
+    W: for<'writer> MakeWriter<'writer> + 'static,
+{
+    pub(crate) fn new(make_writer: W) -> Self {
+        Self {
+            make_writer,
+            summary: None,
+            span_stats: None,
+            histograms: None,
+            metrics_hook: None,
+            folded_stacks: None,
+            #[cfg(feature = "perfetto")]
+            perfetto_trace: None,
+            mirror: None,
+            #[cfg(feature = "csv")]
+            csv_export: None,
+            #[cfg(feature = "sqlite")]
+            sqlite_sink: None,
+            #[cfg(feature = "chrome")]
+            chrome_trace: None,
+            write_errors: AtomicUsize::new(0),
+            root_opened: AtomicBool::new(false),
+            #[cfg(feature = "anstream")]
+            auto_stderr: is_default_writer::<W>(),
+        }
+    }
+
+    /// Moves everything but the writer over to a new output.
+    pub(crate) fn with_writer<W2>(mut self, make_writer: W2) -> Output<W2>
+    where
+        W2: for<'writer> MakeWriter<'writer> + 'static,
+    {
+        Output {
+            make_writer,
+            summary: self.summary.take(),
+            span_stats: self.span_stats.take(),
+            histograms: self.histograms.take(),
+            metrics_hook: self.metrics_hook.take(),
+            folded_stacks: self.folded_stacks.take(),
+            #[cfg(feature = "perfetto")]
+            perfetto_trace: self.perfetto_trace.take(),
+            mirror: self.mirror.take(),
+            #[cfg(feature = "csv")]
+            csv_export: self.csv_export.take(),
+            #[cfg(feature = "sqlite")]
+            sqlite_sink: self.sqlite_sink.take(),
+            #[cfg(feature = "chrome")]
+            chrome_trace: self.chrome_trace.take(),
+            write_errors: AtomicUsize::new(*self.write_errors.get_mut()),
+            root_opened: AtomicBool::new(*self.root_opened.get_mut()),
+            #[cfg(feature = "anstream")]
+            auto_stderr: is_default_writer::<W2>(),
+        }
+    }
+
+    /// Flushes the underlying writer, the mirror and the CSV export, and commits the SQLite
+    /// database.
+    pub(crate) fn flush(&self) -> io::Result<()> {
+        #[cfg(feature = "sqlite")]
+        if let Some(sink) = &self.sqlite_sink {
+            sink.flush()
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        }
+        if let Some(mirror) = &self.mirror {
+            mirror.flush()?;
+        }
+        #[cfg(feature = "csv")]
+        if let Some(export) = &self.csv_export {
+            export.flush()?;
+        }
+        self.make_writer.make_writer().flush()
+    }
+
+    pub(crate) fn write_summary(&self) {
+        let Some(summary) = &self.summary else {
+            return;
+        };
+        let mut buf = String::new();
+        summary
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .render(&mut buf);
+        if let Some(span_stats) = &self.span_stats {
+            span_stats.render(&mut buf);
+        }
+        if let Some(histograms) = &self.histograms {
+            histograms.render(&mut buf);
+        }
+        // There is nobody left to report a failure to
+        let _ = self.make_writer.make_writer().write_all(buf.as_bytes());
+    }
+}
+
+#[cfg(feature = "anstream")]
+fn is_default_writer<W: 'static>() -> bool {
+    std::any::TypeId::of::<W>() == std::any::TypeId::of::<fn() -> io::Stderr>()
+}
+
+impl<W> Drop for Output<W>
+where
+    W: for<'writer> MakeWriter<'writer> + 'static,
+{
+    fn drop(&mut self) {
+        self.write_summary();
+        // There is nobody left to report a failure to
+        let _ = self.flush();
+    }
+}
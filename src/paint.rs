@@ -0,0 +1,110 @@
+//! Renders the escape sequences of styles, with `nu-ansi-term` or, if the `anstream` feature is
+//! enabled, with `anstyle`.
+
+use std::fmt;
+
+use nu_ansi_term::Style;
+
+/// Text surrounded by the escape sequences of a style.
+pub(crate) struct Painted<T> {
+    style: Style,
+    text: T,
+}
+
+/// Paints `text` with `style`, regardless of whether colors are enabled.
+pub(crate) fn paint<T: fmt::Display>(style: Style, text: T) -> Painted<T> {
+    Painted { style, text }
+}
+
+impl<T: fmt::Display> fmt::Display for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.style.is_plain() {
+            return self.text.fmt(f);
+        }
+        write!(
+            f,
+            "{}{}{}",
+            prefix(self.style),
+            self.text,
+            suffix(self.style)
+        )
+    }
+}
+
+/// The escape sequence that starts `style`.
+#[cfg(not(feature = "anstream"))]
+pub(crate) fn prefix(style: Style) -> impl fmt::Display {
+    style.prefix()
+}
+
+/// The escape sequence that ends `style`.
+#[cfg(not(feature = "anstream"))]
+pub(crate) fn suffix(style: Style) -> impl fmt::Display {
+    style.suffix()
+}
+
+/// The escape sequence that starts `style`.
+#[cfg(feature = "anstream")]
+pub(crate) fn prefix(style: Style) -> impl fmt::Display {
+    to_anstyle(style).render()
+}
+
+/// The escape sequence that ends `style`.
+#[cfg(feature = "anstream")]
+pub(crate) fn suffix(style: Style) -> impl fmt::Display {
+    to_anstyle(style).render_reset()
+}
+
+#[cfg(feature = "anstream")]
+fn to_anstyle(style: Style) -> anstyle::Style {
+    use anstyle::Effects;
+
+    let effects = [
+        (style.is_bold, Effects::BOLD),
+        (style.is_dimmed, Effects::DIMMED),
+        (style.is_italic, Effects::ITALIC),
+        (style.is_underline, Effects::UNDERLINE),
+        (style.is_blink, Effects::BLINK),
+        (style.is_reverse, Effects::INVERT),
+        (style.is_hidden, Effects::HIDDEN),
+        (style.is_strikethrough, Effects::STRIKETHROUGH),
+    ];
+    let effects = effects
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .fold(Effects::new(), |effects, &(_, effect)| effects | effect);
+    anstyle::Style::new()
+        .fg_color(style.foreground.and_then(to_anstyle_color))
+        .bg_color(style.background.and_then(to_anstyle_color))
+        .effects(effects)
+}
+
+/// The `anstyle` color with the same escape code, or `None` for the terminal's default color.
+#[cfg(feature = "anstream")]
+fn to_anstyle_color(color: nu_ansi_term::Color) -> Option<anstyle::Color> {
+    use anstyle::{Ansi256Color, AnsiColor, RgbColor};
+    use nu_ansi_term::Color;
+
+    let ansi = match color {
+        Color::Black => AnsiColor::Black,
+        Color::Red => AnsiColor::Red,
+        Color::Green => AnsiColor::Green,
+        Color::Yellow => AnsiColor::Yellow,
+        Color::Blue => AnsiColor::Blue,
+        Color::Purple | Color::Magenta => AnsiColor::Magenta,
+        Color::Cyan => AnsiColor::Cyan,
+        Color::White => AnsiColor::White,
+        Color::DarkGray => AnsiColor::BrightBlack,
+        Color::LightRed => AnsiColor::BrightRed,
+        Color::LightGreen => AnsiColor::BrightGreen,
+        Color::LightYellow => AnsiColor::BrightYellow,
+        Color::LightBlue => AnsiColor::BrightBlue,
+        Color::LightPurple | Color::LightMagenta => AnsiColor::BrightMagenta,
+        Color::LightCyan => AnsiColor::BrightCyan,
+        Color::LightGray => AnsiColor::BrightWhite,
+        Color::Fixed(n) => return Some(Ansi256Color(n).into()),
+        Color::Rgb(r, g, b) => return Some(RgbColor(r, g, b).into()),
+        Color::Default => return None,
+    };
+    Some(ansi.into())
+}
@@ -0,0 +1,328 @@
+//! A trace of spans and events in the protobuf format of [Perfetto](https://ui.perfetto.dev),
+//! written alongside the tree.
+//!
+//! Every root span gets a track of its own, so concurrent tasks don't overlap. Its spans
+//! become nested slices on that track and its events instant events. Events outside of any
+//! span end up on a track of their thread.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use tracing_subscriber::{layer::SubscriberExt, Registry};
+//! use tracing_tree::{perfetto::PerfettoTrace, HierarchicalLayer};
+//!
+//! let trace = PerfettoTrace::new(File::create("trace.perfetto-trace").unwrap());
+//! let layer = HierarchicalLayer::default().with_perfetto_trace(trace.clone());
+//! let subscriber = Registry::default().with(layer);
+//! // ... install the subscriber and do some work ...
+//!
+//! trace.flush().unwrap();
+//! ```
+
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fmt,
+    io::{self, Write as _},
+    sync::{Arc, Mutex, MutexGuard},
+    thread::{self, ThreadId},
+    time::Duration,
+};
+
+use tracing_core::{
+    field::{Field, Visit},
+    Event, Metadata, Subscriber,
+};
+use tracing_subscriber::registry::{LookupSpan, SpanRef};
+
+use crate::{clock::Instant, fields::Fields};
+
+/// The uuid of the track of the process, which all other tracks are children of.
+const PROCESS_TRACK: u64 = 1;
+
+/// A handle to a trace written by a [`HierarchicalLayer`].
+///
+/// Clones write to the same trace, so a clone can be handed to
+/// [`HierarchicalLayer::with_perfetto_trace`] while the original is kept to flush the trace.
+///
+/// [`HierarchicalLayer`]: crate::HierarchicalLayer
+/// [`HierarchicalLayer::with_perfetto_trace`]: crate::HierarchicalLayer::with_perfetto_trace
+#[derive(Clone)]
+pub struct PerfettoTrace {
+    inner: Arc<Mutex<Trace>>,
+}
+
+struct Trace {
+    writer: Box<dyn io::Write + Send>,
+    /// The time stamps of the trace are relative to this instant
+    origin: Instant,
+    /// The tracks of threads, for events outside of any span
+    threads: HashMap<ThreadId, u64>,
+    /// The uuid of the next track
+    next_track: u64,
+    /// Whether the first packet has been written
+    started: bool,
+}
+
+/// The track of a root span, stored in its extensions.
+struct Track(u64);
+
+impl fmt::Debug for PerfettoTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PerfettoTrace").finish_non_exhaustive()
+    }
+}
+
+impl PerfettoTrace {
+    /// Writes a trace to `writer`, e.g. a file. The writer is buffered internally.
+    pub fn new<W: io::Write + Send + 'static>(writer: W) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Trace {
+                writer: Box::new(io::BufWriter::new(writer)),
+                origin: Instant::now(),
+                threads: HashMap::new(),
+                next_track: PROCESS_TRACK + 1,
+                started: false,
+            })),
+        }
+    }
+
+    /// Flushes the packets written so far to the underlying writer.
+    pub fn flush(&self) -> io::Result<()> {
+        self.lock().writer.flush()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Trace> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// The track of the root span `root`, which is created on first use.
+    ///
+    /// This locks the extensions of `root`, so they must not be borrowed by the caller.
+    pub(crate) fn track<S>(&self, root: &SpanRef<'_, S>) -> u64
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let mut ext = root.extensions_mut();
+        if let Some(Track(uuid)) = ext.get_mut::<Track>() {
+            return *uuid;
+        }
+        let mut trace = self.lock();
+        let uuid = trace.new_track();
+        let mut descriptor = Vec::new();
+        descriptor.varint(1, uuid);
+        descriptor.string(2, root.name());
+        descriptor.varint(5, PROCESS_TRACK);
+        trace.write(|packet| packet.message(60, &descriptor));
+        ext.insert(Track(uuid));
+        uuid
+    }
+
+    /// Writes a closed span that was opened at `start`.
+    pub(crate) fn span(
+        &self,
+        track: u64,
+        metadata: &Metadata<'_>,
+        fields: &Fields,
+        start: Instant,
+        elapsed: Duration,
+    ) {
+        let mut trace = self.lock();
+        let begin = start - trace.origin;
+        let mut event = track_event(SLICE_BEGIN, track, metadata.name(), metadata);
+        for (name, value) in fields.iter() {
+            event.message(4, &debug_annotation(name, value));
+        }
+        trace.write(|packet| {
+            packet.varint(8, nanos(begin));
+            packet.message(11, &event);
+        });
+        let event = track_event(SLICE_END, track, "", metadata);
+        trace.write(|packet| {
+            packet.varint(8, nanos(begin + elapsed));
+            packet.message(11, &event);
+        });
+    }
+
+    /// Writes an event that occurred just now, on `track` or the track of the current thread.
+    pub(crate) fn event(&self, track: Option<u64>, metadata: &Metadata<'_>, event: &Event<'_>) {
+        let mut args = Annotations {
+            message: String::new(),
+            annotations: Vec::new(),
+        };
+        event.record(&mut args);
+
+        let mut trace = self.lock();
+        let ts = trace.origin.elapsed();
+        let track = match track {
+            Some(track) => track,
+            None => trace.thread_track(),
+        };
+        let name = if args.message.is_empty() {
+            metadata.name()
+        } else {
+            &args.message
+        };
+        let mut encoded = track_event(INSTANT, track, name, metadata);
+        encoded.message(4, &debug_annotation("level", metadata.level().as_str()));
+        encoded.extend_from_slice(&args.annotations);
+        trace.write(|packet| {
+            packet.varint(8, nanos(ts));
+            packet.message(11, &encoded);
+        });
+    }
+}
+
+impl Trace {
+    fn new_track(&mut self) -> u64 {
+        let uuid = self.next_track;
+        self.next_track += 1;
+        uuid
+    }
+
+    fn thread_track(&mut self) -> u64 {
+        let current = thread::current();
+        if let Some(&uuid) = self.threads.get(&current.id()) {
+            return uuid;
+        }
+        let uuid = self.new_track();
+        let mut thread = Vec::new();
+        thread.varint(1, u64::from(std::process::id()));
+        // Perfetto wants a number, which `ThreadId` can't be turned into
+        thread.varint(2, uuid);
+        if let Some(name) = current.name() {
+            thread.string(5, name);
+        }
+        let mut descriptor = Vec::new();
+        descriptor.varint(1, uuid);
+        descriptor.varint(5, PROCESS_TRACK);
+        descriptor.message(4, &thread);
+        self.write(|packet| packet.message(60, &descriptor));
+        self.threads.insert(current.id(), uuid);
+        uuid
+    }
+
+    /// Writes a `TracePacket`, whose fields are added by `fill`.
+    fn write(&mut self, fill: impl FnOnce(&mut Vec<u8>)) {
+        let mut packet = Vec::new();
+        // All packets belong to the same sequence
+        packet.varint(10, 1);
+        if !self.started {
+            self.started = true;
+            // SEQ_INCREMENTAL_STATE_CLEARED
+            packet.varint(13, 1);
+            let mut process = Vec::new();
+            process.varint(1, u64::from(std::process::id()));
+            let mut descriptor = Vec::new();
+            descriptor.varint(1, PROCESS_TRACK);
+            descriptor.message(3, &process);
+            packet.message(60, &descriptor);
+            self.write_packet(&packet);
+            packet.clear();
+            packet.varint(10, 1);
+        }
+        fill(&mut packet);
+        self.write_packet(&packet);
+    }
+
+    fn write_packet(&mut self, packet: &[u8]) {
+        // A trace is a sequence of `Trace.packet` fields
+        let mut buf = Vec::with_capacity(packet.len() + 4);
+        buf.message(1, packet);
+        // Failing to write the trace must not take down the application
+        let _ = self.writer.write_all(&buf);
+    }
+}
+
+impl Drop for Trace {
+    fn drop(&mut self) {
+        // There is nobody left to report a failure to
+        let _ = self.writer.flush();
+    }
+}
+
+const SLICE_BEGIN: u64 = 1;
+const SLICE_END: u64 = 2;
+const INSTANT: u64 = 3;
+
+/// Encodes a `TrackEvent` of the given type, leaving room for annotations.
+fn track_event(kind: u64, track: u64, name: &str, metadata: &Metadata<'_>) -> Vec<u8> {
+    let mut event = Vec::new();
+    event.varint(9, kind);
+    event.varint(11, track);
+    if kind != SLICE_END {
+        event.string(22, metadata.target());
+        event.string(23, name);
+    }
+    event
+}
+
+/// Encodes a `DebugAnnotation` with a string value.
+fn debug_annotation(name: &str, value: &str) -> Vec<u8> {
+    let mut annotation = Vec::new();
+    annotation.string(10, name);
+    annotation.string(6, value);
+    annotation
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// Just enough of the protobuf wire format.
+trait Protobuf {
+    fn raw_varint(&mut self, value: u64);
+
+    fn varint(&mut self, field: u64, value: u64) {
+        self.raw_varint(field << 3);
+        self.raw_varint(value);
+    }
+
+    fn bytes(&mut self, field: u64, value: &[u8]);
+
+    fn string(&mut self, field: u64, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+
+    fn message(&mut self, field: u64, encoded: &[u8]) {
+        self.bytes(field, encoded);
+    }
+}
+
+impl Protobuf for Vec<u8> {
+    fn raw_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.push(value as u8);
+    }
+
+    fn bytes(&mut self, field: u64, value: &[u8]) {
+        // Length delimited
+        self.raw_varint(field << 3 | 2);
+        self.raw_varint(value.len() as u64);
+        self.extend_from_slice(value);
+    }
+}
+
+/// Collects the fields of an event as encoded debug annotations.
+struct Annotations {
+    message: String,
+    annotations: Vec<u8>,
+}
+
+impl Visit for Annotations {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message.push_str(value),
+            // Skip fields that are actually log metadata
+            #[cfg(feature = "tracing-log")]
+            name if name.starts_with("log.") => {}
+            name => self.annotations.message(4, &debug_annotation(name, value)),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
@@ -0,0 +1,399 @@
+//! Rendering captured traces as a tree again, long after they were recorded.
+//!
+//! Records come from the JSON lines of
+//! [`HierarchicalLayer::with_structured_mirror`](crate::HierarchicalLayer::with_structured_mirror)
+//! or from a [`SpanTree`] captured in memory, and are rendered with any [`Config`], e.g. to
+//! look at a plain production capture as a colored tree locally.
+//!
+//! ```
+//! use tracing_tree::{render::Renderer, Config};
+//!
+//! let capture = r#"{"type":"span_open","level":"INFO","target":"app","path":["server"],"name":"server","fields":{"port":"8080"}}
+//! {"type":"event","level":"INFO","target":"app","path":["server"],"fields":{"message":"listening"},"elapsed":1200}
+//! {"type":"span_close","level":"INFO","target":"app","path":["server"],"name":"server","elapsed":5000}
+//! "#;
+//!
+//! let mut out = Vec::new();
+//! Renderer::new(Config::default().with_ansi(false).with_indent_lines(true))
+//!     .replay(capture.as_bytes(), &mut out)
+//!     .unwrap();
+//! assert_eq!(
+//!     String::from_utf8(out).unwrap(),
+//!     "┐server port=8080\n├─ INFO listening\n┘\n",
+//! );
+//! ```
+//!
+//! Only the layout of the records can be changed. The wall-clock time of events was not
+//! captured, so it is never rendered, and the elapsed time is formatted by the timer of the
+//! [`Renderer`] instead of the one of the layer.
+
+use std::{error::Error, fmt, io, str::FromStr, time::Duration};
+
+use tracing_core::Level;
+
+use crate::{
+    format::{
+        finish_event_fields, visible_width, write_event_field, write_span_mode, Buffers, Config,
+        SpanMode,
+    },
+    json::{self, Value},
+    push_styled,
+    time::FormatTime,
+    tree::{Node, SpanTree},
+    write_event_level, write_span_head, write_styled,
+};
+
+/// What a [`Record`] describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordKind {
+    /// A span was opened.
+    SpanOpen,
+    /// An event occurred.
+    Event,
+    /// A span was closed.
+    SpanClose,
+}
+
+/// A single span or event of a captured trace, with its fields formatted already.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Record {
+    /// What happened.
+    pub kind: RecordKind,
+    /// The level of the span or event.
+    pub level: Level,
+    /// The target of the span or event.
+    pub target: String,
+    /// The names of the spans from the root, ending with the span itself for span records
+    /// and with the span an event occurred in for events.
+    pub path: Vec<String>,
+    /// The fields of an opened span or of an event, in the order they were recorded.
+    pub fields: Vec<(String, String)>,
+    /// For events the time since their span was opened, for closed spans the time they
+    /// were open.
+    pub elapsed: Option<Duration>,
+}
+
+/// A line that is not a record of the structured mirror.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    reason: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid record: {}", self.reason)
+    }
+}
+
+impl Error for ParseError {}
+
+impl FromStr for Record {
+    type Err = ParseError;
+
+    /// Parses a line written by
+    /// [`HierarchicalLayer::with_structured_mirror`](crate::HierarchicalLayer::with_structured_mirror).
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseError { reason };
+        let value = json::parse(line).map_err(error)?;
+        let member = |key| value.get(key).ok_or(error("missing member"));
+        let string = |key| member(key)?.as_str().ok_or(error("expected a string"));
+
+        let kind = match string("type")? {
+            "span_open" => RecordKind::SpanOpen,
+            "event" => RecordKind::Event,
+            "span_close" => RecordKind::SpanClose,
+            _ => return Err(error("unknown record type")),
+        };
+        let level = string("level")?
+            .parse()
+            .map_err(|_| error("unknown level"))?;
+        let path = match member("path")? {
+            Value::Array(names) => names
+                .iter()
+                .map(|name| name.as_str().map(str::to_owned))
+                .collect::<Option<_>>()
+                .ok_or(error("expected span names in the path"))?,
+            _ => return Err(error("expected an array")),
+        };
+        let fields = match value.get("fields") {
+            Some(Value::Object(fields)) => fields
+                .iter()
+                .map(|(name, value)| Some((name.clone(), value.as_str()?.to_owned())))
+                .collect::<Option<_>>()
+                .ok_or(error("expected formatted field values"))?,
+            Some(_) => return Err(error("expected an object")),
+            None => Vec::new(),
+        };
+        let elapsed = match value.get("elapsed") {
+            Some(&Value::Number(micros)) => Some(Duration::from_micros(micros)),
+            Some(_) => return Err(error("expected a number")),
+            None => None,
+        };
+        Ok(Record {
+            kind,
+            level,
+            target: string("target")?.to_owned(),
+            path,
+            fields,
+            elapsed,
+        })
+    }
+}
+
+impl Record {
+    /// The records of a captured tree, in the order they occurred.
+    pub fn from_tree(tree: &SpanTree) -> Vec<Record> {
+        let mut records = Vec::new();
+        let mut path = Vec::new();
+        push_tree(tree, &mut path, &mut records);
+        records
+    }
+
+    /// The depth of the span of the record, with root spans and events outside of any span at
+    /// depth zero.
+    fn depth(&self) -> usize {
+        self.path.len().saturating_sub(1)
+    }
+}
+
+fn push_tree(tree: &SpanTree, path: &mut Vec<String>, records: &mut Vec<Record>) {
+    let owned = |fields: &[(&str, String)]| {
+        fields
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    };
+    path.push(tree.name.to_owned());
+    records.push(Record {
+        kind: RecordKind::SpanOpen,
+        level: tree.level,
+        target: tree.target.to_owned(),
+        path: path.clone(),
+        fields: owned(&tree.fields),
+        elapsed: None,
+    });
+
+    // Child spans are stored in the order they closed, but are rendered when they opened
+    let mut children: Vec<_> = tree.children.iter().collect();
+    children.sort_by_key(|node| match node {
+        Node::Span(span) => span.start,
+        Node::Event(event) => tree.start + event.elapsed,
+    });
+    for node in children {
+        match node {
+            Node::Span(span) => push_tree(span, path, records),
+            Node::Event(event) => {
+                let mut fields = Vec::with_capacity(event.fields.len() + 1);
+                if !event.message.is_empty() {
+                    fields.push(("message".to_owned(), event.message.clone()));
+                }
+                fields.extend(owned(&event.fields));
+                records.push(Record {
+                    kind: RecordKind::Event,
+                    level: event.level,
+                    target: event.target.to_owned(),
+                    path: path.clone(),
+                    fields,
+                    elapsed: Some(event.elapsed),
+                })
+            }
+        }
+    }
+
+    records.push(Record {
+        kind: RecordKind::SpanClose,
+        level: tree.level,
+        target: tree.target.to_owned(),
+        path: path.clone(),
+        fields: Vec::new(),
+        elapsed: Some(tree.duration),
+    });
+    path.pop();
+}
+
+/// Renders records like [`HierarchicalLayer`](crate::HierarchicalLayer) renders live spans
+/// and events.
+///
+/// Records are expected in the order they occurred, with every span opened before the
+/// records inside of it.
+#[derive(Debug)]
+pub struct Renderer<FT = ()> {
+    config: Config,
+    timer: FT,
+    bufs: Buffers,
+    /// The levels and fields of the currently open spans, by depth, for reprinting them on
+    /// close
+    open: Vec<(Level, Vec<(String, String)>)>,
+}
+
+impl Renderer {
+    /// Renders records with the options of `config`, without printing any times.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            timer: (),
+            bufs: Buffers::new(),
+            open: Vec::new(),
+        }
+    }
+}
+
+impl<FT: FormatTime> Renderer<FT> {
+    /// Formats the elapsed time of events with `timer`, e.g. a
+    /// [`HumanDuration`](crate::time::HumanDuration).
+    pub fn with_timer<FT2: FormatTime>(self, timer: FT2) -> Renderer<FT2> {
+        Renderer {
+            config: self.config,
+            timer,
+            bufs: self.bufs,
+            open: self.open,
+        }
+    }
+
+    /// Renders a single record, returning the lines it produced.
+    pub fn render(&mut self, record: &Record) -> &str {
+        self.bufs.record.clear();
+        let depth = record.depth();
+        match record.kind {
+            RecordKind::SpanOpen => {
+                self.open.truncate(depth);
+                if self.config.verbose_entry && depth > 0 {
+                    self.write_span(record, depth - 1, SpanMode::PreOpen);
+                }
+                self.open.push((record.level, record.fields.clone()));
+                let verbose = self.config.verbose_entry;
+                self.write_span(record, depth, SpanMode::Open { verbose });
+            }
+            RecordKind::SpanClose => {
+                let verbose = self.config.verbose_exit;
+                self.write_span(record, depth, SpanMode::Close { verbose });
+                self.open.truncate(depth);
+                if verbose && depth > 0 {
+                    self.write_span(record, depth - 1, SpanMode::PostClose);
+                }
+            }
+            RecordKind::Event => self.write_event(record),
+        }
+        &self.bufs.record
+    }
+
+    /// Renders all records of a captured tree.
+    pub fn render_tree(&mut self, tree: &SpanTree) -> String {
+        let mut out = String::new();
+        for record in Record::from_tree(tree) {
+            out.push_str(self.render(&record));
+        }
+        out
+    }
+
+    /// Renders the JSON lines of a structured mirror read from `reader` into `writer`.
+    /// Empty lines are skipped, anything else that is not a record is an
+    /// [`InvalidData`](io::ErrorKind::InvalidData) error.
+    pub fn replay(
+        &mut self,
+        reader: impl io::BufRead,
+        mut writer: impl io::Write,
+    ) -> io::Result<()> {
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: Record = line.parse().map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", i + 1, err),
+                )
+            })?;
+            writer.write_all(self.render(&record).as_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// Writes the span at `depth` of the path of `record`.
+    fn write_span(&mut self, record: &Record, depth: usize, style: SpanMode) {
+        if self.config.span_modes {
+            write_span_mode(&mut self.bufs.current_buf, style);
+        }
+        let should_write = match style {
+            SpanMode::Close { verbose } => verbose,
+            SpanMode::PreOpen => self.config.verbose_entry,
+            _ => true,
+        };
+        if should_write {
+            let (level, fields) = match self.open.get(depth) {
+                Some((level, fields)) => (level, fields.as_slice()),
+                None => (&record.level, &[][..]),
+            };
+            let name = record.path.get(depth).map_or("", String::as_str);
+            write_span_head(
+                &self.config,
+                &mut self.bufs.current_buf,
+                level,
+                &record.target,
+                name,
+                fields.iter().map(|(k, v)| (k.as_str(), v)),
+            );
+        }
+        self.bufs.indent_current(depth, &self.config, style);
+        self.bufs.flush_current_buf();
+    }
+
+    fn write_event(&mut self, record: &Record) {
+        let (config, timer) = (&self.config, &self.timer);
+        let buf = &mut self.bufs.current_buf;
+        // Events outside of any span have no elapsed time
+        let elapsed = record.elapsed.filter(|_| !record.path.is_empty());
+        if config.time_display.elapsed() && (elapsed.is_some() || config.elapsed_width > 0) {
+            let start = buf.len();
+            if let Some(elapsed) = elapsed {
+                match config.theme().elapsed {
+                    Some(style) => write_styled(config.ansi, style, buf, |buf| {
+                        timer.style_timestamp(false, elapsed, buf).unwrap()
+                    }),
+                    None => timer.style_timestamp(config.ansi, elapsed, buf).unwrap(),
+                }
+            }
+            let padding = config
+                .elapsed_width
+                .saturating_sub(visible_width(&buf[start..]));
+            buf.insert_str(start, &" ".repeat(padding));
+            buf.push(' ');
+        }
+
+        write_event_level(config, buf, &record.level);
+        if config.targets {
+            buf.push(' ');
+            push_styled(config.ansi, buf, config.theme().secondary, &record.target);
+        }
+        let mut comma = false;
+        let start = buf.len();
+        let mut message = None;
+        for (name, value) in &record.fields {
+            if name == "message" {
+                message = Some(value.as_str());
+                continue;
+            }
+            write_event_field(buf, config, &mut comma, name, |buf| buf.push_str(value));
+        }
+        finish_event_fields(buf, start, config, &mut comma, message);
+
+        let deindent = if config.indent_lines || config.markdown {
+            0
+        } else {
+            1
+        };
+        let indent = record.path.len().saturating_sub(deindent);
+        self.bufs
+            .indent_current(indent, &self.config, SpanMode::Event);
+        self.bufs.flush_current_buf();
+    }
+}
+
+impl<FT> Renderer<FT> {
+    /// The options records are rendered with.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+}
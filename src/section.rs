@@ -0,0 +1,94 @@
+//! Markers that turn the output of root spans into collapsible sections of CI logs.
+//!
+//! ```
+//! use tracing_tree::{section::TeamCity, HierarchicalLayer};
+//!
+//! let layer = HierarchicalLayer::default().with_section_marker(TeamCity);
+//! ```
+
+use std::fmt;
+
+/// Writes the control lines a CI system uses to delimit a block of its log.
+///
+/// The layer calls [`open`](Self::open) before the first line of every root span and
+/// [`close`](Self::close) after its last line, with the same `name` made up of the span's name
+/// and fields. Every line written has to end with a newline.
+pub trait SectionMarker: Send + Sync {
+    /// Writes the lines that open the section called `name`.
+    fn open(&self, name: &str, w: &mut dyn fmt::Write) -> fmt::Result;
+
+    /// Writes the lines that close the section called `name`.
+    fn close(&self, name: &str, w: &mut dyn fmt::Write) -> fmt::Result;
+}
+
+/// `::group::` and `::endgroup::` workflow commands of GitHub Actions.
+///
+/// This is the marker used by
+/// [`with_github_groups`](crate::HierarchicalLayer::with_github_groups).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GitHubActions;
+
+impl SectionMarker for GitHubActions {
+    fn open(&self, name: &str, w: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(w, "::group::{}", name)
+    }
+
+    fn close(&self, _name: &str, w: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(w, "::endgroup::")
+    }
+}
+
+/// `blockOpened` and `blockClosed` service messages of TeamCity.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TeamCity;
+
+impl TeamCity {
+    fn write_message(kind: &str, name: &str, w: &mut dyn fmt::Write) -> fmt::Result {
+        write!(w, "##teamcity[{} name='", kind)?;
+        for c in name.chars() {
+            match c {
+                '\'' | '|' | '[' | ']' => write!(w, "|{}", c)?,
+                '\n' => w.write_str("|n")?,
+                '\r' => w.write_str("|r")?,
+                c => w.write_char(c)?,
+            }
+        }
+        w.write_str("']\n")
+    }
+}
+
+impl SectionMarker for TeamCity {
+    fn open(&self, name: &str, w: &mut dyn fmt::Write) -> fmt::Result {
+        Self::write_message("blockOpened", name, w)
+    }
+
+    fn close(&self, name: &str, w: &mut dyn fmt::Write) -> fmt::Result {
+        Self::write_message("blockClosed", name, w)
+    }
+}
+
+/// `---` section headers of Buildkite.
+///
+/// Buildkite sections last until the next header, so nothing is written when a span closes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Buildkite {
+    expanded: bool,
+}
+
+impl Buildkite {
+    /// Writes `+++` headers instead, which Buildkite shows expanded.
+    pub fn expanded() -> Self {
+        Buildkite { expanded: true }
+    }
+}
+
+impl SectionMarker for Buildkite {
+    fn open(&self, name: &str, w: &mut dyn fmt::Write) -> fmt::Result {
+        let header = if self.expanded { "+++" } else { "---" };
+        writeln!(w, "{} {}", header, name.replace('\n', " "))
+    }
+
+    fn close(&self, _name: &str, _w: &mut dyn fmt::Write) -> fmt::Result {
+        Ok(())
+    }
+}
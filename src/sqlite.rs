@@ -0,0 +1,281 @@
+//! A SQLite database of spans and events, written alongside the tree.
+//!
+//! The database has three tables:
+//!
+//! - `spans(id, parent_id, name, target, level, opened_at, duration_us)`, where `parent_id`
+//!   refers to the enclosing span and `duration_us` is `NULL` until the span closes,
+//! - `events(id, span_id, target, level, message, occurred_at)`, where `span_id` refers to the
+//!   span the event occurred in,
+//! - `fields(span_id, event_id, name, value)`, holding the fields of spans and events, formatted
+//!   as they are in the tree.
+//!
+//! Times are microseconds since the Unix epoch. Rows are inserted in a transaction that is
+//! committed by [`SqliteSink::flush`] and when the last clone of the sink is dropped.
+//!
+//! ```no_run
+//! use std::io;
+//! use tracing_subscriber::{layer::SubscriberExt, Registry};
+//! use tracing_tree::{sqlite::SqliteSink, HierarchicalLayer};
+//!
+//! let sink = SqliteSink::open("trace.db").unwrap();
+//! let layer = HierarchicalLayer::default()
+//!     // Only write the database, not the tree
+//!     .with_writer(io::sink)
+//!     .with_sqlite_sink(sink.clone());
+//! let subscriber = Registry::default().with(layer);
+//! // ... install the subscriber and do some work ...
+//!
+//! sink.flush().unwrap();
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::{params, Connection};
+use tracing_core::{span, Event, Metadata};
+
+use crate::{
+    fields::{self, Fields},
+    format::Config,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS spans (
+    id INTEGER PRIMARY KEY,
+    parent_id INTEGER REFERENCES spans(id),
+    name TEXT NOT NULL,
+    target TEXT NOT NULL,
+    level TEXT NOT NULL,
+    opened_at INTEGER NOT NULL,
+    duration_us INTEGER
+);
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY,
+    span_id INTEGER REFERENCES spans(id),
+    target TEXT NOT NULL,
+    level TEXT NOT NULL,
+    message TEXT,
+    occurred_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS fields (
+    span_id INTEGER REFERENCES spans(id),
+    event_id INTEGER REFERENCES events(id),
+    name TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+";
+
+/// A handle to a database written by a [`HierarchicalLayer`].
+///
+/// Clones write to the same database, so a clone can be handed to
+/// [`HierarchicalLayer::with_sqlite_sink`] while the original is kept to flush or query it.
+///
+/// [`HierarchicalLayer`]: crate::HierarchicalLayer
+/// [`HierarchicalLayer::with_sqlite_sink`]: crate::HierarchicalLayer::with_sqlite_sink
+#[derive(Clone)]
+pub struct SqliteSink {
+    inner: Arc<Mutex<Sink>>,
+}
+
+struct Sink {
+    connection: Connection,
+    /// The rows of the spans that are still open, by span id, as span ids are reused
+    open: HashMap<span::Id, i64>,
+}
+
+impl fmt::Debug for SqliteSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqliteSink").finish_non_exhaustive()
+    }
+}
+
+impl SqliteSink {
+    /// Writes to the database at `path`, creating it and the tables if they don't exist yet.
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        Self::new(Connection::open(path)?)
+    }
+
+    /// Writes to a database in memory, to be queried with
+    /// [`with_connection`](Self::with_connection).
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    /// Writes to the database of `connection`, creating the tables if they don't exist yet.
+    pub fn new(connection: Connection) -> rusqlite::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Sink {
+                connection,
+                open: HashMap::new(),
+            })),
+        })
+    }
+
+    /// Commits the rows inserted so far.
+    pub fn flush(&self) -> rusqlite::Result<()> {
+        self.lock().commit()
+    }
+
+    /// Commits the rows inserted so far and calls `f` with the connection, e.g. to query them.
+    pub fn with_connection<R>(&self, f: impl FnOnce(&Connection) -> R) -> rusqlite::Result<R> {
+        let sink = self.lock();
+        sink.commit()?;
+        Ok(f(&sink.connection))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Sink> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Inserts a span that was just opened inside of `parent`.
+    pub(crate) fn open_span(
+        &self,
+        id: &span::Id,
+        parent: Option<&span::Id>,
+        metadata: &Metadata<'_>,
+        fields: &Fields,
+    ) {
+        let mut sink = self.lock();
+        // Failing to write the database must not take down the application
+        let _ = sink.open_span(id, parent, metadata, fields);
+    }
+
+    /// Records that the span `id` closed after `duration_us` microseconds.
+    pub(crate) fn close_span(&self, id: &span::Id, duration_us: u128) {
+        let mut sink = self.lock();
+        let _ = sink.close_span(id, duration_us);
+    }
+
+    /// Inserts an event that occurred just now inside of `span`.
+    pub(crate) fn event(
+        &self,
+        span: Option<&span::Id>,
+        metadata: &Metadata<'_>,
+        event: &Event<'_>,
+        config: &Config,
+    ) {
+        let mut message = None;
+        let mut others = Vec::new();
+        fields::flatten(event, config, |name, value| {
+            if name == "message" {
+                message = Some(value.to_owned());
+            } else {
+                others.push((name, value.to_owned()));
+            }
+        });
+        let mut sink = self.lock();
+        let _ = sink.event(span, metadata, message, &others);
+    }
+}
+
+impl Sink {
+    /// Starts a transaction unless one is pending already.
+    fn begin(&self) -> rusqlite::Result<()> {
+        if self.connection.is_autocommit() {
+            self.connection.execute_batch("BEGIN")?;
+        }
+        Ok(())
+    }
+
+    fn commit(&self) -> rusqlite::Result<()> {
+        if !self.connection.is_autocommit() {
+            self.connection.execute_batch("COMMIT")?;
+        }
+        Ok(())
+    }
+
+    fn open_span(
+        &mut self,
+        id: &span::Id,
+        parent: Option<&span::Id>,
+        metadata: &Metadata<'_>,
+        fields: &Fields,
+    ) -> rusqlite::Result<()> {
+        self.begin()?;
+        let parent = parent.and_then(|parent| self.open.get(parent));
+        self.connection
+            .prepare_cached(
+                "INSERT INTO spans (parent_id, name, target, level, opened_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?
+            .execute(params![
+                parent,
+                metadata.name(),
+                metadata.target(),
+                metadata.level().as_str(),
+                now(),
+            ])?;
+        let row = self.connection.last_insert_rowid();
+        let mut insert = self
+            .connection
+            .prepare_cached("INSERT INTO fields (span_id, name, value) VALUES (?1, ?2, ?3)")?;
+        for (name, value) in fields.iter() {
+            insert.execute(params![row, name, value])?;
+        }
+        drop(insert);
+        self.open.insert(id.clone(), row);
+        Ok(())
+    }
+
+    fn close_span(&mut self, id: &span::Id, duration_us: u128) -> rusqlite::Result<()> {
+        let Some(row) = self.open.remove(id) else {
+            return Ok(());
+        };
+        self.begin()?;
+        self.connection
+            .prepare_cached("UPDATE spans SET duration_us = ?1 WHERE id = ?2")?
+            .execute(params![duration_us as i64, row])?;
+        Ok(())
+    }
+
+    fn event(
+        &mut self,
+        span: Option<&span::Id>,
+        metadata: &Metadata<'_>,
+        message: Option<String>,
+        fields: &[(&str, String)],
+    ) -> rusqlite::Result<()> {
+        self.begin()?;
+        let span = span.and_then(|span| self.open.get(span));
+        self.connection
+            .prepare_cached(
+                "INSERT INTO events (span_id, target, level, message, occurred_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?
+            .execute(params![
+                span,
+                metadata.target(),
+                metadata.level().as_str(),
+                message,
+                now(),
+            ])?;
+        let row = self.connection.last_insert_rowid();
+        let mut insert = self
+            .connection
+            .prepare_cached("INSERT INTO fields (event_id, name, value) VALUES (?1, ?2, ?3)")?;
+        for (name, value) in fields {
+            insert.execute(params![row, name, value])?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Sink {
+    fn drop(&mut self) {
+        // There is nobody left to report a failure to
+        let _ = self.commit();
+    }
+}
+
+/// The current time in microseconds since the Unix epoch.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_micros() as i64)
+}
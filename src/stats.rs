@@ -0,0 +1,141 @@
+//! Timing statistics of spans, aggregated per callsite.
+//!
+//! ```
+//! use tracing_subscriber::{layer::SubscriberExt, Registry};
+//! use tracing_tree::{stats::SpanStats, HierarchicalLayer};
+//!
+//! let stats = SpanStats::new();
+//! let layer = HierarchicalLayer::default().with_span_stats(stats.clone());
+//! let subscriber = Registry::default().with(layer);
+//! // ... install the subscriber and do some work ...
+//!
+//! for stat in stats.snapshot() {
+//!     println!("{}: {} calls, {:?} on average", stat.name, stat.count, stat.mean());
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fmt::Write as _,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tracing_core::{callsite, Metadata};
+
+/// A handle to the timing statistics collected by a [`HierarchicalLayer`].
+///
+/// Clones share the same statistics, so a clone can be handed to
+/// [`HierarchicalLayer::with_span_stats`] while the original is kept to read the statistics.
+///
+/// [`HierarchicalLayer`]: crate::HierarchicalLayer
+/// [`HierarchicalLayer::with_span_stats`]: crate::HierarchicalLayer::with_span_stats
+#[derive(Debug, Clone, Default)]
+pub struct SpanStats {
+    inner: Arc<Mutex<HashMap<callsite::Identifier, SpanStat>>>,
+}
+
+/// The aggregated timings of all closed spans of one callsite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SpanStat {
+    /// The name of the span.
+    pub name: &'static str,
+    /// The target of the span.
+    pub target: &'static str,
+    /// How many spans have been closed.
+    pub count: u64,
+    /// The sum of the time between opening and closing the spans.
+    pub total: Duration,
+    /// The longest time between opening and closing a span.
+    pub max: Duration,
+}
+
+impl SpanStat {
+    /// The average time between opening and closing a span.
+    pub fn mean(&self) -> Duration {
+        match u32::try_from(self.count) {
+            Ok(0) => Duration::ZERO,
+            Ok(count) => self.total / count,
+            Err(_) => Duration::from_secs_f64(self.total.as_secs_f64() / self.count as f64),
+        }
+    }
+
+    fn merge(&mut self, other: &SpanStat) {
+        self.count += other.count;
+        self.total += other.total;
+        self.max = self.max.max(other.max);
+    }
+}
+
+impl SpanStats {
+    /// Creates an empty set of statistics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The statistics of every callsite seen so far, with the most total time first.
+    pub fn snapshot(&self) -> Vec<SpanStat> {
+        let mut stats: Vec<_> = self.inner.lock().unwrap().values().cloned().collect();
+        stats.sort_by(|a, b| b.total.cmp(&a.total).then(a.name.cmp(b.name)));
+        stats
+    }
+
+    /// The statistics of all callsites with the given span name combined.
+    pub fn get(&self, name: &str) -> Option<SpanStat> {
+        let inner = self.inner.lock().unwrap();
+        let mut matching = inner.values().filter(|stat| stat.name == name);
+        let mut stat = matching.next()?.clone();
+        for other in matching {
+            stat.merge(other);
+        }
+        Some(stat)
+    }
+
+    /// Forgets all statistics collected so far.
+    pub fn reset(&self) {
+        self.inner.lock().unwrap().clear();
+    }
+
+    pub(crate) fn record(&self, metadata: &'static Metadata<'static>, elapsed: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        let stat = inner
+            .entry(metadata.callsite())
+            .or_insert_with(|| SpanStat {
+                name: metadata.name(),
+                target: metadata.target(),
+                count: 0,
+                total: Duration::ZERO,
+                max: Duration::ZERO,
+            });
+        stat.count += 1;
+        stat.total += elapsed;
+        stat.max = stat.max.max(elapsed);
+    }
+
+    pub(crate) fn render(&self, buf: &mut String) {
+        let stats = self.snapshot();
+        if stats.is_empty() {
+            return;
+        }
+        writeln!(
+            buf,
+            "  span timings:\n  {:>8} {:>10} {:>10} {:>10} name",
+            "count", "total", "mean", "max"
+        )
+        .unwrap();
+        for stat in stats {
+            writeln!(
+                buf,
+                "  {:>8} {:>10.2?} {:>10.2?} {:>10.2?} {}",
+                stat.count,
+                stat.total,
+                stat.mean(),
+                stat.max,
+                stat.name
+            )
+            .unwrap();
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::{borrow::Cow, fmt::Write as _, time::Duration};
+
+use tracing_core::Level;
+
+/// Statistics over the whole lifetime of a layer, printed as a summary block.
+#[derive(Debug, Default)]
+pub(crate) struct Summary {
+    /// Events per level, from `ERROR` to `TRACE`
+    events: [usize; 5],
+    spans: usize,
+    /// The slowest closed spans, slowest first
+    slowest: Vec<(Cow<'static, str>, Duration)>,
+}
+
+impl Summary {
+    /// How many of the slowest spans are listed
+    const SLOWEST: usize = 5;
+
+    const LEVELS: [Level; 5] = [
+        Level::ERROR,
+        Level::WARN,
+        Level::INFO,
+        Level::DEBUG,
+        Level::TRACE,
+    ];
+
+    pub(crate) fn record_event(&mut self, level: &Level) {
+        if let Some(i) = Self::LEVELS.iter().position(|l| l == level) {
+            self.events[i] += 1;
+        }
+    }
+
+    pub(crate) fn record_span(&mut self, name: Cow<'static, str>, elapsed: Duration) {
+        self.spans += 1;
+        let i = self.slowest.partition_point(|(_, d)| *d >= elapsed);
+        if i < Self::SLOWEST {
+            self.slowest.insert(i, (name, elapsed));
+            self.slowest.truncate(Self::SLOWEST);
+        }
+    }
+
+    pub(crate) fn render(&self, buf: &mut String) {
+        let events: usize = self.events.iter().sum();
+        writeln!(buf, "summary: {} spans, {} events", self.spans, events).unwrap();
+        for (level, count) in Self::LEVELS.iter().zip(self.events) {
+            if count > 0 {
+                writeln!(buf, "  {:>5} {}", level, count).unwrap();
+            }
+        }
+        if !self.slowest.is_empty() {
+            writeln!(buf, "  slowest spans:").unwrap();
+            for (name, elapsed) in &self.slowest {
+                writeln!(buf, "  {:>10.2?} {}", elapsed, name).unwrap();
+            }
+        }
+    }
+}
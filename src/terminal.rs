@@ -0,0 +1,213 @@
+//! Detection of the terminal the output is written to.
+
+#[cfg(not(feature = "anstream"))]
+use std::io::IsTerminal;
+use std::{any, io, sync::Mutex, time::Duration};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::clock::Instant;
+
+/// Whether the writers of `W` write to a terminal, or `None` if that can't be told.
+///
+/// There is no way to ask an arbitrary writer about its destination, so this looks for the
+/// standard streams in the name of the writer type, also when they are wrapped like in
+/// `EitherWriter<Stderr, Stdout>`. All of the mentioned streams must be terminals. Type names
+/// are not guaranteed to be stable across compiler versions, so this is only a hint: a writer
+/// without a recognized stream, such as a file or a buffer, is reported as unknown.
+pub(crate) fn is_terminal<W>() -> Option<bool>
+where
+    W: for<'writer> MakeWriter<'writer>,
+{
+    let name = any::type_name::<<W as MakeWriter<'static>>::Writer>();
+    let mentions = |stream: &str| {
+        name.split(|c: char| !c.is_alphanumeric() && c != '_')
+            .any(|ident| ident == stream || ident.strip_suffix("Lock") == Some(stream))
+    };
+    let streams = [
+        (mentions("Stdout"), supports_colors(&io::stdout())),
+        (mentions("Stderr"), supports_colors(&io::stderr())),
+    ];
+    let mut mentioned = streams
+        .iter()
+        .filter(|&&(mentioned, _)| mentioned)
+        .peekable();
+    mentioned.peek()?;
+    Some(mentioned.all(|&(_, terminal)| terminal))
+}
+
+/// Whether the terminal supports 24-bit colors, as announced by the `COLORTERM` environment
+/// variable.
+pub(crate) fn supports_truecolor() -> bool {
+    matches!(
+        std::env::var("COLORTERM").as_deref(),
+        Ok("truecolor") | Ok("24bit")
+    )
+}
+
+#[cfg(not(feature = "anstream"))]
+pub(crate) fn supports_colors(stream: &impl IsTerminal) -> bool {
+    stream.is_terminal()
+}
+
+/// Whether `anstream` would color the output to `stream`, which also honors `NO_COLOR`,
+/// `CLICOLOR_FORCE` and consoles without color support.
+#[cfg(feature = "anstream")]
+pub(crate) fn supports_colors<S: anstream::stream::RawStream>(stream: &S) -> bool {
+    anstream::AutoStream::choice(stream) != anstream::ColorChoice::Never
+}
+
+/// How long a detected width is used before the terminal is queried again, so that resizing
+/// the terminal is picked up eventually.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+static CACHE: Mutex<Option<(Instant, Option<usize>)>> = Mutex::new(None);
+
+/// The number of columns of the terminal, taken from the `COLUMNS` environment variable or
+/// else from the terminal stderr is attached to.
+///
+/// No `SIGWINCH` handler is installed, as a library should not take over signal handling.
+/// Instead the width is polled at most once per [`REFRESH_INTERVAL`], so a resize shows up with
+/// a delay of up to a second.
+pub(crate) fn width() -> Option<usize> {
+    let mut cache = CACHE.lock().unwrap_or_else(|err| err.into_inner());
+    match *cache {
+        Some((detected, width)) if detected.elapsed() < REFRESH_INTERVAL => width,
+        _ => {
+            let width = detect();
+            *cache = Some((Instant::now(), width));
+            width
+        }
+    }
+}
+
+fn detect() -> Option<usize> {
+    let columns = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok());
+    columns.filter(|&n| n > 0).or_else(sys::width)
+}
+
+// Linux uses the generic value of `TIOCGWINSZ` only on some architectures. Others, such as
+// mips, powerpc and sparc, fall back to `COLUMNS`.
+#[cfg(any(
+    all(
+        any(target_os = "linux", target_os = "android"),
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "arm",
+            target_arch = "aarch64",
+            target_arch = "riscv32",
+            target_arch = "riscv64",
+            target_arch = "loongarch64",
+            target_arch = "s390x"
+        )
+    ),
+    target_os = "macos"
+))]
+mod sys {
+    use std::os::raw::{c_int, c_ulong, c_ushort};
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Winsize {
+        ws_row: c_ushort,
+        ws_col: c_ushort,
+        ws_xpixel: c_ushort,
+        ws_ypixel: c_ushort,
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const TIOCGWINSZ: c_ulong = 0x5413;
+    #[cfg(target_os = "macos")]
+    const TIOCGWINSZ: c_ulong = 0x40087468;
+
+    const STDERR_FILENO: c_int = 2;
+
+    extern "C" {
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+
+    pub(super) fn width() -> Option<usize> {
+        let mut size = Winsize::default();
+        // SAFETY: `TIOCGWINSZ` only writes a `winsize` to the pointer
+        let result = unsafe { ioctl(STDERR_FILENO, TIOCGWINSZ, &mut size as *mut Winsize) };
+        (result == 0 && size.ws_col > 0).then_some(usize::from(size.ws_col))
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::{convert::TryFrom, ffi::c_void, mem::MaybeUninit};
+
+    #[repr(C)]
+    struct Coord {
+        x: i16,
+        y: i16,
+    }
+
+    #[repr(C)]
+    struct SmallRect {
+        left: i16,
+        top: i16,
+        right: i16,
+        bottom: i16,
+    }
+
+    #[repr(C)]
+    struct ConsoleScreenBufferInfo {
+        size: Coord,
+        cursor_position: Coord,
+        attributes: u16,
+        window: SmallRect,
+        maximum_window_size: Coord,
+    }
+
+    const STD_ERROR_HANDLE: u32 = -12i32 as u32;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut c_void;
+        fn GetConsoleScreenBufferInfo(
+            console_output: *mut c_void,
+            console_screen_buffer_info: *mut ConsoleScreenBufferInfo,
+        ) -> i32;
+    }
+
+    pub(super) fn width() -> Option<usize> {
+        let mut info = MaybeUninit::<ConsoleScreenBufferInfo>::uninit();
+        // SAFETY: the call fills in `info` when it succeeds
+        let info = unsafe {
+            if GetConsoleScreenBufferInfo(GetStdHandle(STD_ERROR_HANDLE), info.as_mut_ptr()) == 0 {
+                return None;
+            }
+            info.assume_init()
+        };
+        let width = i32::from(info.window.right) - i32::from(info.window.left) + 1;
+        usize::try_from(width).ok().filter(|&n| n > 0)
+    }
+}
+
+#[cfg(not(any(
+    all(
+        any(target_os = "linux", target_os = "android"),
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "arm",
+            target_arch = "aarch64",
+            target_arch = "riscv32",
+            target_arch = "riscv64",
+            target_arch = "loongarch64",
+            target_arch = "s390x"
+        )
+    ),
+    target_os = "macos",
+    windows
+)))]
+mod sys {
+    pub(super) fn width() -> Option<usize> {
+        None
+    }
+}
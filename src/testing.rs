@@ -0,0 +1,164 @@
+//! Helpers for asserting on the structure of traces in tests, instead of matching the
+//! rendered text.
+//!
+//! [`capture`] collects the trees of all root spans closed while running a closure, and
+//! [`assert_tree!`](crate::assert_tree) compares them to the expected structure. Braces list
+//! the child spans of a span, brackets the messages of the events directly inside of it:
+//!
+//! ```
+//! use tracing::{info, info_span};
+//! use tracing_tree::{assert_tree, testing::capture};
+//!
+//! let trees = capture(|| {
+//!     info_span!("server").in_scope(|| {
+//!         info_span!("conn").in_scope(|| {
+//!             info!("connected");
+//!             info!("disconnected");
+//!         });
+//!     });
+//! });
+//!
+//! assert_tree!(trees, { "server" => { "conn" => ["connected", "disconnected"] } });
+//! ```
+//!
+//! Listed spans and events have to match exactly and in the order they were opened and
+//! occurred respectively, except for root spans, which are listed in the order they closed as
+//! their start times have no common origin. Braces don't check events and brackets don't check
+//! child spans.
+
+use tracing_core::dispatcher::{self, Dispatch};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+use crate::{
+    render::Renderer,
+    tree::{Builder, SpanTree},
+    Config,
+};
+
+/// Runs `f` with a subscriber capturing every root span, and returns their trees in the order
+/// they closed.
+pub fn capture(f: impl FnOnce()) -> Vec<SpanTree> {
+    let builder = Builder::new();
+    let dispatch = Dispatch::new(Registry::default().with(builder.clone()));
+    dispatcher::with_default(&dispatch, f);
+    builder.take()
+}
+
+/// What [`assert_tree!`](crate::assert_tree) expects inside of a span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expected {
+    /// The child spans, in the order they were opened, or the root spans, in the order they
+    /// closed.
+    Spans(Vec<ExpectedSpan>),
+    /// The messages of the events, in the order they occurred.
+    Events(Vec<&'static str>),
+}
+
+/// A span expected by [`assert_tree!`](crate::assert_tree).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedSpan {
+    /// The name of the span.
+    pub name: &'static str,
+    /// What is expected inside of the span.
+    pub children: Expected,
+}
+
+/// Compares captured root spans, in the order they closed, to the expected structure,
+/// describing the first difference along with the rendered trees.
+pub fn check(trees: &[SpanTree], expected: &Expected) -> Result<(), String> {
+    let roots: Vec<_> = trees.iter().collect();
+    check_children("", &roots, None, expected).map_err(|mut message| {
+        message.push_str("\n\ncaptured:\n");
+        let mut renderer =
+            Renderer::new(Config::default().with_ansi(false).with_indent_lines(true));
+        for tree in trees {
+            message.push_str(&renderer.render_tree(tree));
+        }
+        message
+    })
+}
+
+fn check_children(
+    path: &str,
+    spans: &[&SpanTree],
+    parent: Option<&SpanTree>,
+    expected: &Expected,
+) -> Result<(), String> {
+    match expected {
+        Expected::Spans(expected) => {
+            let names: Vec<_> = spans.iter().map(|span| span.name).collect();
+            let expected_names: Vec<_> = expected.iter().map(|span| span.name).collect();
+            if names != expected_names {
+                return Err(format!(
+                    "{}expected spans {:?}, found {:?}",
+                    location(path),
+                    expected_names,
+                    names
+                ));
+            }
+            for (span, expected) in spans.iter().zip(expected) {
+                let mut children: Vec<_> = span.spans().collect();
+                children.sort_by_key(|child| child.start);
+                let path = if path.is_empty() {
+                    span.name.to_owned()
+                } else {
+                    format!("{}/{}", path, span.name)
+                };
+                check_children(&path, &children, Some(span), &expected.children)?;
+            }
+            Ok(())
+        }
+        Expected::Events(expected) => {
+            let messages: Vec<_> = parent
+                .iter()
+                .flat_map(|span| span.events())
+                .map(|event| event.message.as_str())
+                .collect();
+            if messages != *expected {
+                return Err(format!(
+                    "{}expected events {:?}, found {:?}",
+                    location(path),
+                    expected,
+                    messages
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Where a difference was found, e.g. ``in `server/conn`: ``.
+fn location(path: &str) -> String {
+    if path.is_empty() {
+        "at the root: ".to_owned()
+    } else {
+        format!("in `{}`: ", path)
+    }
+}
+
+/// Asserts that captured trees have the given structure, see the [`testing`](crate::testing)
+/// module.
+///
+/// The first argument is anything that dereferences to a slice of [`SpanTree`]s, e.g. the
+/// result of [`capture`].
+#[macro_export]
+macro_rules! assert_tree {
+    (@children { $($name:literal => $children:tt),* $(,)? }) => {
+        $crate::testing::Expected::Spans(vec![$(
+            $crate::testing::ExpectedSpan {
+                name: $name,
+                children: $crate::assert_tree!(@children $children),
+            }
+        ),*])
+    };
+    (@children [ $($message:literal),* $(,)? ]) => {
+        $crate::testing::Expected::Events(vec![$($message),*])
+    };
+    ($trees:expr, $expected:tt $(,)?) => {
+        if let Err(message) =
+            $crate::testing::check(&$trees, &$crate::assert_tree!(@children $expected))
+        {
+            panic!("{}", message);
+        }
+    };
+}
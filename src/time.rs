@@ -1,8 +1,9 @@
 use std::{fmt::Write, time::Duration};
 
 use nu_ansi_term::Style;
+use tracing_core::Metadata;
 
-use crate::styled;
+use crate::{paint::paint, styled};
 
 /// A type that can measure and format the current time.
 ///
@@ -33,6 +34,55 @@ pub trait FormatTime {
         elapsed: Duration,
         w: &mut impl std::fmt::Write,
     ) -> std::fmt::Result;
+
+    /// Like [`format_time`](Self::format_time), but with access to the line the time is
+    /// printed on, e.g. to only print the time for specific targets.
+    ///
+    /// This is what [HierarchicalLayer] calls. The default implementation ignores the
+    /// context and calls `format_time`.
+    ///
+    /// [HierarchicalLayer]: crate::HierarchicalLayer
+    fn format_time_with(
+        &self,
+        _context: &TimeContext<'_>,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        self.format_time(w)
+    }
+
+    /// Like [`style_timestamp`](Self::style_timestamp), but with access to the line the
+    /// elapsed time is printed on.
+    ///
+    /// This is what [HierarchicalLayer] calls. The default implementation ignores the
+    /// context and calls `style_timestamp`.
+    ///
+    /// [HierarchicalLayer]: crate::HierarchicalLayer
+    fn style_timestamp_with(
+        &self,
+        _context: &TimeContext<'_>,
+        ansi: bool,
+        elapsed: Duration,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        self.style_timestamp(ansi, elapsed, w)
+    }
+}
+
+/// The line a time is printed on, passed to [`FormatTime::format_time_with`] and
+/// [`FormatTime::style_timestamp_with`].
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct TimeContext<'a> {
+    /// The metadata of the event.
+    pub metadata: &'a Metadata<'a>,
+    /// The number of spans the event is nested in.
+    pub depth: usize,
+}
+
+impl<'a> TimeContext<'a> {
+    pub(crate) fn new(metadata: &'a Metadata<'a>, depth: usize) -> Self {
+        TimeContext { metadata, depth }
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -54,6 +104,66 @@ impl FormatTime for () {
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// Retrieve and print the current wall-clock time in UTC timezone, like
+/// `2024-01-31T12:34:56.789Z`.
+///
+/// Unlike [`UtcDateTime`] this does not require the `time` feature.
+///
+/// [`UtcDateTime`]: https://docs.rs/tracing-tree/latest/tracing_tree/time/struct.UtcDateTime.html
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub struct SystemClock {
+    /// Whether to print the time with higher precision.
+    pub higher_precision: bool,
+}
+
+impl FormatTime for SystemClock {
+    fn format_time(&self, w: &mut impl std::fmt::Write) -> std::fmt::Result {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let secs = now.as_secs();
+        let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+        let secs_of_day = secs % 86_400;
+        write!(
+            w,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            year,
+            month,
+            day,
+            secs_of_day / 3600,
+            secs_of_day / 60 % 60,
+            secs_of_day % 60,
+            now.subsec_millis(),
+        )
+    }
+    fn style_timestamp(
+        &self,
+        ansi: bool,
+        elapsed: Duration,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        style_timestamp(ansi, self.higher_precision, elapsed, w)
+    }
+}
+
+/// Converts days since the unix epoch into a `(year, month, day)` date of the proleptic
+/// Gregorian calendar.
+// NB: Howard Hinnant's `civil_from_days`, see http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
 /// Retrieve and print the current wall-clock time in UTC timezone.
 #[cfg(feature = "time")]
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
@@ -85,7 +195,9 @@ impl FormatTime for UtcDateTime {
 ///
 /// # Panics
 ///
-/// Panics if [time crate] cannot determine the local UTC offset.
+/// Panics if [time crate] cannot determine the local UTC offset. On many platforms this is
+/// the case once the program has started a second thread, see [`CachedLocalDateTime`] for an
+/// alternative.
 ///
 /// [time crate]: time
 // NB:
@@ -116,6 +228,212 @@ impl FormatTime for LocalDateTime {
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// Retrieve and print the current wall-clock time, with a local UTC offset that is determined
+/// up front instead of for every event.
+///
+/// Create the timer early, e.g. at the start of `main` while the program is still single
+/// threaded, as the [time crate] cannot determine the local UTC offset on many platforms
+/// afterwards. The offset is looked up again every [refresh
+/// interval](Self::with_refresh_interval) to pick up daylight saving time changes; if the
+/// lookup fails, the previous offset is kept.
+///
+/// ```
+/// use tracing_tree::{time::CachedLocalDateTime, HierarchicalLayer};
+///
+/// let timer = CachedLocalDateTime::new().unwrap_or_else(|_| CachedLocalDateTime::utc());
+/// let layer = HierarchicalLayer::default().with_timer(timer);
+/// ```
+///
+/// [time crate]: time
+#[cfg(feature = "time")]
+#[derive(Debug)]
+pub struct CachedLocalDateTime {
+    /// The cached offset, and when it was determined.
+    offset: std::sync::Mutex<(time::UtcOffset, std::time::Instant)>,
+    refresh_interval: Duration,
+    /// Whether to print the time with higher precision.
+    pub higher_precision: bool,
+}
+
+#[cfg(feature = "time")]
+impl CachedLocalDateTime {
+    /// Determines the local UTC offset, or fails if the [time crate] cannot determine it.
+    ///
+    /// [time crate]: time
+    pub fn new() -> Result<Self, time::error::IndeterminateOffset> {
+        Ok(Self::from_offset(time::UtcOffset::current_local_offset()?))
+    }
+
+    /// Starts out in UTC, until a refresh determines the local UTC offset.
+    pub fn utc() -> Self {
+        Self::from_offset(time::UtcOffset::UTC)
+    }
+
+    fn from_offset(offset: time::UtcOffset) -> Self {
+        CachedLocalDateTime {
+            offset: std::sync::Mutex::new((offset, std::time::Instant::now())),
+            refresh_interval: Duration::from_secs(60),
+            higher_precision: false,
+        }
+    }
+
+    /// How often to look up the local UTC offset again. Defaults to a minute.
+    pub fn with_refresh_interval(self, refresh_interval: Duration) -> Self {
+        Self {
+            refresh_interval,
+            ..self
+        }
+    }
+
+    fn offset(&self) -> time::UtcOffset {
+        let mut cached = self.offset.lock().unwrap_or_else(|err| err.into_inner());
+        if cached.1.elapsed() >= self.refresh_interval {
+            if let Ok(offset) = time::UtcOffset::current_local_offset() {
+                cached.0 = offset;
+            }
+            cached.1 = std::time::Instant::now();
+        }
+        cached.0
+    }
+}
+
+#[cfg(feature = "time")]
+impl FormatTime for CachedLocalDateTime {
+    fn format_time(&self, w: &mut impl std::fmt::Write) -> std::fmt::Result {
+        let time = time::OffsetDateTime::now_utc().to_offset(self.offset());
+        write!(w, "{}", time)
+    }
+    fn style_timestamp(
+        &self,
+        ansi: bool,
+        elapsed: Duration,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        style_timestamp(ansi, self.higher_precision, elapsed, w)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Retrieve and print the current wall-clock time in a fixed timezone.
+///
+/// Unlike [`LocalDateTime`] this does not depend on the host's timezone configuration, and
+/// never panics.
+///
+/// ```
+/// use tracing_tree::{time::OffsetDateTime, HierarchicalLayer};
+///
+/// let offset = time::UtcOffset::from_hms(2, 0, 0).unwrap();
+/// let layer = HierarchicalLayer::default().with_timer(OffsetDateTime::from(offset));
+/// ```
+#[cfg(feature = "time")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct OffsetDateTime {
+    /// The offset from UTC to print the time in.
+    pub offset: time::UtcOffset,
+    /// Whether to print the time with higher precision.
+    pub higher_precision: bool,
+}
+
+#[cfg(feature = "time")]
+impl From<time::UtcOffset> for OffsetDateTime {
+    fn from(offset: time::UtcOffset) -> Self {
+        OffsetDateTime {
+            offset,
+            higher_precision: false,
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+impl FormatTime for OffsetDateTime {
+    fn format_time(&self, w: &mut impl std::fmt::Write) -> std::fmt::Result {
+        let time = time::OffsetDateTime::now_utc().to_offset(self.offset);
+        write!(w, "{}", time)
+    }
+    fn style_timestamp(
+        &self,
+        ansi: bool,
+        elapsed: Duration,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        style_timestamp(ansi, self.higher_precision, elapsed, w)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Retrieve and print the current wall-clock time using a custom [format description].
+///
+/// ```
+/// use tracing_tree::{time::FormattedDateTime, HierarchicalLayer};
+///
+/// let timer = FormattedDateTime::new(
+///     "[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:3]",
+/// )
+/// .unwrap()
+/// .with_utc(true);
+/// let layer = HierarchicalLayer::default().with_timer(timer);
+/// ```
+///
+/// # Panics
+///
+/// Unless [`with_utc`](Self::with_utc) is enabled, panics like [`LocalDateTime`] if the
+/// [time crate] cannot determine the local UTC offset.
+///
+/// [format description]: https://time-rs.github.io/book/api/format-description.html
+/// [time crate]: time
+#[cfg(feature = "time")]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FormattedDateTime {
+    format: time::format_description::OwnedFormatItem,
+    utc: bool,
+    /// Whether to print the time with higher precision.
+    pub higher_precision: bool,
+}
+
+#[cfg(feature = "time")]
+impl FormattedDateTime {
+    /// Parses `format` as a [format description] for the local time.
+    ///
+    /// [format description]: https://time-rs.github.io/book/api/format-description.html
+    pub fn new(format: &str) -> Result<Self, time::error::InvalidFormatDescription> {
+        Ok(Self {
+            format: time::format_description::parse_owned::<1>(format)?,
+            utc: false,
+            higher_precision: false,
+        })
+    }
+
+    /// Whether to print the time in UTC instead of the local time.
+    pub fn with_utc(self, utc: bool) -> Self {
+        Self { utc, ..self }
+    }
+}
+
+#[cfg(feature = "time")]
+impl FormatTime for FormattedDateTime {
+    fn format_time(&self, w: &mut impl std::fmt::Write) -> std::fmt::Result {
+        let time = if self.utc {
+            time::OffsetDateTime::now_utc()
+        } else {
+            time::OffsetDateTime::now_local().expect("time offset cannot be determined")
+        };
+        let formatted = time.format(&self.format).map_err(|_| std::fmt::Error)?;
+        w.write_str(&formatted)
+    }
+    fn style_timestamp(
+        &self,
+        ansi: bool,
+        elapsed: Duration,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        style_timestamp(ansi, self.higher_precision, elapsed, w)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
 /// Retrieve and print the relative elapsed wall-clock time since an epoch.
 ///
 /// The `Default` implementation for `Uptime` makes the epoch the current time.
@@ -157,6 +475,374 @@ impl FormatTime for Uptime {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Prints the wall-clock time of the wrapped timer only when the current second (or minute)
+/// changes, and blanks it out otherwise.
+///
+/// This reduces the noise of dense output, while the time axis is still visible:
+///
+/// ```text
+/// 2024-01-31 12:34:56 INFO first
+///                     INFO second
+/// 2024-01-31 12:34:57 INFO third
+/// ```
+///
+/// ```
+/// use tracing_tree::{time::{OnChange, Uptime}, HierarchicalLayer};
+///
+/// let layer = HierarchicalLayer::default().with_timer(OnChange::minutes(Uptime::default()));
+/// ```
+#[derive(Debug)]
+pub struct OnChange<FT> {
+    timer: FT,
+    granularity: u64,
+    dimmed: bool,
+    /// The second or minute of the last printed time, `u64::MAX` if none has been printed.
+    last: std::sync::atomic::AtomicU64,
+}
+
+impl<FT> OnChange<FT> {
+    /// Prints the time whenever the second changes.
+    pub fn seconds(timer: FT) -> Self {
+        Self::new(timer, 1)
+    }
+
+    /// Prints the time whenever the minute changes.
+    pub fn minutes(timer: FT) -> Self {
+        Self::new(timer, 60)
+    }
+
+    fn new(timer: FT, granularity: u64) -> Self {
+        OnChange {
+            timer,
+            granularity,
+            dimmed: false,
+            last: std::sync::atomic::AtomicU64::new(u64::MAX),
+        }
+    }
+
+    /// Prints repeated times dimmed instead of blanking them out. The dimming uses ANSI escape
+    /// codes regardless of [`with_ansi`](crate::HierarchicalLayer::with_ansi).
+    pub fn with_dimmed(self, dimmed: bool) -> Self {
+        Self { dimmed, ..self }
+    }
+}
+
+impl<FT> OnChange<FT> {
+    fn write_if_changed(&self, time: String, w: &mut impl std::fmt::Write) -> std::fmt::Result {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let bucket = now.as_secs() / self.granularity;
+        let last = self.last.swap(bucket, std::sync::atomic::Ordering::Relaxed);
+        if last == bucket {
+            if self.dimmed {
+                write!(w, "{}", paint(Style::new().dimmed(), time))
+            } else {
+                write!(w, "{:width$}", "", width = time.chars().count())
+            }
+        } else {
+            w.write_str(&time)
+        }
+    }
+}
+
+impl<FT: FormatTime> FormatTime for OnChange<FT> {
+    fn format_time(&self, w: &mut impl std::fmt::Write) -> std::fmt::Result {
+        let mut time = String::new();
+        self.timer.format_time(&mut time)?;
+        self.write_if_changed(time, w)
+    }
+    fn style_timestamp(
+        &self,
+        ansi: bool,
+        elapsed: Duration,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        self.timer.style_timestamp(ansi, elapsed, w)
+    }
+    fn format_time_with(
+        &self,
+        context: &TimeContext<'_>,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        let mut time = String::new();
+        self.timer.format_time_with(context, &mut time)?;
+        self.write_if_changed(time, w)
+    }
+    fn style_timestamp_with(
+        &self,
+        context: &TimeContext<'_>,
+        ansi: bool,
+        elapsed: Duration,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        self.timer.style_timestamp_with(context, ansi, elapsed, w)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Prints the elapsed time in human-friendly units, like `1m 23s` or `450μs`.
+///
+/// Durations of a second or more are printed with their two most significant units, shorter
+/// ones with a single unit. The wall-clock time is delegated to the wrapped timer, which prints
+/// nothing by default:
+///
+/// ```
+/// use tracing_tree::{time::{HumanDuration, Uptime}, HierarchicalLayer};
+///
+/// let layer = HierarchicalLayer::default().with_timer(HumanDuration::new());
+/// let layer = HierarchicalLayer::default().with_timer(HumanDuration(Uptime::default()));
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub struct HumanDuration<FT = ()>(pub FT);
+
+impl HumanDuration {
+    /// Prints the elapsed time only, without a wall-clock time.
+    pub fn new() -> Self {
+        HumanDuration(())
+    }
+}
+
+impl<FT: FormatTime> FormatTime for HumanDuration<FT> {
+    fn format_time(&self, w: &mut impl std::fmt::Write) -> std::fmt::Result {
+        self.0.format_time(w)
+    }
+    fn format_time_with(
+        &self,
+        context: &TimeContext<'_>,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        self.0.format_time_with(context, w)
+    }
+    fn style_timestamp(
+        &self,
+        ansi: bool,
+        elapsed: Duration,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        let timestamp = human_duration(elapsed);
+        write!(
+            w,
+            "{}",
+            styled(
+                ansi,
+                Style::new().dimmed(),
+                format_args!("{:>9}", timestamp)
+            )
+        )
+    }
+}
+
+pub(crate) fn human_duration(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    let (major, minor) = if secs >= 3600 {
+        ((secs / 3600, "h"), (secs / 60 % 60, "m"))
+    } else if secs >= 60 {
+        ((secs / 60, "m"), (secs % 60, "s"))
+    } else if secs > 0 {
+        ((secs, "s"), (u64::from(elapsed.subsec_millis()), "ms"))
+    } else if elapsed.subsec_millis() > 0 {
+        return format!("{}ms", elapsed.subsec_millis());
+    } else if elapsed.subsec_micros() > 0 {
+        return format!("{}μs", elapsed.subsec_micros());
+    } else {
+        return format!("{}ns", elapsed.subsec_nanos());
+    };
+    if minor.0 == 0 {
+        format!("{}{}", major.0, major.1)
+    } else {
+        format!("{}{} {}{}", major.0, major.1, minor.0, minor.1)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A unit of time used by [`DurationFormat`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum DurationUnit {
+    /// Nanoseconds (`ns`).
+    Nanos,
+    /// Microseconds (`μs`).
+    Micros,
+    /// Milliseconds (`ms`).
+    Millis,
+    /// Seconds (`s`).
+    Secs,
+    /// Minutes (`m`).
+    Mins,
+}
+
+impl DurationUnit {
+    const ALL: [DurationUnit; 5] = [
+        DurationUnit::Nanos,
+        DurationUnit::Micros,
+        DurationUnit::Millis,
+        DurationUnit::Secs,
+        DurationUnit::Mins,
+    ];
+
+    fn duration(self) -> Duration {
+        match self {
+            DurationUnit::Nanos => Duration::from_nanos(1),
+            DurationUnit::Micros => Duration::from_micros(1),
+            DurationUnit::Millis => Duration::from_millis(1),
+            DurationUnit::Secs => Duration::from_secs(1),
+            DurationUnit::Mins => Duration::from_secs(60),
+        }
+    }
+
+    /// The label of the unit, padded to two characters so that units line up.
+    fn label(self) -> &'static str {
+        match self {
+            DurationUnit::Nanos => "ns",
+            DurationUnit::Micros => "μs",
+            DurationUnit::Millis => "ms",
+            DurationUnit::Secs => "s ",
+            DurationUnit::Mins => "m ",
+        }
+    }
+}
+
+/// Prints the elapsed time with configurable units, width and precision.
+///
+/// The elapsed time is printed in the largest unit out of a range of units that the duration
+/// reaches, or in the smallest unit of the range if it is shorter than that. The defaults
+/// match the `()` timer's elapsed time: milliseconds up to minutes, three digits wide, without
+/// decimals. The wall-clock time is delegated to the [timer](Self::with_timer), which prints
+/// nothing by default:
+///
+/// ```
+/// use tracing_tree::{time::{DurationFormat, DurationUnit, Uptime}, HierarchicalLayer};
+///
+/// // Always microseconds
+/// let timer = DurationFormat::new().with_unit(DurationUnit::Micros).with_width(8);
+/// let layer = HierarchicalLayer::default().with_timer(timer);
+///
+/// // Always seconds with 3 decimals, after the uptime
+/// let timer = DurationFormat::new()
+///     .with_unit(DurationUnit::Secs)
+///     .with_precision(3)
+///     .with_timer(Uptime::default());
+/// let layer = HierarchicalLayer::default().with_timer(timer);
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct DurationFormat<FT = ()> {
+    timer: FT,
+    smallest: DurationUnit,
+    largest: DurationUnit,
+    width: usize,
+    precision: usize,
+}
+
+impl DurationFormat {
+    /// Milliseconds up to minutes, three digits wide, without decimals.
+    pub fn new() -> Self {
+        DurationFormat {
+            timer: (),
+            smallest: DurationUnit::Millis,
+            largest: DurationUnit::Mins,
+            width: 3,
+            precision: 0,
+        }
+    }
+}
+
+impl Default for DurationFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<FT> DurationFormat<FT> {
+    /// Uses `timer` to print the wall-clock time.
+    pub fn with_timer<FT2: FormatTime>(self, timer: FT2) -> DurationFormat<FT2> {
+        DurationFormat {
+            timer,
+            smallest: self.smallest,
+            largest: self.largest,
+            width: self.width,
+            precision: self.precision,
+        }
+    }
+
+    /// Picks the unit out of the range `smallest..=largest`. The units are swapped if they are
+    /// given in the wrong order.
+    pub fn with_units(self, smallest: DurationUnit, largest: DurationUnit) -> Self {
+        Self {
+            smallest: smallest.min(largest),
+            largest: smallest.max(largest),
+            ..self
+        }
+    }
+
+    /// Always prints the elapsed time in `unit`.
+    pub fn with_unit(self, unit: DurationUnit) -> Self {
+        self.with_units(unit, unit)
+    }
+
+    /// The minimum width of the number, excluding the unit.
+    pub fn with_width(self, width: usize) -> Self {
+        Self { width, ..self }
+    }
+
+    /// The number of decimals. Excess digits are truncated, not rounded, so that a duration is
+    /// never shown as longer than it is.
+    pub fn with_precision(self, precision: usize) -> Self {
+        Self { precision, ..self }
+    }
+
+    fn unit(&self, elapsed: Duration) -> DurationUnit {
+        DurationUnit::ALL
+            .iter()
+            .copied()
+            .filter(|unit| (self.smallest..=self.largest).contains(unit))
+            .rev()
+            .find(|unit| elapsed >= unit.duration())
+            .unwrap_or(self.smallest)
+    }
+}
+
+impl<FT: FormatTime> FormatTime for DurationFormat<FT> {
+    fn format_time(&self, w: &mut impl std::fmt::Write) -> std::fmt::Result {
+        self.timer.format_time(w)
+    }
+    fn format_time_with(
+        &self,
+        context: &TimeContext<'_>,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        self.timer.format_time_with(context, w)
+    }
+    fn style_timestamp(
+        &self,
+        ansi: bool,
+        elapsed: Duration,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        let unit = self.unit(elapsed);
+        let nanos = elapsed.as_nanos();
+        let unit_nanos = unit.duration().as_nanos();
+        let mut n = (nanos / unit_nanos).to_string();
+        if self.precision > 0 {
+            // Digits past the nanosecond resolution of any unit are always zero.
+            let digits = self.precision.min(18);
+            let fraction = (nanos % unit_nanos) * 10u128.pow(digits as u32) / unit_nanos;
+            write!(
+                n,
+                ".{:0>digits$}{:0<pad$}",
+                fraction,
+                "",
+                pad = self.precision - digits
+            )?;
+        }
+        let timestamp = format!("{:>width$}", n, width = self.width);
+        write_style_timestamp(ansi, timestamp, unit.label(), w)
+    }
+}
+
 fn style_timestamp(
     ansi: bool,
     higher_precision: bool,
@@ -229,7 +915,7 @@ fn write_style_timestamp(
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
-impl<'a, F> FormatTime for &'a F
+impl<F> FormatTime for &F
 where
     F: FormatTime,
 {
@@ -244,6 +930,22 @@ where
     ) -> std::fmt::Result {
         F::style_timestamp(self, ansi, duration, w)
     }
+    fn format_time_with(
+        &self,
+        context: &TimeContext<'_>,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        F::format_time_with(self, context, w)
+    }
+    fn style_timestamp_with(
+        &self,
+        context: &TimeContext<'_>,
+        ansi: bool,
+        duration: Duration,
+        w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        F::style_timestamp_with(self, context, ansi, duration, w)
+    }
 }
 
 // NB:
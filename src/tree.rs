@@ -0,0 +1,540 @@
+//! Capturing spans and events as a data structure instead of rendering them.
+//!
+//! [`Builder`] is a layer that collects every closed root span into a [`SpanTree`], so tests
+//! and tools can assert on the structure of a trace rather than parsing the rendered text.
+//! [`diff`] compares two such trees, e.g. to catch regressions between two builds in CI.
+//!
+//! ```
+//! use tracing::{info, info_span};
+//! use tracing_subscriber::{layer::SubscriberExt, Registry};
+//! use tracing_tree::tree::Builder;
+//!
+//! let builder = Builder::new();
+//! let subscriber = Registry::default().with(builder.clone());
+//! tracing::subscriber::with_default(subscriber, || {
+//!     info_span!("server", port = 8080).in_scope(|| {
+//!         info_span!("conn").in_scope(|| info!(bytes = 42, "response sent"));
+//!     });
+//! });
+//!
+//! let trees = builder.take();
+//! let server = &trees[0];
+//! assert_eq!(server.name, "server");
+//! assert_eq!(server.field("port"), Some("8080"));
+//! let event = server.find("conn").unwrap().events().next().unwrap();
+//! assert_eq!(event.message, "response sent");
+//! ```
+
+use std::{
+    fmt::{self, Write as _},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use tracing_core::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, Layer},
+    registry::LookupSpan,
+};
+
+use nu_ansi_term::{Color, Style};
+
+use crate::{clock::Instant, paint::paint, time::human_duration};
+
+/// A closed span along with everything that happened inside of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SpanTree {
+    /// The name of the span.
+    pub name: &'static str,
+    /// The target of the span.
+    pub target: &'static str,
+    /// The level of the span.
+    pub level: Level,
+    /// The fields of the span, in the order they were recorded.
+    pub fields: Vec<(&'static str, String)>,
+    /// The time between opening the root span and opening this span.
+    pub start: Duration,
+    /// The time between opening and closing the span.
+    pub duration: Duration,
+    /// The events and child spans, in the order they occurred and closed respectively.
+    pub children: Vec<Node>,
+}
+
+/// An entry inside of a [`SpanTree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// A closed child span.
+    Span(SpanTree),
+    /// An event that occurred directly inside of the span.
+    Event(TreeEvent),
+}
+
+/// An event captured in a [`SpanTree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TreeEvent {
+    /// The level of the event.
+    pub level: Level,
+    /// The target of the event.
+    pub target: &'static str,
+    /// The `message` field of the event, or an empty string.
+    pub message: String,
+    /// The other fields of the event.
+    pub fields: Vec<(&'static str, String)>,
+    /// The time between opening the span and the event.
+    pub elapsed: Duration,
+}
+
+impl SpanTree {
+    /// The value of the field `name`. Strings are stored as they are and anything else as its
+    /// `Debug` representation.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        field(&self.fields, name)
+    }
+
+    /// The child spans directly inside of this span.
+    pub fn spans(&self) -> impl Iterator<Item = &SpanTree> + '_ {
+        self.children.iter().filter_map(|node| match node {
+            Node::Span(span) => Some(span),
+            Node::Event(_) => None,
+        })
+    }
+
+    /// The events directly inside of this span.
+    pub fn events(&self) -> impl Iterator<Item = &TreeEvent> + '_ {
+        self.children.iter().filter_map(|node| match node {
+            Node::Event(event) => Some(event),
+            Node::Span(_) => None,
+        })
+    }
+
+    /// The first span called `name` among this span and its descendants, searched depth first.
+    pub fn find(&self, name: &str) -> Option<&SpanTree> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.spans().find_map(|span| span.find(name))
+    }
+}
+
+impl TreeEvent {
+    /// The value of the field `name`, see [`SpanTree::field`].
+    pub fn field(&self, name: &str) -> Option<&str> {
+        field(&self.fields, name)
+    }
+}
+
+fn field<'a>(fields: &'a [(&'static str, String)], name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(field, _)| *field == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Compares the spans of two trees, e.g. of the same workload built twice.
+///
+/// Child spans are matched up by name, in order. The result renders as a tree of the spans
+/// of both trees, with added spans marked `+`, removed spans `-` and spans that got
+/// significantly slower `!`:
+///
+/// ```text
+///   server 10ms → 31ms
+/// !   query 2ms → 20ms (+900%)
+/// +   cache 1ms
+/// -   retry 3ms
+/// ```
+pub fn diff<'a>(before: &'a SpanTree, after: &'a SpanTree) -> Diff<'a> {
+    Diff {
+        before,
+        after,
+        threshold: 1.25,
+        min_regression: Duration::from_millis(1),
+        ansi: false,
+    }
+}
+
+/// The differences between two trees, see [`diff`].
+#[derive(Debug, Clone)]
+pub struct Diff<'a> {
+    before: &'a SpanTree,
+    after: &'a SpanTree,
+    threshold: f64,
+    min_regression: Duration,
+    ansi: bool,
+}
+
+/// How a span differs between two trees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// The span is in both trees and did not get significantly slower.
+    Unchanged,
+    /// The span is only in the second tree.
+    Added,
+    /// The span is only in the first tree.
+    Removed,
+    /// The span got significantly slower.
+    Regressed,
+}
+
+/// A span of either tree, as listed by [`Diff::entries`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DiffEntry<'a> {
+    /// How the span differs.
+    pub change: Change,
+    /// The number of ancestors of the span.
+    pub depth: usize,
+    /// The span in the second tree, or in the first one if it was removed.
+    pub span: &'a SpanTree,
+    /// How long the span took in the first tree.
+    pub before: Option<Duration>,
+    /// How long the span took in the second tree.
+    pub after: Option<Duration>,
+}
+
+impl<'a> Diff<'a> {
+    /// Spans count as regressed if they take more than `ratio` times as long as before, by
+    /// default `1.25`.
+    pub fn with_threshold(self, ratio: f64) -> Self {
+        Self {
+            threshold: ratio,
+            ..self
+        }
+    }
+
+    /// Spans only count as regressed if they take at least `duration` longer than before, by
+    /// default one millisecond, so that tiny spans don't flap.
+    pub fn with_min_regression(self, duration: Duration) -> Self {
+        Self {
+            min_regression: duration,
+            ..self
+        }
+    }
+
+    /// Whether the rendered tree colors the changes.
+    pub fn with_ansi(self, ansi: bool) -> Self {
+        Self { ansi, ..self }
+    }
+
+    /// All spans of both trees, depth first.
+    pub fn entries(&self) -> Vec<DiffEntry<'a>> {
+        let mut entries = Vec::new();
+        if self.before.name == self.after.name {
+            self.matched(self.before, self.after, 0, &mut entries);
+        } else {
+            whole(Change::Removed, self.before, 0, &mut entries);
+            whole(Change::Added, self.after, 0, &mut entries);
+        }
+        entries
+    }
+
+    /// Whether any span was added, removed or regressed.
+    pub fn has_changes(&self) -> bool {
+        self.entries()
+            .iter()
+            .any(|entry| entry.change != Change::Unchanged)
+    }
+
+    fn matched(
+        &self,
+        before: &'a SpanTree,
+        after: &'a SpanTree,
+        depth: usize,
+        entries: &mut Vec<DiffEntry<'a>>,
+    ) {
+        let slower = after.duration.saturating_sub(before.duration);
+        let regressed = slower >= self.min_regression
+            && after.duration.as_secs_f64() > before.duration.as_secs_f64() * self.threshold;
+        entries.push(DiffEntry {
+            change: if regressed {
+                Change::Regressed
+            } else {
+                Change::Unchanged
+            },
+            depth,
+            span: after,
+            before: Some(before.duration),
+            after: Some(after.duration),
+        });
+
+        let old = ordered_spans(before);
+        let new = ordered_spans(after);
+        // The longest common subsequence of names, `lcs[i][j]` for the suffixes at `i` and `j`
+        let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lcs[i][j] = if old[i].name == new[j].name {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && old[i].name == new[j].name {
+                self.matched(old[i], new[j], depth + 1, entries);
+                i += 1;
+                j += 1;
+            } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                whole(Change::Removed, old[i], depth + 1, entries);
+                i += 1;
+            } else {
+                whole(Change::Added, new[j], depth + 1, entries);
+                j += 1;
+            }
+        }
+    }
+}
+
+/// The child spans of `tree` in the order they were opened.
+fn ordered_spans(tree: &SpanTree) -> Vec<&SpanTree> {
+    let mut spans: Vec<_> = tree.spans().collect();
+    spans.sort_by_key(|span| span.start);
+    spans
+}
+
+/// Lists `tree` and all its descendants as added or removed.
+fn whole<'a>(change: Change, tree: &'a SpanTree, depth: usize, entries: &mut Vec<DiffEntry<'a>>) {
+    let duration = Some(tree.duration);
+    let (before, after) = match change {
+        Change::Removed => (duration, None),
+        _ => (None, duration),
+    };
+    entries.push(DiffEntry {
+        change,
+        depth,
+        span: tree,
+        before,
+        after,
+    });
+    for span in ordered_spans(tree) {
+        whole(change, span, depth + 1, entries);
+    }
+}
+
+impl fmt::Display for Diff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in self.entries() {
+            let (marker, style) = match entry.change {
+                Change::Unchanged => (' ', Style::new()),
+                Change::Added => ('+', Color::Green.normal()),
+                Change::Removed => ('-', Color::Red.normal()),
+                Change::Regressed => ('!', Color::Yellow.bold()),
+            };
+            let mut line = format!(
+                "{} {:indent$}{}",
+                marker,
+                "",
+                entry.span.name,
+                indent = entry.depth * 2
+            );
+            match (entry.before, entry.after) {
+                (Some(before), Some(after)) => {
+                    write!(
+                        line,
+                        " {} → {}",
+                        human_duration(before),
+                        human_duration(after)
+                    )?;
+                    if entry.change == Change::Regressed && !before.is_zero() {
+                        let percent = (after.as_secs_f64() / before.as_secs_f64() - 1.0) * 100.0;
+                        write!(line, " (+{:.0}%)", percent)?;
+                    }
+                }
+                (Some(duration), None) | (None, Some(duration)) => {
+                    write!(line, " {}", human_duration(duration))?;
+                }
+                (None, None) => {}
+            }
+            if self.ansi {
+                writeln!(f, "{}", paint(style, line))?;
+            } else {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+type OnRoot = dyn Fn(&SpanTree) + Send + Sync;
+
+/// A layer building a [`SpanTree`] for every root span.
+///
+/// Trees are handed out once their root span closes, spans that are still open are not
+/// included. Events outside of any span are not captured.
+///
+/// Clones share the same trees, so a clone can be added to the subscriber while the original
+/// is kept to [`take`](Self::take) the trees.
+#[derive(Clone, Default)]
+pub struct Builder {
+    roots: Arc<Mutex<Vec<SpanTree>>>,
+    on_root: Option<Arc<OnRoot>>,
+}
+
+impl fmt::Debug for Builder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Builder")
+            .field("roots", &self.lock().len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Builder {
+    /// Creates a layer which keeps all trees until they are [taken](Self::take).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Passes every tree to `on_root` as soon as its root span closes, instead of keeping it.
+    pub fn on_root<F>(self, on_root: F) -> Self
+    where
+        F: Fn(&SpanTree) + Send + Sync + 'static,
+    {
+        Self {
+            on_root: Some(Arc::new(on_root)),
+            ..self
+        }
+    }
+
+    /// Removes and returns the trees of all root spans closed so far, in the order they closed.
+    pub fn take(&self) -> Vec<SpanTree> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<SpanTree>> {
+        self.roots.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// The span extension holding a tree under construction.
+struct Building {
+    tree: SpanTree,
+    start: Instant,
+    /// When the root span was opened
+    root_start: Instant,
+}
+
+/// Collects fields as strings.
+struct Fields<'a> {
+    message: Option<&'a mut String>,
+    fields: &'a mut Vec<(&'static str, String)>,
+}
+
+impl Fields<'_> {
+    fn set(&mut self, field: &Field, value: String) {
+        match &mut self.message {
+            Some(message) if field.name() == "message" => **message = value,
+            _ => match self
+                .fields
+                .iter_mut()
+                .find(|(name, _)| *name == field.name())
+            {
+                Some((_, old)) => *old = value,
+                None => self.fields.push((field.name(), value)),
+            },
+        }
+    }
+}
+
+impl Visit for Fields<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.set(field, format!("{:?}", value));
+    }
+}
+
+impl<S> Layer<S> for Builder
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("in new_span but span does not exist");
+        let start = Instant::now();
+        let root_start = span
+            .parent()
+            .and_then(|parent| Some(parent.extensions().get::<Building>()?.root_start))
+            .unwrap_or(start);
+        let metadata = span.metadata();
+        let mut tree = SpanTree {
+            name: metadata.name(),
+            target: metadata.target(),
+            level: *metadata.level(),
+            fields: Vec::new(),
+            start: start - root_start,
+            duration: Duration::ZERO,
+            children: Vec::new(),
+        };
+        attrs.record(&mut Fields {
+            message: None,
+            fields: &mut tree.fields,
+        });
+        span.extensions_mut().insert(Building {
+            tree,
+            start,
+            root_start,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("in on_record but span does not exist");
+        let mut ext = span.extensions_mut();
+        if let Some(building) = ext.get_mut::<Building>() {
+            values.record(&mut Fields {
+                message: None,
+                fields: &mut building.tree.fields,
+            });
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.event_span(event) else {
+            return;
+        };
+        let mut ext = span.extensions_mut();
+        let Some(building) = ext.get_mut::<Building>() else {
+            return;
+        };
+        let metadata = event.metadata();
+        let mut captured = TreeEvent {
+            level: *metadata.level(),
+            target: metadata.target(),
+            message: String::new(),
+            fields: Vec::new(),
+            elapsed: building.start.elapsed(),
+        };
+        event.record(&mut Fields {
+            message: Some(&mut captured.message),
+            fields: &mut captured.fields,
+        });
+        building.tree.children.push(Node::Event(captured));
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let span = ctx.span(&id).expect("invalid span in on_close");
+        let Some(Building {
+            mut tree, start, ..
+        }) = span.extensions_mut().remove::<Building>()
+        else {
+            return;
+        };
+        tree.duration = start.elapsed();
+        if let Some(parent) = span.parent() {
+            if let Some(building) = parent.extensions_mut().get_mut::<Building>() {
+                building.tree.children.push(Node::Span(tree));
+            }
+        } else if let Some(on_root) = &self.on_root {
+            on_root(&tree);
+        } else {
+            self.lock().push(tree);
+        }
+    }
+}
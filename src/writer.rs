@@ -0,0 +1,164 @@
+//! Writers for use with [`HierarchicalLayer::with_writer`].
+//!
+//! [`HierarchicalLayer::with_writer`]: crate::HierarchicalLayer::with_writer
+
+use std::io;
+
+use tracing_core::{Level, Metadata};
+use tracing_subscriber::fmt::{writer::EitherWriter, MakeWriter};
+
+mod background;
+mod capture;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown", feature = "wasm"))]
+mod console;
+#[cfg(all(windows, feature = "windows-debugger"))]
+mod debugger;
+#[cfg(feature = "gzip")]
+mod gzip;
+#[cfg(all(unix, feature = "journald"))]
+mod journald;
+#[cfg(feature = "indicatif")]
+mod progress;
+
+pub use background::{Background, BackgroundWriter};
+pub use capture::{TestCapture, TestCaptureWriter};
+#[cfg(all(target_arch = "wasm32", target_os = "unknown", feature = "wasm"))]
+pub use console::{Console, ConsoleWriter};
+#[cfg(all(windows, feature = "windows-debugger"))]
+pub use debugger::{Debugger, DebuggerWriter};
+#[cfg(feature = "gzip")]
+pub use gzip::{Gzip, GzipWriter};
+#[cfg(all(unix, feature = "journald"))]
+pub use journald::{Journald, JournaldWriter};
+#[cfg(feature = "indicatif")]
+pub use progress::{Indicatif, IndicatifWriter};
+
+/// Sends severe events to one writer and everything else to another, e.g. errors and
+/// warnings to stderr and the rest to stdout.
+///
+/// Each writer receives its lines at their usual indentation, so the tree stays readable in
+/// both streams. See [`HierarchicalLayer::with_level_writers`].
+///
+/// [`HierarchicalLayer::with_level_writers`]: crate::HierarchicalLayer::with_level_writers
+#[derive(Debug, Clone)]
+pub struct LevelWriters<A, B> {
+    severe: A,
+    rest: B,
+    threshold: Level,
+}
+
+impl<A, B> LevelWriters<A, B> {
+    /// Sends `ERROR` and `WARN` events to `severe`, everything else to `rest`.
+    pub fn new(severe: A, rest: B) -> Self {
+        LevelWriters {
+            severe,
+            rest,
+            threshold: Level::WARN,
+        }
+    }
+
+    /// Sends events of `threshold` and more severe levels to the `severe` writer.
+    pub fn with_threshold(self, threshold: Level) -> Self {
+        Self { threshold, ..self }
+    }
+}
+
+impl<'a, A, B> MakeWriter<'a> for LevelWriters<A, B>
+where
+    A: MakeWriter<'a>,
+    B: MakeWriter<'a>,
+{
+    type Writer = EitherWriter<A::Writer, B::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        EitherWriter::B(self.rest.make_writer())
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        if *meta.level() <= self.threshold {
+            EitherWriter::A(self.severe.make_writer_for(meta))
+        } else {
+            EitherWriter::B(self.rest.make_writer_for(meta))
+        }
+    }
+}
+
+/// Removes ANSI escape sequences from everything written to the wrapped writer.
+///
+/// Colors are decided once for the whole layer by `with_ansi`. To write colors to one
+/// destination but plain text to another, enable colors and strip them from the plain one:
+///
+/// ```
+/// use std::io;
+/// use tracing_subscriber::fmt::writer::MakeWriterExt;
+/// use tracing_tree::{writer::StripAnsi, HierarchicalLayer};
+///
+/// # let file = io::sink;
+/// let layer = HierarchicalLayer::default()
+///     .with_ansi(true)
+///     .with_writer(io::stderr.and(StripAnsi::new(file)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct StripAnsi<M> {
+    inner: M,
+}
+
+impl<M> StripAnsi<M> {
+    /// Strips the escape sequences from the writers of `inner`.
+    pub fn new(inner: M) -> Self {
+        StripAnsi { inner }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for StripAnsi<M> {
+    type Writer = StripAnsiWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        StripAnsiWriter::new(self.inner.make_writer())
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        StripAnsiWriter::new(self.inner.make_writer_for(meta))
+    }
+}
+
+/// The writer of [`StripAnsi`].
+#[derive(Debug)]
+pub struct StripAnsiWriter<W> {
+    inner: W,
+    /// Whether the last write ended inside of an escape sequence.
+    in_escape: bool,
+    buf: Vec<u8>,
+}
+
+impl<W> StripAnsiWriter<W> {
+    fn new(inner: W) -> Self {
+        StripAnsiWriter {
+            inner,
+            in_escape: false,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl<W: io::Write> io::Write for StripAnsiWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.clear();
+        for &byte in buf {
+            if self.in_escape {
+                // Control sequences like `\x1b[1;32m` end with a letter
+                self.in_escape = !byte.is_ascii_alphabetic();
+            } else if byte == 0x1b {
+                self.in_escape = true;
+            } else {
+                self.buf.push(byte);
+            }
+        }
+        self.inner.write_all(&self.buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    io, mem,
+    sync::{
+        mpsc::{self, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+/// How many records may be waiting for the background thread by default.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Writes on a dedicated thread, so that the threads of the application never wait for I/O.
+///
+/// The lines are still rendered on the thread that emits the span or event, as the fields of
+/// an event can't outlive the callback. The rendered records are then passed to the
+/// background thread through a bounded queue. When the queue is full, the emitting thread
+/// waits, so the memory used stays bounded.
+///
+/// Every record is written with the writer returned by `make_writer()`, so writers that pick
+/// a destination per level or target should be wrapped in one `Background` each instead.
+/// Dropping the last clone waits for everything queued to be written.
+///
+/// ```
+/// use std::io;
+/// use tracing_tree::{writer::Background, HierarchicalLayer};
+///
+/// let layer = HierarchicalLayer::default().with_writer(Background::new(io::stderr));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Background {
+    worker: Arc<Worker>,
+}
+
+#[derive(Debug)]
+struct Worker {
+    sender: SyncSender<Message>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+enum Message {
+    Record(Vec<u8>),
+    Flush(SyncSender<io::Result<()>>),
+}
+
+impl std::fmt::Debug for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Message::Record(record) => f.debug_tuple("Record").field(&record.len()).finish(),
+            Message::Flush(_) => f.write_str("Flush"),
+        }
+    }
+}
+
+impl Background {
+    /// Writes to `make_writer` on a new thread, with room for 1024 queued records.
+    pub fn new<M>(make_writer: M) -> Self
+    where
+        M: for<'a> MakeWriter<'a> + Send + 'static,
+    {
+        Self::with_capacity(make_writer, DEFAULT_CAPACITY)
+    }
+
+    /// Writes to `make_writer` on a new thread, with room for `capacity` queued records.
+    pub fn with_capacity<M>(make_writer: M, capacity: usize) -> Self
+    where
+        M: for<'a> MakeWriter<'a> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let thread = thread::Builder::new()
+            .name("tracing-tree".into())
+            .spawn(move || run(make_writer, receiver))
+            .expect("failed to spawn the background writer thread");
+        Background {
+            worker: Arc::new(Worker {
+                sender,
+                thread: Mutex::new(Some(thread)),
+            }),
+        }
+    }
+}
+
+fn run<M>(make_writer: M, receiver: Receiver<Message>)
+where
+    M: for<'a> MakeWriter<'a>,
+{
+    for message in receiver {
+        match message {
+            // There is nobody to report a failure to, the record was queued long ago
+            Message::Record(record) => {
+                let _ = io::Write::write_all(&mut make_writer.make_writer(), &record);
+            }
+            Message::Flush(done) => {
+                let _ = done.send(io::Write::flush(&mut make_writer.make_writer()));
+            }
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // Replacing the sender disconnects the channel, which stops the thread once it has
+        // written everything queued
+        let (sender, _) = mpsc::sync_channel(0);
+        drop(mem::replace(&mut self.sender, sender));
+        let thread = self.thread.get_mut().unwrap_or_else(|err| err.into_inner());
+        if let Some(thread) = thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for Background {
+    type Writer = BackgroundWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        BackgroundWriter {
+            sender: &self.worker.sender,
+            buf: Vec::new(),
+        }
+    }
+}
+
+/// The writer of [`Background`].
+///
+/// Everything written is queued once the writer is flushed or dropped.
+#[derive(Debug)]
+pub struct BackgroundWriter<'a> {
+    sender: &'a SyncSender<Message>,
+    buf: Vec<u8>,
+}
+
+fn send(sender: &SyncSender<Message>, message: Message) -> io::Result<()> {
+    sender.send(message).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::BrokenPipe,
+            "the background writer has stopped",
+        )
+    })
+}
+
+impl io::Write for BackgroundWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    /// Waits until everything queued so far has been written and flushed.
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            send(self.sender, Message::Record(mem::take(&mut self.buf)))?;
+        }
+        let (done, wait) = mpsc::sync_channel(1);
+        send(self.sender, Message::Flush(done))?;
+        wait.recv().unwrap_or(Ok(()))
+    }
+}
+
+impl Drop for BackgroundWriter<'_> {
+    fn drop(&mut self) {
+        if !self.buf.is_empty() {
+            let _ = send(self.sender, Message::Record(mem::take(&mut self.buf)));
+        }
+    }
+}
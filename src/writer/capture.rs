@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex},
+    thread::{self, ThreadId},
+};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Collects the output of each test separately, so that tests can check what they logged
+/// even when they share a global subscriber.
+///
+/// The output is keyed by the thread it was written on, which is the thread of the test
+/// unless the test spawns more threads. Everything is also printed to stdout, where
+/// `cargo test` captures it and shows it for failed tests.
+///
+/// ```
+/// use tracing_subscriber::{layer::SubscriberExt, Registry};
+/// use tracing_tree::{writer::TestCapture, HierarchicalLayer};
+///
+/// let capture = TestCapture::new();
+/// let layer = HierarchicalLayer::new(2)
+///     .with_ansi(false)
+///     .with_writer(capture.clone());
+/// tracing::subscriber::with_default(Registry::default().with(layer), || {
+///     tracing::info!("hello");
+/// });
+/// capture.assert_contains("INFO hello");
+/// ```
+#[derive(Debug, Clone)]
+pub struct TestCapture {
+    outputs: Arc<Mutex<HashMap<ThreadId, Vec<u8>>>>,
+    print: bool,
+}
+
+impl Default for TestCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestCapture {
+    /// Creates an empty capture. Clones share the same output.
+    pub fn new() -> Self {
+        TestCapture {
+            outputs: Arc::default(),
+            print: true,
+        }
+    }
+
+    /// Whether to also print the output to stdout, which is enabled by default.
+    pub fn with_print(self, print: bool) -> Self {
+        Self { print, ..self }
+    }
+
+    /// Everything written on the current thread.
+    pub fn contents(&self) -> String {
+        let outputs = self.outputs.lock().unwrap_or_else(|err| err.into_inner());
+        let output = outputs.get(&thread::current().id());
+        String::from_utf8_lossy(output.map_or(&[][..], |v| v)).into_owned()
+    }
+
+    /// Discards everything written on the current thread.
+    pub fn clear(&self) {
+        let mut outputs = self.outputs.lock().unwrap_or_else(|err| err.into_inner());
+        outputs.remove(&thread::current().id());
+    }
+
+    /// Panics with the output of the current thread unless it contains `needle`.
+    #[track_caller]
+    pub fn assert_contains(&self, needle: &str) {
+        let contents = self.contents();
+        assert!(
+            contents.contains(needle),
+            "output does not contain {:?}:\n{}",
+            needle,
+            contents
+        );
+    }
+}
+
+impl<'a> MakeWriter<'a> for TestCapture {
+    type Writer = TestCaptureWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        TestCaptureWriter { capture: self }
+    }
+}
+
+/// The writer of [`TestCapture`].
+#[derive(Debug)]
+pub struct TestCaptureWriter<'a> {
+    capture: &'a TestCapture,
+}
+
+impl io::Write for TestCaptureWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.capture.print {
+            // `print!` rather than writing to `io::stdout()`, as only the former is captured
+            print!("{}", String::from_utf8_lossy(buf));
+        }
+        let mut outputs = self
+            .capture
+            .outputs
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        outputs
+            .entry(thread::current().id())
+            .or_default()
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use std::io;
+
+use tracing_core::{Level, Metadata};
+use tracing_subscriber::fmt::MakeWriter;
+use wasm_bindgen::JsValue;
+
+/// Sends every line to the browser's console, using `console.error` for errors,
+/// `console.warn` for warnings and so on, so that the console's level filter applies.
+///
+/// Disable colors with [`with_ansi(false)`](crate::HierarchicalLayer::with_ansi), as most
+/// consoles show escape sequences verbatim.
+///
+/// ```no_run
+/// use tracing_tree::{writer::Console, HierarchicalLayer};
+///
+/// let layer = HierarchicalLayer::default()
+///     .with_ansi(false)
+///     .with_writer(Console);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Console;
+
+impl<'a> MakeWriter<'a> for Console {
+    type Writer = ConsoleWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        ConsoleWriter::new(Level::INFO)
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        ConsoleWriter::new(*meta.level())
+    }
+}
+
+/// The writer of [`Console`].
+///
+/// Complete lines are sent right away, the rest once the writer is flushed or dropped.
+#[derive(Debug)]
+pub struct ConsoleWriter {
+    level: Level,
+    line: Vec<u8>,
+}
+
+impl ConsoleWriter {
+    fn new(level: Level) -> Self {
+        ConsoleWriter {
+            level,
+            line: Vec::new(),
+        }
+    }
+
+    fn send_line(&mut self) {
+        let line = JsValue::from_str(&String::from_utf8_lossy(&self.line));
+        match self.level {
+            Level::ERROR => web_sys::console::error_1(&line),
+            Level::WARN => web_sys::console::warn_1(&line),
+            Level::INFO => web_sys::console::info_1(&line),
+            Level::DEBUG => web_sys::console::log_1(&line),
+            Level::TRACE => web_sys::console::debug_1(&line),
+        }
+        self.line.clear();
+    }
+}
+
+impl io::Write for ConsoleWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            match line.strip_suffix(b"\n") {
+                Some(line) => {
+                    self.line.extend_from_slice(line);
+                    self.send_line();
+                }
+                None => self.line.extend_from_slice(line),
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.send_line();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ConsoleWriter {
+    fn drop(&mut self) {
+        let _ = io::Write::flush(self);
+    }
+}
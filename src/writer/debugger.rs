@@ -0,0 +1,78 @@
+use std::io;
+
+use tracing_subscriber::fmt::MakeWriter;
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn OutputDebugStringW(output_string: *const u16);
+}
+
+/// Sends every line to the debugger via `OutputDebugStringW`, e.g. to view the tree of a GUI
+/// application without console in DebugView or the output window of Visual Studio.
+///
+/// Disable colors with [`with_ansi(false)`](crate::HierarchicalLayer::with_ansi), as debuggers
+/// show escape sequences verbatim.
+///
+/// ```no_run
+/// use tracing_tree::{writer::Debugger, HierarchicalLayer};
+///
+/// let layer = HierarchicalLayer::default()
+///     .with_ansi(false)
+///     .with_writer(Debugger);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Debugger;
+
+impl<'a> MakeWriter<'a> for Debugger {
+    type Writer = DebuggerWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        DebuggerWriter { line: Vec::new() }
+    }
+}
+
+/// The writer of [`Debugger`].
+///
+/// Complete lines are sent right away, the rest once the writer is flushed or dropped.
+#[derive(Debug)]
+pub struct DebuggerWriter {
+    line: Vec<u8>,
+}
+
+impl DebuggerWriter {
+    fn send_line(&mut self) {
+        let line = String::from_utf8_lossy(&self.line);
+        let wide: Vec<u16> = line.encode_utf16().chain([u16::from(b'\n'), 0]).collect();
+        // SAFETY: `wide` is a nul-terminated UTF-16 string that outlives the call
+        unsafe { OutputDebugStringW(wide.as_ptr()) };
+        self.line.clear();
+    }
+}
+
+impl io::Write for DebuggerWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            match line.strip_suffix(b"\n") {
+                Some(line) => {
+                    self.line.extend_from_slice(line);
+                    self.send_line();
+                }
+                None => self.line.extend_from_slice(line),
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.send_line();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DebuggerWriter {
+    fn drop(&mut self) {
+        let _ = io::Write::flush(self);
+    }
+}
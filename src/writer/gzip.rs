@@ -0,0 +1,123 @@
+use std::{
+    fmt, io,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Compresses everything written to the wrapped writer with gzip, e.g. to keep verbose logs
+/// of a long-running service on disk.
+///
+/// The gzip stream is finished when the last clone is dropped, or explicitly with
+/// [`finish`](Self::finish). [`HierarchicalLayer::flush`] makes everything written so far
+/// readable even if the process does not shut down cleanly.
+///
+/// ```no_run
+/// use std::fs::File;
+/// use tracing_tree::{writer::Gzip, HierarchicalLayer};
+///
+/// let file = File::create("trace.log.gz").unwrap();
+/// let layer = HierarchicalLayer::default()
+///     .with_ansi(false)
+///     .with_writer(Gzip::new(file));
+/// ```
+///
+/// [`HierarchicalLayer::flush`]: crate::HierarchicalLayer::flush
+pub struct Gzip<W: io::Write> {
+    encoder: Arc<Encoder<W>>,
+}
+
+struct Encoder<W: io::Write>(Mutex<Option<GzEncoder<W>>>);
+
+impl<W: io::Write> Gzip<W> {
+    /// Compresses with the default compression level.
+    pub fn new(inner: W) -> Self {
+        Self::from_encoder(GzEncoder::new(inner, Compression::default()))
+    }
+
+    /// Compresses with a compression level from 0 (none) to 9 (best).
+    pub fn with_level(inner: W, level: u32) -> Self {
+        Self::from_encoder(GzEncoder::new(inner, Compression::new(level.min(9))))
+    }
+
+    fn from_encoder(encoder: GzEncoder<W>) -> Self {
+        Gzip {
+            encoder: Arc::new(Encoder(Mutex::new(Some(encoder)))),
+        }
+    }
+
+    /// Finishes the gzip stream and returns the wrapped writer. Everything written afterwards
+    /// fails with an error.
+    pub fn finish(&self) -> io::Result<W> {
+        match self.encoder.lock().take() {
+            Some(encoder) => encoder.finish(),
+            None => Err(finished()),
+        }
+    }
+}
+
+impl<W: io::Write> Encoder<W> {
+    fn lock(&self) -> MutexGuard<'_, Option<GzEncoder<W>>> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<W: io::Write> Drop for Encoder<W> {
+    fn drop(&mut self) {
+        if let Some(encoder) = self.lock().take() {
+            // There is nobody left to report a failure to
+            let _ = encoder.finish();
+        }
+    }
+}
+
+fn finished() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "the gzip stream is already finished")
+}
+
+impl<W: io::Write> Clone for Gzip<W> {
+    fn clone(&self) -> Self {
+        Gzip {
+            encoder: self.encoder.clone(),
+        }
+    }
+}
+
+impl<W: io::Write> fmt::Debug for Gzip<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Gzip").finish_non_exhaustive()
+    }
+}
+
+impl<'a, W: io::Write + 'a> MakeWriter<'a> for Gzip<W> {
+    type Writer = GzipWriter<'a, W>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        GzipWriter {
+            encoder: self.encoder.lock(),
+        }
+    }
+}
+
+/// The writer of [`Gzip`], which holds a lock on the encoder.
+pub struct GzipWriter<'a, W: io::Write> {
+    encoder: MutexGuard<'a, Option<GzEncoder<W>>>,
+}
+
+impl<W: io::Write> fmt::Debug for GzipWriter<'_, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GzipWriter").finish_non_exhaustive()
+    }
+}
+
+impl<W: io::Write> io::Write for GzipWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.as_mut().ok_or_else(finished)?.write(buf)
+    }
+
+    /// Compresses everything written so far, so that it can be decompressed.
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.as_mut().ok_or_else(finished)?.flush()
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    io,
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use tracing_core::{Level, Metadata};
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::format::syslog_priority;
+
+/// Sends every line to the systemd journal with a `PRIORITY` derived from the level of its
+/// event or span, so that `journalctl -p` can filter the tree by severity.
+///
+/// Each line becomes one journal entry whose `MESSAGE` keeps the tree prefix. Disable colors
+/// with [`with_ansi(false)`](crate::HierarchicalLayer::with_ansi), as the journal shows escape
+/// sequences verbatim.
+///
+/// ```no_run
+/// use tracing_tree::{writer::Journald, HierarchicalLayer};
+///
+/// let layer = HierarchicalLayer::default()
+///     .with_ansi(false)
+///     .with_writer(Journald::new().unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Journald {
+    socket: Arc<UnixDatagram>,
+    path: PathBuf,
+    identifier: Option<String>,
+}
+
+impl Journald {
+    /// Connects to the journal of the system.
+    pub fn new() -> io::Result<Self> {
+        Self::with_socket_path("/run/systemd/journal/socket")
+    }
+
+    /// Connects to a journal listening on `path`.
+    pub fn with_socket_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        Ok(Journald {
+            socket: Arc::new(socket),
+            path: path.as_ref().to_owned(),
+            identifier: std::env::args_os()
+                .next()
+                .and_then(|arg0| Some(Path::new(&arg0).file_name()?.to_str()?.to_owned())),
+        })
+    }
+
+    /// Sets the `SYSLOG_IDENTIFIER` of the entries. Defaults to the name of the executable.
+    pub fn with_identifier(self, identifier: impl Into<String>) -> Self {
+        Self {
+            identifier: Some(identifier.into()),
+            ..self
+        }
+    }
+
+    fn writer(&self, priority: u8) -> JournaldWriter<'_> {
+        JournaldWriter {
+            journald: self,
+            priority,
+            line: Vec::new(),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for Journald {
+    type Writer = JournaldWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.writer(syslog_priority(&Level::INFO))
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        self.writer(syslog_priority(meta.level()))
+    }
+}
+
+/// The writer of [`Journald`].
+///
+/// Complete lines are sent right away, the rest once the writer is flushed or dropped.
+#[derive(Debug)]
+pub struct JournaldWriter<'a> {
+    journald: &'a Journald,
+    priority: u8,
+    line: Vec<u8>,
+}
+
+impl JournaldWriter<'_> {
+    fn send_line(&mut self) -> io::Result<()> {
+        let mut entry = Vec::new();
+        write_field(&mut entry, "PRIORITY", self.priority.to_string().as_bytes());
+        if let Some(identifier) = &self.journald.identifier {
+            write_field(&mut entry, "SYSLOG_IDENTIFIER", identifier.as_bytes());
+        }
+        write_field(&mut entry, "MESSAGE", &self.line);
+        self.line.clear();
+        self.journald
+            .socket
+            .send_to(&entry, &self.journald.path)
+            .map(drop)
+    }
+}
+
+/// Writes a field in the binary format of the native journal protocol, which allows any bytes
+/// in the value.
+fn write_field(entry: &mut Vec<u8>, name: &str, value: &[u8]) {
+    entry.extend_from_slice(name.as_bytes());
+    entry.push(b'\n');
+    entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    entry.extend_from_slice(value);
+    entry.push(b'\n');
+}
+
+impl io::Write for JournaldWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            match line.strip_suffix(b"\n") {
+                Some(line) => {
+                    self.line.extend_from_slice(line);
+                    self.send_line()?;
+                }
+                None => self.line.extend_from_slice(line),
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.send_line()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for JournaldWriter<'_> {
+    fn drop(&mut self) {
+        let _ = io::Write::flush(self);
+    }
+}
@@ -0,0 +1,108 @@
+use std::io;
+
+use indicatif::MultiProgress;
+use tracing_core::Metadata;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Writes lines while the progress bars of a [`MultiProgress`] are hidden, so that the tree
+/// and the bars don't overwrite each other.
+///
+/// The bars are redrawn below the new lines. Lines are written to stderr by default, which is
+/// also where indicatif draws; use [`with_writer`](Self::with_writer) to change this.
+///
+/// ```no_run
+/// use indicatif::{MultiProgress, ProgressBar};
+/// use tracing_tree::{writer::Indicatif, HierarchicalLayer};
+///
+/// let bars = MultiProgress::new();
+/// let layer = HierarchicalLayer::default().with_writer(Indicatif::new(bars.clone()));
+/// // ... install the subscriber ...
+/// let bar = bars.add(ProgressBar::new(100));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Indicatif<M = fn() -> io::Stderr> {
+    bars: MultiProgress,
+    make_writer: M,
+}
+
+impl Indicatif {
+    /// Writes to stderr around the bars of `bars`.
+    pub fn new(bars: MultiProgress) -> Self {
+        Indicatif {
+            bars,
+            make_writer: io::stderr,
+        }
+    }
+}
+
+impl<M> Indicatif<M> {
+    /// Writes to `make_writer` instead of stderr.
+    pub fn with_writer<M2>(self, make_writer: M2) -> Indicatif<M2>
+    where
+        M2: for<'a> MakeWriter<'a>,
+    {
+        Indicatif {
+            bars: self.bars,
+            make_writer,
+        }
+    }
+}
+
+impl<'a, M> MakeWriter<'a> for Indicatif<M>
+where
+    M: MakeWriter<'a>,
+{
+    type Writer = IndicatifWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        IndicatifWriter::new(&self.bars, self.make_writer.make_writer())
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        IndicatifWriter::new(&self.bars, self.make_writer.make_writer_for(meta))
+    }
+}
+
+/// The writer of [`Indicatif`].
+///
+/// Everything written is held back until the writer is flushed or dropped and then written
+/// at once with the bars hidden.
+#[derive(Debug)]
+pub struct IndicatifWriter<W: io::Write> {
+    bars: MultiProgress,
+    inner: W,
+    buf: Vec<u8>,
+}
+
+impl<W: io::Write> IndicatifWriter<W> {
+    fn new(bars: &MultiProgress, inner: W) -> Self {
+        IndicatifWriter {
+            bars: bars.clone(),
+            inner,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl<W: io::Write> io::Write for IndicatifWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return self.inner.flush();
+        }
+        let Self { bars, inner, buf } = self;
+        let result = bars.suspend(|| inner.write_all(buf).and_then(|()| inner.flush()));
+        buf.clear();
+        result
+    }
+}
+
+impl<W: io::Write> Drop for IndicatifWriter<W> {
+    fn drop(&mut self) {
+        let _ = io::Write::flush(self);
+    }
+}
@@ -0,0 +1,54 @@
+use std::io::{self, IsTerminal};
+
+use tracing_tree::{writer::LevelWriters, AnsiMode, HierarchicalLayer};
+
+#[test]
+fn detects_colors_from_the_writer() {
+    let stderr = io::stderr().is_terminal();
+    let stdout = io::stdout().is_terminal();
+
+    assert_eq!(HierarchicalLayer::new(2).ansi(), stderr);
+    assert_eq!(
+        HierarchicalLayer::new(2).with_writer(io::stdout).ansi(),
+        stdout
+    );
+    assert_eq!(
+        HierarchicalLayer::new(2)
+            .with_writer(LevelWriters::new(io::stderr, io::stdout))
+            .ansi(),
+        stderr && stdout
+    );
+    assert!(!HierarchicalLayer::new(2).with_writer(io::sink).ansi());
+}
+
+#[test]
+fn explicit_colors_survive_a_new_writer() {
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(true)
+        .with_writer(io::sink);
+    assert!(layer.ansi());
+
+    let layer = HierarchicalLayer::new(2)
+        .with_options("ansi")
+        .with_writer(io::sink);
+    assert!(layer.ansi());
+}
+
+#[test]
+fn auto_colors_follow_the_writer_again() {
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(true)
+        .with_ansi_mode(AnsiMode::Auto)
+        .with_writer(io::sink);
+    assert!(!layer.ansi());
+
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(io::sink)
+        .with_options("ansi=false,ansi=auto");
+    assert!(!layer.ansi());
+
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(io::sink)
+        .with_ansi_mode(AnsiMode::Always);
+    assert!(layer.ansi());
+}
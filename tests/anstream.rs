@@ -0,0 +1,26 @@
+#![cfg(feature = "anstream")]
+
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[test]
+fn renders_styles_with_anstyle() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(true)
+        .with_writer(output.clone());
+
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("conn", port = 42381).in_scope(|| info!("connected"));
+    });
+
+    assert_eq!(
+        output.contents(),
+        " \x1b[1m\x1b[32mconn\x1b[0m port=42381\n  \x1b[1m\x1b[32m INFO\x1b[0m connected\n"
+    );
+}
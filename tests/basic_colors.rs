@@ -0,0 +1,49 @@
+mod common;
+
+use tracing::{info, info_span, warn};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{HierarchicalLayer, Theme};
+
+use common::Output;
+
+fn render(theme: Theme) -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(true)
+        .with_indent_lines(true)
+        .with_guide_gradient(true)
+        .with_basic_colors(true)
+        .with_theme(theme)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request").in_scope(|| {
+            info!("handling");
+            warn!("slow");
+        });
+    });
+    output.contents()
+}
+
+fn assert_basic(output: &str) {
+    assert!(!output.contains("38;2;"), "{:?}", output);
+    assert!(!output.contains("38;5;"), "{:?}", output);
+}
+
+#[test]
+fn replaces_extended_colors() {
+    let dark = render(Theme::default());
+    assert_basic(&dark);
+    assert!(dark.contains("33m WARN"), "{:?}", dark);
+    assert!(dark.contains("\x1b[36m┐"), "{:?}", dark);
+
+    let light = render(Theme::light());
+    assert_basic(&light);
+    assert!(light.contains("32mrequest"), "{:?}", light);
+    assert!(light.contains("33m WARN"), "{:?}", light);
+}
+
+#[test]
+fn basic_colors_option() {
+    let layer = HierarchicalLayer::default().with_options("basic_colors");
+    assert!(layer.config().basic_colors);
+}
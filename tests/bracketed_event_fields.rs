@@ -0,0 +1,54 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{HierarchicalLayer, MessagePosition};
+
+use common::Output;
+
+fn render(position: MessagePosition) -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_bracketed_fields(true)
+        .with_bracketed_event_fields(true)
+        .with_message_position(position)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request", id = 1).in_scope(|| {
+            info!(status = 200, bytes = 5, "done");
+            info!(status = 500);
+            info!("plain");
+        });
+    });
+    output.contents()
+}
+
+#[test]
+fn event_fields_are_bracketed() {
+    assert_eq!(
+        render(MessagePosition::First),
+        concat!(
+            "┐request{id=1}\n",
+            "├─ INFO done {status=200, bytes=5}\n",
+            "├─ INFO {status=500}\n",
+            "├─ INFO plain\n",
+            "┘\n",
+        )
+    );
+}
+
+#[test]
+fn message_follows_bracketed_fields() {
+    assert_eq!(
+        render(MessagePosition::Last),
+        concat!(
+            "┐request{id=1}\n",
+            "├─ INFO {status=200, bytes=5} done\n",
+            "├─ INFO {status=500}\n",
+            "├─ INFO plain\n",
+            "┘\n",
+        )
+    );
+}
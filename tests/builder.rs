@@ -0,0 +1,58 @@
+use tracing_tree::{ConfigError, HierarchicalLayer};
+
+#[test]
+fn accepts_valid_combinations() {
+    HierarchicalLayer::builder()
+        .with_indent_lines(true)
+        .with_verbose_entry(true)
+        .with_verbose_exit(true)
+        .with_span_retrace(true)
+        .with_retrace_depth(2)
+        .build()
+        .unwrap();
+}
+
+#[test]
+fn rejects_invalid_combinations() {
+    let err = |builder: tracing_tree::HierarchicalLayerBuilder| builder.build().unwrap_err();
+
+    assert_eq!(
+        err(HierarchicalLayer::builder()
+            .with_indent_lines(true)
+            .with_indent_amount(0)),
+        ConfigError::ZeroIndentWithLines
+    );
+    assert_eq!(
+        err(HierarchicalLayer::builder().with_wraparound(0)),
+        ConfigError::ZeroWraparound
+    );
+    assert_eq!(
+        err(HierarchicalLayer::builder().with_verbose_entry(true)),
+        ConfigError::VerboseEntryWithoutExit
+    );
+    assert_eq!(
+        err(HierarchicalLayer::builder().with_retrace_depth(3)),
+        ConfigError::RetraceDepthWithoutRetrace
+    );
+    assert_eq!(
+        err(HierarchicalLayer::builder()
+            .with_deferred_spans(true)
+            .with_retrace_depth(0)),
+        ConfigError::ZeroRetraceDepth
+    );
+}
+
+#[test]
+fn normalizes_invalid_combinations() {
+    let layer = HierarchicalLayer::builder()
+        .with_indent_lines(true)
+        .with_indent_amount(0)
+        .with_wraparound(0)
+        .with_verbose_entry(true)
+        .with_retrace_depth(0)
+        .build_normalized();
+
+    tracing_tree::HierarchicalLayerBuilder::from(layer)
+        .build()
+        .unwrap();
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "chrome")]
+
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{chrome::ChromeTrace, HierarchicalLayer};
+
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn trace_events() {
+    let out = Shared::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(io::sink)
+        .with_chrome_trace(ChromeTrace::new(out.clone()));
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("outer", id = 7).in_scope(|| info!(n = 3, "hello"));
+    });
+
+    let trace = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<_> = trace.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", trace);
+    assert_eq!(lines[0], "[");
+    assert!(lines[1].starts_with(r#"{"name":"hello","cat":"chrome","ph":"i","ts":"#));
+    assert!(lines[1].ends_with(r#","s":"t","args":{"level":"INFO","n":"3"}},"#));
+    assert!(lines[2].starts_with(r#"{"name":"outer","cat":"chrome","ph":"X","ts":"#));
+    assert!(lines[2].ends_with(r#","args":{"id":"7"}}"#), "{}", lines[2]);
+    assert!(lines[2].contains(r#","tid":1,"dur":"#));
+    assert_eq!(lines[3], "]");
+}
+
+#[test]
+fn empty_trace() {
+    let out = Shared::default();
+    drop(ChromeTrace::new(out.clone()));
+    assert_eq!(out.0.lock().unwrap().as_slice(), b"[]\n");
+}
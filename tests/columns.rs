@@ -0,0 +1,61 @@
+mod common;
+
+use std::thread;
+
+use tracing::{info, info_span, warn};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{time::Uptime, Columns, HierarchicalLayer};
+
+use common::Output;
+
+fn render(columns: Columns) -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_timer(Uptime::default())
+        .with_deterministic_output(true)
+        .with_columns(columns)
+        .with_writer(output.clone());
+    thread::Builder::new()
+        .name("worker".to_owned())
+        .spawn(move || {
+            tracing::subscriber::with_default(Registry::default().with(layer), || {
+                info!("starting");
+                info_span!("server", port = 80).in_scope(|| {
+                    info_span!("conn").in_scope(|| warn!(peer = 1, "accepted"));
+                });
+            });
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    output.contents()
+}
+
+#[test]
+fn prints_events_in_columns() {
+    assert_eq!(
+        render(Columns::default()),
+        concat!(
+            "   0.000000s | 1:worker     |  0 | INFO  |                          | starting |\n",
+            "   0.000000s | 1:worker     |  2 | WARN  | server:conn              | accepted | peer=1\n",
+        )
+    );
+}
+
+#[test]
+fn custom_delimiter_and_widths() {
+    let columns = Columns::new("\t").with_thread_width(0).with_path_width(0);
+    assert_eq!(
+        render(columns),
+        "   0.000000s\t1:worker\t 0\tINFO \t\tstarting\n   0.000000s\t1:worker\t 2\tWARN \tserver:conn\taccepted\tpeer=1\n"
+    );
+}
+
+#[test]
+fn columns_option() {
+    let layer = HierarchicalLayer::default().with_options("columns=;");
+    assert_eq!(layer.config().columns, Some(Columns::new(";")));
+    let layer = HierarchicalLayer::default().with_options("columns");
+    assert_eq!(layer.config().columns, Some(Columns::default()));
+}
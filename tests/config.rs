@@ -0,0 +1,72 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{Config, HierarchicalLayer};
+
+use common::Output;
+
+fn render(config: Config) -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::default()
+        .with_config(config)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("conn", port = 42381).in_scope(|| info!("connected"));
+    });
+    output.contents()
+}
+
+#[test]
+fn with_config_replaces_options() {
+    let base = Config::default().with_ansi(false).with_indent_lines(true);
+    let bracketed = base.clone().with_bracketed_fields(true);
+
+    assert_eq!(render(base), "┐conn port=42381\n├─ INFO connected\n┘\n");
+    assert_eq!(
+        render(bracketed),
+        "┐conn{port=42381}\n├─ INFO connected\n┘\n"
+    );
+}
+
+#[test]
+fn accessors_reflect_options() {
+    let layer = HierarchicalLayer::new(4)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_deferred_spans(true)
+        .with_span_retrace(true)
+        .with_wraparound(8);
+
+    assert!(!layer.ansi());
+    assert_eq!(layer.indent_amount(), 4);
+    assert!(layer.indent_lines());
+    assert!(layer.is_deferred());
+    assert!(layer.is_retracing());
+    assert_eq!(layer.wraparound(), 8);
+    assert!(!layer.targets());
+    assert!(!layer.verbose_entry());
+    assert_eq!(layer.config().indent_amount, 4);
+}
+
+#[test]
+fn ci_preset_has_a_fixed_width() {
+    let layer = HierarchicalLayer::ci();
+    assert_eq!(layer.width(), Some(80));
+    assert!(!layer.ansi());
+}
+
+#[test]
+fn options_ignore_values_the_builder_rejects() {
+    let layer = HierarchicalLayer::new(2).with_options("wraparound=0,retrace_depth=0");
+    assert_eq!(layer.wraparound(), usize::MAX);
+    assert_eq!(layer.config().retrace_depth, usize::MAX);
+
+    let layer = HierarchicalLayer::new(2).with_options("indent=0,lines");
+    assert_eq!(layer.indent_amount(), 2);
+
+    let config = Config::default()
+        .with_ansi(false)
+        .with_options("wraparound=0");
+    assert_eq!(render(config), " conn port=42381\n  INFO connected\n");
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use std::thread;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{Glyphs, HierarchicalLayer};
+
+use common::Output;
+
+fn render(
+    configure: impl FnOnce(HierarchicalLayer<Output>) -> HierarchicalLayer<Output>,
+) -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(output.clone())
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_thread_names(true);
+    let layer = configure(layer);
+    thread::Builder::new()
+        .name("worker".to_owned())
+        .spawn(move || {
+            tracing::subscriber::with_default(Registry::default().with(layer), || {
+                info_span!("job").in_scope(|| info!("first\nsecond"));
+            });
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    output.contents()
+}
+
+#[test]
+fn thread_prefix_is_repeated_by_default() {
+    assert_eq!(
+        render(|layer| layer),
+        concat!(
+            "worker┐job \n",
+            "worker├─ INFO first\n",
+            "worker│ second\n",
+            "worker┘\n",
+        )
+    );
+}
+
+#[test]
+fn thread_prefix_can_be_blanked() {
+    assert_eq!(
+        render(|layer| layer.with_continuation_thread_prefix(false)),
+        concat!(
+            "worker┐job \n",
+            "worker├─ INFO first\n",
+            "      │ second\n",
+            "worker┘\n",
+        )
+    );
+}
+
+#[test]
+fn custom_continuation_glyph() {
+    assert_eq!(
+        render(|layer| layer.with_glyphs(Glyphs::unicode().with_continuation("┆   "))),
+        concat!(
+            "worker┐job \n",
+            "worker├─ INFO first\n",
+            "worker┆   second\n",
+            "worker┘\n",
+        )
+    );
+}
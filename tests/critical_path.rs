@@ -0,0 +1,53 @@
+mod common;
+
+use std::{thread, time::Duration};
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[test]
+fn printed_on_root_close() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_critical_path(true)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("server").in_scope(|| {
+            info_span!("cache").in_scope(|| info!("hit"));
+            info_span!("query").in_scope(|| {
+                info_span!("plan").in_scope(|| {});
+                info_span!("parse").in_scope(|| thread::sleep(Duration::from_millis(10)));
+            });
+        });
+    });
+
+    let contents = output.contents();
+    let lines: Vec<_> = contents.lines().collect();
+    let path: Vec<_> = lines
+        .iter()
+        .filter(|line| line.contains("critical path"))
+        .collect();
+    assert_eq!(path.len(), 1, "{}", contents);
+    assert!(
+        path[0].starts_with(" critical path: server → query → parse ("),
+        "{}",
+        contents
+    );
+    assert_eq!(lines.last(), Some(path[0]));
+}
+
+#[test]
+fn off_by_default() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("server").in_scope(|| info_span!("query").in_scope(|| {}));
+    });
+    assert!(!output.contents().contains("critical path"));
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "csv")]
+
+mod common;
+
+use tracing::{info, info_span, warn};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+/// Replaces the `elapsed_us` cell, which differs between runs.
+fn strip_elapsed(line: &str) -> String {
+    let mut cells: Vec<_> = line.splitn(5, ',').collect();
+    if cells[3].chars().all(|c| c.is_ascii_digit()) && !cells[3].is_empty() {
+        cells[3] = "N";
+    }
+    cells.join(",")
+}
+
+#[test]
+fn exports_events() {
+    let tree = Output::default();
+    let export = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_max_events_per_span(1)
+        .with_writer(tree.clone())
+        .with_csv_export(export.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!(target: "app", "server", port = 8080).in_scope(|| {
+            info_span!(target: "app", "conn").in_scope(|| {
+                info!(target: "app", peer = "a,b", id = 7, "connected");
+                warn!(target: "app", "dropped");
+            });
+        });
+        info!(target: "app", "outside");
+    });
+
+    let lines: Vec<_> = export.contents().lines().map(strip_elapsed).collect();
+    assert_eq!(
+        lines,
+        [
+            "level,target,path,elapsed_us,message,fields",
+            r#"INFO,app,server:conn,N,connected,"peer=""a,b"" id=7""#,
+            "WARN,app,server:conn,N,dropped,",
+            "INFO,app,,,outside,",
+        ]
+    );
+    assert!(!tree.contents().contains("dropped"));
+}
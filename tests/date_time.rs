@@ -0,0 +1,69 @@
+#![cfg(feature = "time")]
+
+use std::time::Duration;
+
+use tracing_tree::time::{
+    CachedLocalDateTime, FormatTime, FormattedDateTime, OffsetDateTime, SystemClock,
+};
+
+fn formatted(timer: &impl FormatTime) -> String {
+    let mut buf = String::new();
+    timer.format_time(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn formatted_date_time() {
+    let timer = FormattedDateTime::new(
+        "[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:3]",
+    )
+    .unwrap()
+    .with_utc(true);
+    let time = formatted(&timer);
+    assert_eq!(time.len(), "2024-01-31 12:34:56.789".len(), "{}", time);
+    assert_eq!(&time[4..5], "-");
+    assert_eq!(&time[19..20], ".");
+}
+
+#[test]
+fn invalid_format_description() {
+    assert!(FormattedDateTime::new("[year").is_err());
+    assert!(FormattedDateTime::new("[nonsense]").is_err());
+}
+
+#[test]
+fn fixed_offset() {
+    let offset = time::UtcOffset::from_hms(5, 30, 0).unwrap();
+    assert!(formatted(&OffsetDateTime::from(offset)).ends_with(" +05:30:00"));
+    let offset = time::UtcOffset::from_hms(-8, 0, 0).unwrap();
+    assert!(formatted(&OffsetDateTime::from(offset)).ends_with(" -08:00:00"));
+}
+
+#[test]
+fn system_clock_matches_time_crate() {
+    let minute = FormattedDateTime::new("[year]-[month]-[day]T[hour]:[minute]")
+        .unwrap()
+        .with_utc(true);
+    // Retry in case the minute changed in between
+    let matches = (0..2).any(|_| {
+        let expected = formatted(&minute);
+        formatted(&SystemClock::default()).starts_with(&expected)
+    });
+    assert!(matches);
+}
+
+#[test]
+fn cached_local_offset() {
+    // Test threads usually cannot determine the local offset, so the timer keeps using UTC
+    let timer = CachedLocalDateTime::utc().with_refresh_interval(Duration::ZERO);
+    let time = formatted(&timer);
+    assert!(
+        time.ends_with(" +00:00:00") || CachedLocalDateTime::new().is_ok(),
+        "{}",
+        time
+    );
+
+    if let Ok(timer) = CachedLocalDateTime::new() {
+        assert!(formatted(&timer).contains(':'));
+    }
+}
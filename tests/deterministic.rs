@@ -0,0 +1,56 @@
+mod common;
+
+use std::{thread, time::Duration};
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{
+    time::{HumanDuration, SystemClock},
+    HierarchicalLayer,
+};
+
+use common::Output;
+
+fn run() -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_thread_ids(true)
+        .with_event_deltas(true)
+        .with_critical_path(true)
+        .with_timer(HumanDuration(SystemClock::default()))
+        .with_deterministic_output(true)
+        .with_writer(output.clone());
+    let subscriber = Registry::default().with(layer);
+    let dispatch = tracing::Dispatch::new(subscriber);
+    tracing::dispatcher::with_default(&dispatch, || {
+        info_span!("server").in_scope(|| {
+            info!("started");
+            thread::sleep(Duration::from_millis(3));
+            info!("listening");
+        });
+        let dispatch = dispatch.clone();
+        thread::spawn(move || {
+            tracing::dispatcher::with_default(&dispatch, || info!("worker"));
+        })
+        .join()
+        .unwrap();
+    });
+    output.contents()
+}
+
+#[test]
+fn stable_across_runs() {
+    let first = run();
+    assert_eq!(first, run());
+    assert_eq!(
+        first,
+        "\
+1 server 
+1 0000-00-00T00:00:00.000Z       0ns +0ns INFO started
+1 0000-00-00T00:00:00.000Z       0ns +0ns INFO listening
+1 critical path: server (0ns)
+2 0000-00-00T00:00:00.000Z INFO worker
+"
+    );
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use tracing_tree::time::{
+    DurationFormat, DurationUnit, FormatTime, HumanDuration, OnChange, SystemClock, Uptime,
+};
+
+fn styled(timer: &impl FormatTime, elapsed: Duration) -> String {
+    let mut buf = String::new();
+    timer.style_timestamp(false, elapsed, &mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn human_duration() {
+    let timer = HumanDuration::new();
+    assert_eq!(styled(&timer, Duration::from_nanos(12)), "     12ns");
+    assert_eq!(styled(&timer, Duration::from_micros(450)), "    450μs");
+    assert_eq!(styled(&timer, Duration::from_micros(1_450)), "      1ms");
+    assert_eq!(styled(&timer, Duration::from_millis(12_345)), "12s 345ms");
+    assert_eq!(styled(&timer, Duration::from_secs(5)), "       5s");
+    assert_eq!(styled(&timer, Duration::from_secs(83)), "   1m 23s");
+    assert_eq!(styled(&timer, Duration::from_secs(7_260)), "    2h 1m");
+}
+
+#[test]
+fn duration_format_default() {
+    let timer = DurationFormat::new();
+    for elapsed in [
+        Duration::from_micros(450),
+        Duration::from_millis(12),
+        Duration::from_millis(1_999),
+        Duration::from_secs(59),
+        Duration::from_secs(150),
+    ] {
+        assert_eq!(styled(&timer, elapsed), styled(&Uptime::default(), elapsed));
+    }
+}
+
+#[test]
+fn duration_format_fixed_unit() {
+    let micros = DurationFormat::new()
+        .with_unit(DurationUnit::Micros)
+        .with_width(7);
+    assert_eq!(styled(&micros, Duration::from_nanos(450)), "      0μs");
+    assert_eq!(styled(&micros, Duration::from_millis(12)), "  12000μs");
+
+    let secs = DurationFormat::new()
+        .with_unit(DurationUnit::Secs)
+        .with_precision(3);
+    assert_eq!(styled(&secs, Duration::from_micros(1_999)), "0.001s ");
+    assert_eq!(styled(&secs, Duration::from_secs(150)), "150.000s ");
+}
+
+#[test]
+fn duration_format_truncates_exactly() {
+    let secs = DurationFormat::new()
+        .with_unit(DurationUnit::Secs)
+        .with_precision(2);
+    assert_eq!(styled(&secs, Duration::from_millis(290)), "0.29s ");
+    assert_eq!(styled(&secs, Duration::from_millis(570)), "0.57s ");
+    assert_eq!(styled(&secs, Duration::from_millis(1_150)), "1.15s ");
+    assert_eq!(styled(&secs, Duration::from_millis(1_159)), "1.15s ");
+
+    let millis = DurationFormat::new()
+        .with_unit(DurationUnit::Millis)
+        .with_precision(12);
+    assert_eq!(
+        styled(&millis, Duration::from_nanos(1_000_001)),
+        "1.000001000000ms"
+    );
+}
+
+#[test]
+fn duration_format_unit_range() {
+    let timer = DurationFormat::new()
+        .with_units(DurationUnit::Secs, DurationUnit::Nanos)
+        .with_precision(1);
+    assert_eq!(styled(&timer, Duration::from_nanos(12)), "12.0ns");
+    assert_eq!(styled(&timer, Duration::from_micros(1_250)), "1.2ms");
+    assert_eq!(styled(&timer, Duration::from_secs(150)), "150.0s ");
+}
+
+/// Always prints the same wall-clock time.
+struct Noon;
+
+impl FormatTime for Noon {
+    fn format_time(&self, w: &mut impl std::fmt::Write) -> std::fmt::Result {
+        w.write_str("12:00")
+    }
+    fn style_timestamp(
+        &self,
+        _ansi: bool,
+        _elapsed: Duration,
+        _w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        Ok(())
+    }
+}
+
+fn formatted(timer: &impl FormatTime) -> String {
+    let mut buf = String::new();
+    timer.format_time(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn on_change() {
+    // The minute is very unlikely to change during the test
+    let timer = OnChange::minutes(Noon);
+    assert_eq!(formatted(&timer), "12:00");
+    assert_eq!(formatted(&timer), "     ");
+    assert_eq!(formatted(&timer), "     ");
+
+    let timer = OnChange::minutes(Noon).with_dimmed(true);
+    assert_eq!(formatted(&timer), "12:00");
+    assert_eq!(formatted(&timer), "\x1b[2m12:00\x1b[0m");
+}
+
+#[test]
+fn system_clock() {
+    let time = formatted(&SystemClock::default());
+    assert_eq!(time.len(), "2024-01-31T12:34:56.789Z".len(), "{}", time);
+    let year: u32 = time[..4].parse().unwrap();
+    assert!(year >= 2024);
+    assert_eq!(&time[10..11], "T");
+    assert!(time.ends_with('Z'));
+}
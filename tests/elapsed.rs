@@ -0,0 +1,257 @@
+mod common;
+
+use std::{
+    fmt::Write,
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{
+    nu_ansi_term::{Color, Style},
+    time::{FormatTime, TimeContext, Uptime},
+    HierarchicalLayer, Theme, TimeDisplay,
+};
+
+use common::Output;
+
+/// Remembers the elapsed times it is asked to print.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<Duration>>>);
+
+impl FormatTime for Recorder {
+    fn format_time(&self, _w: &mut impl Write) -> std::fmt::Result {
+        Ok(())
+    }
+    fn style_timestamp(
+        &self,
+        _ansi: bool,
+        elapsed: Duration,
+        _w: &mut impl Write,
+    ) -> std::fmt::Result {
+        self.0.lock().unwrap().push(elapsed);
+        Ok(())
+    }
+}
+
+fn elapsed_of_nested_event(from_root: bool) -> Duration {
+    let recorder = Recorder::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(io::sink)
+        .with_timer(recorder.clone())
+        .with_elapsed_from_root(from_root);
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request").in_scope(|| {
+            thread::sleep(Duration::from_millis(20));
+            info_span!("handler").in_scope(|| info!("handling"));
+        });
+    });
+    let elapsed = recorder.0.lock().unwrap();
+    assert_eq!(elapsed.len(), 1);
+    elapsed[0]
+}
+
+#[test]
+fn elapsed_from_innermost_span() {
+    assert!(elapsed_of_nested_event(false) < Duration::from_millis(20));
+}
+
+#[test]
+fn elapsed_from_root_span() {
+    assert!(elapsed_of_nested_event(true) >= Duration::from_millis(20));
+}
+
+/// Prints placeholders instead of actual times.
+struct Placeholders;
+
+impl FormatTime for Placeholders {
+    fn format_time(&self, w: &mut impl Write) -> std::fmt::Result {
+        w.write_str("wall")
+    }
+    fn style_timestamp(
+        &self,
+        _ansi: bool,
+        _elapsed: Duration,
+        w: &mut impl Write,
+    ) -> std::fmt::Result {
+        w.write_str("elapsed")
+    }
+}
+
+fn event_line(display: TimeDisplay) -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(output.clone())
+        .with_timer(Placeholders)
+        .with_time_display(display);
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request").in_scope(|| info!("handling"));
+    });
+    let contents = output.contents();
+    let line = contents.lines().find(|l| l.ends_with("handling")).unwrap();
+    line.trim_start().to_owned()
+}
+
+#[test]
+fn time_display() {
+    assert_eq!(event_line(TimeDisplay::Both), "wall elapsed INFO handling");
+    assert_eq!(
+        event_line(TimeDisplay::ElapsedFirst),
+        "elapsed wall INFO handling"
+    );
+    assert_eq!(event_line(TimeDisplay::WallClock), "wall INFO handling");
+    assert_eq!(event_line(TimeDisplay::Elapsed), "elapsed INFO handling");
+    assert_eq!(event_line(TimeDisplay::None), "INFO handling");
+}
+
+/// Prints times of a different length on every event.
+#[derive(Default)]
+struct Varying(AtomicUsize);
+
+impl FormatTime for Varying {
+    fn format_time(&self, w: &mut impl Write) -> std::fmt::Result {
+        let n = self.0.fetch_add(1, Ordering::Relaxed) % 3 + 1;
+        w.write_str(&"w".repeat(n))
+    }
+    fn style_timestamp(
+        &self,
+        _ansi: bool,
+        _elapsed: Duration,
+        w: &mut impl Write,
+    ) -> std::fmt::Result {
+        let n = self.0.load(Ordering::Relaxed) % 3 + 1;
+        w.write_str(&"e".repeat(n))
+    }
+}
+
+#[test]
+fn fixed_time_widths() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(output.clone())
+        .with_timer(Varying::default())
+        .with_wall_clock_width(4)
+        .with_elapsed_width(4);
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request").in_scope(|| {
+            for _ in 0..3 {
+                info!("handling");
+            }
+        });
+        info!("done");
+    });
+
+    let contents = output.contents();
+    let lines: Vec<_> = contents.lines().filter(|l| l.contains("INFO")).collect();
+    assert_eq!(lines[0], " w      ee INFO handling");
+    assert_eq!(lines[1], " ww    eee INFO handling");
+    assert_eq!(lines[2], " www     e INFO handling");
+    assert_eq!(lines[3], " w         INFO done");
+}
+
+fn themed_event_line(timer: impl FormatTime + Send + Sync + 'static, theme: Theme) -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(true)
+        .with_writer(output.clone())
+        .with_timer(timer)
+        .with_theme(theme);
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request").in_scope(|| info!("handling"));
+    });
+    let contents = output.contents();
+    let line = contents.lines().find(|l| l.ends_with("handling")).unwrap();
+    line.to_owned()
+}
+
+#[test]
+fn timestamp_styles() {
+    let theme = Theme::default()
+        .with_wall_clock(Color::Blue.normal())
+        .with_elapsed(Style::new().bold());
+    let line = themed_event_line(Placeholders, theme);
+    assert!(
+        line.contains("\x1b[34mwall\x1b[0m \x1b[1melapsed\x1b[0m "),
+        "{:?}",
+        line
+    );
+
+    // The built-in timers dim the elapsed time unless the theme says otherwise
+    let line = themed_event_line(Uptime::default(), Theme::default());
+    assert!(line.contains("\x1b[2m"), "{:?}", line);
+    let line = themed_event_line(
+        Uptime::default(),
+        Theme::default().with_elapsed(Style::new()),
+    );
+    assert!(!line.contains("\x1b[2m"), "{:?}", line);
+}
+
+/// Only prints times for the `important` target, along with the depth of the event.
+struct ImportantOnly;
+
+impl FormatTime for ImportantOnly {
+    fn format_time(&self, _w: &mut impl Write) -> std::fmt::Result {
+        unreachable!("the layer passes a context")
+    }
+    fn style_timestamp(
+        &self,
+        _ansi: bool,
+        _elapsed: Duration,
+        _w: &mut impl Write,
+    ) -> std::fmt::Result {
+        unreachable!("the layer passes a context")
+    }
+    fn format_time_with(&self, context: &TimeContext<'_>, w: &mut impl Write) -> std::fmt::Result {
+        if context.metadata.target() == "important" {
+            write!(w, "depth={}", context.depth)?;
+        }
+        Ok(())
+    }
+    fn style_timestamp_with(
+        &self,
+        context: &TimeContext<'_>,
+        _ansi: bool,
+        _elapsed: Duration,
+        w: &mut impl Write,
+    ) -> std::fmt::Result {
+        if context.metadata.target() == "important" {
+            w.write_str("elapsed")?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn time_context() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(output.clone())
+        .with_timer(ImportantOnly);
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request").in_scope(|| {
+            info_span!("handler").in_scope(|| {
+                info!(target: "important", "handling");
+                info!(target: "chatty", "chatting");
+            })
+        });
+    });
+    let contents = output.contents();
+    let line = |message: &str| {
+        contents
+            .lines()
+            .find(|l| l.ends_with(message))
+            .unwrap()
+            .trim_start()
+    };
+    assert_eq!(line("handling"), "depth=2 elapsed INFO handling");
+    assert_eq!(line("chatting"), "INFO chatting");
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use tracing::{error, info_span, warn};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[test]
+fn prints_backtraces_below_errors() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_error_backtraces(true)
+        .with_force_backtraces(true)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request").in_scope(|| {
+            warn!("slow");
+            error!("failed");
+        });
+    });
+
+    let output = output.contents();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines[0], "┐request ");
+    assert_eq!(lines[1], "├─ WARN slow");
+    assert_eq!(lines[2], "├─ ERROR failed");
+    // Every frame is indented below the event
+    let frames = &lines[3..lines.len() - 1];
+    assert!(
+        frames.iter().all(|line| line.starts_with("│  ")),
+        "{}",
+        output
+    );
+    assert!(
+        frames
+            .iter()
+            .any(|line| line.contains("prints_backtraces_below_errors")),
+        "{}",
+        output
+    );
+    assert_eq!(lines[lines.len() - 1], "┘");
+}
+
+#[test]
+fn error_backtraces_option() {
+    let layer = HierarchicalLayer::default().with_options("error_backtraces,force_backtraces");
+    assert!(layer.config().error_backtraces);
+    assert!(layer.config().force_backtraces);
+}
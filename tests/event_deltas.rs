@@ -0,0 +1,41 @@
+mod common;
+
+use std::{thread, time::Duration};
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[test]
+fn event_deltas() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_event_deltas(true)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("pipeline").in_scope(|| {
+            info!("first step");
+            thread::sleep(Duration::from_millis(20));
+            info!("second step");
+        });
+        info!("outside of any span");
+    });
+
+    let contents = output.contents();
+    let delta = |message: &str| {
+        let line = contents.lines().find(|l| l.ends_with(message)).unwrap();
+        let start = line.find('+').unwrap();
+        line[start + 1..start + line[start..].find(' ').unwrap()].to_owned()
+    };
+    assert!(!delta("first step").is_empty());
+    // At least the 20ms of sleep
+    let second = delta("second step");
+    assert!(second.ends_with("ms") && second.len() >= 4, "{}", second);
+    let outside = contents
+        .lines()
+        .find(|l| l.ends_with("outside of any span"));
+    assert!(!outside.unwrap().contains('+'));
+}
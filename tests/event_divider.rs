@@ -0,0 +1,72 @@
+mod common;
+
+use tracing::{error, info, info_span, warn, Level};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{Divider, HierarchicalLayer};
+
+use common::Output;
+
+fn render(level: Level, divider: Divider) -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_event_divider(level, divider)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("job", id = 7).in_scope(|| {
+            info!("started");
+            warn!("slow");
+            error!("failed");
+        });
+    });
+    output.contents()
+}
+
+#[test]
+fn rule_follows_error_events() {
+    assert_eq!(
+        render(Level::ERROR, Divider::Rule),
+        concat!(
+            "┐job id=7\n",
+            "├─ INFO started\n",
+            "├─ WARN slow\n",
+            "├─ ERROR failed\n",
+            "├─ ────────\n",
+            "┘\n",
+        )
+    );
+}
+
+#[test]
+fn header_follows_events_at_or_above_the_level() {
+    assert_eq!(
+        render(Level::WARN, Divider::Header),
+        concat!(
+            "┐job id=7\n",
+            "├─ INFO started\n",
+            "├─ WARN slow\n",
+            "├─ ↺ job id=7\n",
+            "├─ ERROR failed\n",
+            "├─ ↺ job id=7\n",
+            "┘\n",
+        )
+    );
+}
+
+#[test]
+fn divider_from_options() {
+    let config = HierarchicalLayer::default()
+        .with_options("event_divider=warn:header")
+        .config()
+        .clone();
+    assert_eq!(config.divider_level, Some(Level::WARN));
+    assert_eq!(config.divider, Divider::Header);
+
+    let config = HierarchicalLayer::default()
+        .with_options("event_divider")
+        .config()
+        .clone();
+    assert_eq!(config.divider_level, Some(Level::ERROR));
+    assert_eq!(config.divider, Divider::Rule);
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{Glyphs, HierarchicalLayer};
+
+use common::Output;
+
+#[test]
+fn events_are_drawn_with_the_event_glyph() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_glyphs(Glyphs::unicode().with_event("•"))
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("server").in_scope(|| {
+            info!("listening");
+            info_span!("conn").in_scope(|| info!("line one\nline two"));
+        });
+    });
+
+    assert_eq!(
+        output.contents(),
+        concat!(
+            "┐server \n",
+            "•  INFO listening\n",
+            "└─┐conn \n",
+            "  •  INFO line one\n",
+            "  │ line two\n",
+            "┌─┘\n",
+            "┘\n",
+        )
+    );
+}
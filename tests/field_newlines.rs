@@ -0,0 +1,60 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{HierarchicalLayer, Newlines};
+
+use common::Output;
+
+fn render(newlines: Option<Newlines>) -> String {
+    let output = Output::default();
+    let mut layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_writer(output.clone());
+    if let Some(newlines) = newlines {
+        layer = layer.with_field_newlines(newlines);
+    }
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("query", sql = %"SELECT 1\nFROM t").in_scope(|| {
+            info!(row = %"a\tb\r\nc", "first\nsecond");
+        });
+    });
+    output.contents()
+}
+
+#[test]
+fn newlines_in_fields_are_escaped_by_default() {
+    assert_eq!(
+        render(None),
+        concat!(
+            "┐query sql=SELECT 1\\nFROM t\n",
+            "├─ INFO first\n",
+            "│ second, row=a\\tb\\r\\nc\n",
+            "┘\n",
+        )
+    );
+}
+
+#[test]
+fn newlines_in_fields_as_symbols() {
+    assert_eq!(
+        render(Some(Newlines::Symbols)),
+        concat!(
+            "┐query sql=SELECT 1␤FROM t\n",
+            "├─ INFO first\n",
+            "│ second, row=a␉b␍␤c\n",
+            "┘\n",
+        )
+    );
+}
+
+#[test]
+fn newlines_in_fields_can_be_kept() {
+    let output = render(Some(Newlines::Keep));
+    assert!(
+        output.lines().any(|line| line.ends_with("FROM t")),
+        "{}",
+        output
+    );
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[test]
+fn many_span_fields() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("none").in_scope(|| {
+            info_span!("few", a = 1, b = "two").in_scope(|| {
+                info_span!("many", a = 1, b = 2, c = 3, d = 4, e = 5, f = "six")
+                    .in_scope(|| info!("inside"));
+            });
+        });
+    });
+    assert_eq!(
+        output.contents(),
+        " none \n   \
+         few a=1, b=\"two\"\n     \
+         many a=1, b=2, c=3, d=4, e=5, f=\"six\"\n      \
+         INFO inside\n"
+    );
+}
+
+#[test]
+fn field_budget() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_field_budget_bytes(12)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        let dump = "é".repeat(1000);
+        info_span!("small", a = 1).in_scope(|| {});
+        info_span!("huge", a = 1, dump = %dump, b = 2).in_scope(|| {});
+    });
+    assert_eq!(
+        output.contents(),
+        " small a=1\n \
+         huge a=1, dump=ééééé…, b=…\n"
+    );
+}
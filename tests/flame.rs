@@ -0,0 +1,63 @@
+mod common;
+
+use std::{io, thread, time::Duration};
+
+use tracing::info_span;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{flame::FoldedStacks, HierarchicalLayer};
+
+use common::Output;
+
+fn workload() {
+    info_span!("server").in_scope(|| {
+        thread::sleep(Duration::from_millis(2));
+        for _ in 0..2 {
+            info_span!("conn").in_scope(|| thread::sleep(Duration::from_millis(1)));
+        }
+    });
+}
+
+fn parse(folded: &str) -> Vec<(&str, u64)> {
+    folded
+        .lines()
+        .map(|line| {
+            let (stack, micros) = line.rsplit_once(' ').unwrap();
+            (stack, micros.parse().unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn self_time_per_stack() {
+    let stacks = FoldedStacks::new();
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(io::sink)
+        .with_folded_stacks(stacks.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+
+    let folded = stacks.folded();
+    let parsed = parse(&folded);
+    assert_eq!(parsed.len(), 2, "{}", folded);
+    assert_eq!(parsed[0].0, "server");
+    assert_eq!(parsed[1].0, "server;conn");
+    // The time spent in `conn` is not counted for `server` as well
+    assert!(parsed[0].1 >= 2_000, "{}", folded);
+    assert!(parsed[1].1 >= 2_000, "{}", folded);
+    assert!(parsed[0].1 < 2_000 + parsed[1].1, "{}", folded);
+
+    stacks.reset();
+    assert_eq!(stacks.folded(), "");
+}
+
+#[test]
+fn written_on_drop() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(io::sink)
+        .with_folded_stacks(FoldedStacks::with_writer(output.clone()));
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+
+    let contents = output.contents();
+    let stacks: Vec<_> = parse(&contents).into_iter().map(|(s, _)| s).collect();
+    assert_eq!(stacks, ["server", "server;conn"]);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use std::thread;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+/// Renders on a new thread, whose buffers have not cached any indentation yet.
+fn render() -> String {
+    thread::spawn(|| {
+        let output = Output::default();
+        let layer = HierarchicalLayer::new(2)
+            .with_ansi(true)
+            .with_indent_lines(true)
+            .with_guide_gradient(true)
+            .with_writer(output.clone());
+        tracing::subscriber::with_default(Registry::default().with(layer), || {
+            info_span!("server").in_scope(|| {
+                info_span!("conn").in_scope(|| info!("accepted"));
+            });
+        });
+        output.contents()
+    })
+    .join()
+    .unwrap()
+}
+
+#[test]
+fn colors_guides_by_depth() {
+    std::env::set_var("COLORTERM", "truecolor");
+    let output = render();
+    assert!(
+        output.contains("\x1b[38;2;92;230;230m┐\x1b[0m"),
+        "{:?}",
+        output
+    );
+    assert!(
+        output.contains("\x1b[38;2;92;172;230m└─┐\x1b[0m"),
+        "{:?}",
+        output
+    );
+
+    // Without truecolor support the closest colors of the 256 color palette are used
+    std::env::remove_var("COLORTERM");
+    let output = render();
+    assert!(output.contains("\x1b[38;5;123m┐\x1b[0m"), "{:?}", output);
+    assert!(output.contains("\x1b[38;5;111m└─┐\x1b[0m"), "{:?}", output);
+    assert!(
+        output.contains("  \x1b[38;5;105m├─\x1b[0m "),
+        "{:?}",
+        output
+    );
+}
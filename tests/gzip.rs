@@ -0,0 +1,73 @@
+#![cfg(feature = "gzip")]
+
+use std::{
+    io::{self, Read},
+    sync::{Arc, Mutex},
+};
+
+use flate2::read::GzDecoder;
+use tracing::{info, info_span, Dispatch};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{writer::Gzip, HierarchicalLayer};
+
+fn decompress(data: &[u8]) -> String {
+    let mut out = String::new();
+    GzDecoder::new(data).read_to_string(&mut out).unwrap();
+    out
+}
+
+fn log() {
+    info_span!("compressed").in_scope(|| info!("hello"));
+}
+
+#[test]
+fn finish() {
+    let gzip = Gzip::new(Vec::new());
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(gzip.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), log);
+
+    let data = gzip.finish().unwrap();
+    assert_eq!(decompress(&data), " compressed \n  INFO hello\n");
+    assert!(gzip.finish().is_err());
+}
+
+/// A file shared with the test
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn flush_makes_output_readable() {
+    let file = Shared::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(Gzip::with_level(file.clone(), 9));
+    let dispatch = Dispatch::new(Registry::default().with(layer));
+    tracing::dispatcher::with_default(&dispatch, log);
+    let layer = dispatch
+        .downcast_ref::<HierarchicalLayer<Gzip<Shared>>>()
+        .unwrap();
+    layer.flush().unwrap();
+
+    // The stream is not finished yet, but everything written so far can be decompressed
+    let data = file.0.lock().unwrap().clone();
+    let mut out = Vec::new();
+    assert!(GzDecoder::new(&data[..]).read_to_end(&mut out).is_err());
+    assert_eq!(out, b" compressed \n  INFO hello\n");
+
+    drop(dispatch);
+    let data = file.0.lock().unwrap().clone();
+    assert_eq!(decompress(&data), " compressed \n  INFO hello\n");
+}
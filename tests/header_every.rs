@@ -0,0 +1,56 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[test]
+fn header_is_reprinted_after_every_n_events() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_header_every(2)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("batch", size = 5).in_scope(|| {
+            for i in 0..5 {
+                info!(i, "item");
+            }
+        });
+    });
+
+    assert_eq!(
+        output.contents(),
+        concat!(
+            "┐batch size=5\n",
+            "├─ INFO item, i=0\n",
+            "├─ INFO item, i=1\n",
+            "├─ ↺ batch size=5\n",
+            "├─ INFO item, i=2\n",
+            "├─ INFO item, i=3\n",
+            "├─ ↺ batch size=5\n",
+            "├─ INFO item, i=4\n",
+            "┘\n",
+        )
+    );
+}
+
+#[test]
+fn header_is_not_reprinted_by_default() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("batch").in_scope(|| {
+            for _ in 0..10 {
+                info!("item");
+            }
+        });
+    });
+
+    assert!(!output.contents().contains('↺'), "{}", output.contents());
+}
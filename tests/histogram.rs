@@ -0,0 +1,60 @@
+mod common;
+
+use std::{io, thread, time::Duration};
+
+use tracing::info_span;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{histogram::Histograms, HierarchicalLayer};
+
+use common::Output;
+
+fn workload() {
+    for i in 0..10 {
+        info_span!("request").in_scope(|| {
+            if i == 9 {
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+    }
+}
+
+#[test]
+fn percentiles_per_callsite() {
+    let histograms = Histograms::new();
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(io::sink)
+        .with_latency_histograms(histograms.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+
+    let request = histograms.get("request").unwrap();
+    assert_eq!(request.count, 10);
+    assert!(request.percentile(50.0) < Duration::from_millis(10));
+    assert!(request.percentile(90.0) < Duration::from_millis(10));
+    assert_eq!(request.percentile(99.0), request.max);
+    assert!(request.max >= Duration::from_millis(20));
+    assert!(request.percentile(0.0) <= request.percentile(50.0));
+    assert!(histograms.get("response").is_none());
+
+    histograms.reset();
+    assert!(histograms.snapshot().is_empty());
+}
+
+#[test]
+fn included_in_summary() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_summary_report(true)
+        .with_latency_histograms(Histograms::new())
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+
+    let contents = output.contents();
+    let latencies = &contents[contents.find("span latencies:\n").unwrap()..];
+    assert!(latencies.contains("    count        p50        p90        p99        max name\n"));
+    let request = latencies
+        .lines()
+        .find(|line| line.ends_with(" request"))
+        .unwrap();
+    assert!(request.trim_start().starts_with("10 "), "{}", request);
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "indicatif")]
+
+mod common;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget};
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{writer::Indicatif, HierarchicalLayer};
+
+use common::Output;
+
+#[test]
+fn writes_around_bars() {
+    let output = Output::default();
+    let bars = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+    let bar = bars.add(ProgressBar::new(2));
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(Indicatif::new(bars).with_writer(output.clone()));
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("download").in_scope(|| {
+            info!("first");
+            bar.inc(1);
+            info!("second");
+            bar.inc(1);
+        });
+    });
+    bar.finish();
+
+    assert_eq!(
+        output.contents(),
+        " download \n  INFO first\n  INFO second\n"
+    );
+}
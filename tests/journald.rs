@@ -0,0 +1,62 @@
+#![cfg(all(unix, feature = "journald"))]
+
+use std::{collections::HashMap, convert::TryInto, os::unix::net::UnixDatagram};
+
+use tracing::{error, info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{writer::Journald, HierarchicalLayer};
+
+/// Parses an entry of the native journal protocol in its binary format.
+fn parse_entry(mut entry: &[u8]) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    while !entry.is_empty() {
+        let name_end = entry.iter().position(|&b| b == b'\n').unwrap();
+        let name = String::from_utf8(entry[..name_end].to_vec()).unwrap();
+        let len = u64::from_le_bytes(entry[name_end + 1..name_end + 9].try_into().unwrap());
+        let value = &entry[name_end + 9..name_end + 9 + len as usize];
+        fields.insert(name, String::from_utf8(value.to_vec()).unwrap());
+        entry = &entry[name_end + 9 + len as usize + 1..];
+    }
+    fields
+}
+
+#[test]
+fn journald_entries() {
+    let path = std::env::temp_dir().join(format!("tracing-tree-journal-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let journal = UnixDatagram::bind(&path).unwrap();
+
+    let journald = Journald::with_socket_path(&path)
+        .unwrap()
+        .with_identifier("tree-test");
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_writer(journald);
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("server").in_scope(|| {
+            info!("starting");
+            error!("failed");
+        });
+    });
+
+    let mut entries = Vec::new();
+    journal.set_nonblocking(true).unwrap();
+    let mut buf = vec![0; 4096];
+    while let Ok(len) = journal.recv(&mut buf) {
+        entries.push(parse_entry(&buf[..len]));
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    let entry = |message: &str| {
+        entries
+            .iter()
+            .find(|e| e["MESSAGE"].ends_with(message))
+            .unwrap_or_else(|| panic!("no entry for {:?} in {:?}", message, entries))
+    };
+    assert_eq!(entry("starting")["PRIORITY"], "5");
+    assert_eq!(entry("starting")["MESSAGE"], "├─ INFO starting");
+    assert_eq!(entry("failed")["PRIORITY"], "3");
+    assert_eq!(entry("failed")["SYSLOG_IDENTIFIER"], "tree-test");
+    assert_eq!(entry("server ")["PRIORITY"], "5");
+}
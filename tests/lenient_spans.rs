@@ -0,0 +1,40 @@
+mod common;
+
+use tracing::{info, info_span, level_filters::LevelFilter};
+use tracing_subscriber::{layer::SubscriberExt, reload, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+/// Installs `layer` only after a span was created, so the span has no data of the layer.
+fn run_with_late_layer(layer: HierarchicalLayer<Output>) {
+    let (reload, handle) = reload::Layer::new(None::<HierarchicalLayer<Output>>);
+    // Without another layer, the subscriber would disable the span while the tree is missing
+    let subscriber = Registry::default().with(LevelFilter::TRACE).with(reload);
+    tracing::subscriber::with_default(subscriber, || {
+        let span = info_span!("early", id = 1);
+        handle.reload(Some(layer)).unwrap();
+        span.in_scope(|| info!("inside"));
+    });
+}
+
+#[test]
+fn renders_spans_without_data() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_verbose_exit(true)
+        .with_lenient_spans(true)
+        .with_writer(output.clone());
+
+    run_with_late_layer(layer);
+
+    assert_eq!(
+        output.contents(),
+        "\
+├─ INFO inside
+┘early 
+"
+    );
+}
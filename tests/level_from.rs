@@ -0,0 +1,57 @@
+mod common;
+
+use tracing::{debug, error, info, info_span, warn, Level};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+fn render(
+    configure: impl FnOnce(HierarchicalLayer<Output>) -> HierarchicalLayer<Output>,
+) -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(output.clone())
+        .with_ansi(false)
+        .with_indent_lines(true);
+    let layer = configure(layer);
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("job").in_scope(|| {
+            debug!("polling");
+            info!("started");
+            warn!("slow");
+            error!("failed");
+        });
+    });
+    output.contents()
+}
+
+#[test]
+fn levels_below_the_threshold_are_blank() {
+    assert_eq!(
+        render(|layer| layer.with_level_from(Level::WARN)),
+        concat!(
+            "┐job \n",
+            "├─       polling\n",
+            "├─      started\n",
+            "├─ WARN slow\n",
+            "├─ ERROR failed\n",
+            "┘\n",
+        )
+    );
+}
+
+#[test]
+fn short_levels_below_the_threshold_are_blank() {
+    assert_eq!(
+        render(|layer| layer.with_short_levels(true).with_level_from(Level::WARN)),
+        concat!(
+            "┐job \n",
+            "├─   polling\n",
+            "├─   started\n",
+            "├─ W slow\n",
+            "├─ E failed\n",
+            "┘\n",
+        )
+    );
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use tracing::{debug, debug_span, info, info_span, level_filters::LevelFilter, Level};
+use tracing_subscriber::{layer::SubscriberExt, Layer, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[test]
+fn filters_callsites() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_max_level(Level::INFO)
+        .with_writer(output.clone());
+    assert_eq!(
+        Layer::<Registry>::max_level_hint(&layer),
+        Some(LevelFilter::INFO)
+    );
+
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("server").in_scope(|| {
+            debug!("ignored");
+            debug_span!("conn").in_scope(|| {
+                info!("accepted");
+                debug!("ignored");
+            });
+        });
+    });
+
+    assert_eq!(
+        output.contents(),
+        "\
+┐server 
+├─ INFO accepted
+┘
+"
+    );
+}
@@ -0,0 +1,66 @@
+use std::{thread, time::Duration};
+
+use tracing::info_span;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{
+    mermaid,
+    tree::{Builder, SpanTree},
+};
+
+fn capture() -> SpanTree {
+    let builder = Builder::new();
+    tracing::subscriber::with_default(Registry::default().with(builder.clone()), || {
+        info_span!("server", name = "a \"b\"").in_scope(|| {
+            thread::sleep(Duration::from_millis(2));
+            info_span!("conn", peer = 1).in_scope(|| thread::sleep(Duration::from_millis(2)));
+            info_span!("conn", peer = 2).in_scope(|| {});
+        });
+    });
+    builder.take().pop().unwrap()
+}
+
+#[test]
+fn flowchart() {
+    assert_eq!(
+        mermaid::flowchart(&capture()),
+        r#"flowchart TD
+    s0["server name=a #quot;b#quot;"]
+    s1["conn peer=1"]
+    s2["conn peer=2"]
+    s0 --> s1
+    s0 --> s2
+"#
+    );
+}
+
+#[test]
+fn gantt() {
+    let gantt = mermaid::gantt(&capture());
+    let lines: Vec<_> = gantt.lines().collect();
+    assert_eq!(
+        lines[..4],
+        [
+            "gantt",
+            "    dateFormat x",
+            "    axisFormat %S.%L",
+            "    section server"
+        ]
+    );
+    assert_eq!(lines.len(), 7, "{}", gantt);
+
+    let bars: Vec<(&str, u128, u128)> = lines[4..]
+        .iter()
+        .map(|line| {
+            let (name, task) = line.trim().split_once(" :").unwrap();
+            let mut parts = task.split(", ").skip(1).map(|n| n.parse().unwrap());
+            (name, parts.next().unwrap(), parts.next().unwrap())
+        })
+        .collect();
+    assert_eq!(bars[0].0, "server");
+    assert_eq!(bars[0].1, 0);
+    assert!(bars[0].2 >= 4, "{}", gantt);
+    assert_eq!(bars[1].0, "conn");
+    assert!(bars[1].1 >= 2 && bars[1].2 >= bars[1].1 + 2, "{}", gantt);
+    assert!(bars[2].1 >= bars[1].2 && bars[2].2 > bars[2].1, "{}", gantt);
+    assert!(bars[2].2 <= bars[0].2 + 1, "{}", gantt);
+}
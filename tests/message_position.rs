@@ -0,0 +1,52 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{HierarchicalLayer, MessagePosition};
+
+use common::Output;
+
+fn render(position: MessagePosition) -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_message_position(position)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request").in_scope(|| {
+            info!(status = 200, "done");
+            info!(status = 404, message = %"missing");
+            info!(status = 500);
+        });
+    });
+    output.contents()
+}
+
+#[test]
+fn message_first() {
+    assert_eq!(
+        render(MessagePosition::First),
+        concat!(
+            "┐request \n",
+            "├─ INFO done, status=200\n",
+            "├─ INFO missing, status=404\n",
+            "├─ INFO status=500\n",
+            "┘\n",
+        )
+    );
+}
+
+#[test]
+fn message_last() {
+    assert_eq!(
+        render(MessagePosition::Last),
+        concat!(
+            "┐request \n",
+            "├─ INFO status=200, done\n",
+            "├─ INFO status=404, missing\n",
+            "├─ INFO status=500\n",
+            "┘\n",
+        )
+    );
+}
@@ -0,0 +1,74 @@
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use tracing::{debug, info, info_span, Level};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{metrics::MetricsKind, HierarchicalLayer};
+
+#[test]
+fn hook_sees_spans_and_events() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(io::sink)
+        .with_max_events_per_span(1)
+        .with_metrics_hook({
+            let seen = seen.clone();
+            move |event| {
+                seen.lock().unwrap().push((
+                    event.kind,
+                    event.name.to_owned(),
+                    event.level,
+                    event.duration.is_some(),
+                    event.depth,
+                ))
+            }
+        });
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("server").in_scope(|| {
+            info_span!("conn").in_scope(|| {
+                info!("first");
+                debug!("suppressed, but counted");
+            });
+        });
+        info!("outside");
+    });
+
+    let seen = seen.lock().unwrap();
+    let kinds: Vec<_> = seen.iter().map(|(kind, ..)| *kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            MetricsKind::Event,
+            MetricsKind::Event,
+            MetricsKind::SpanClose,
+            MetricsKind::SpanClose,
+            MetricsKind::Event,
+        ]
+    );
+    assert_eq!(&seen[1].2, &Level::DEBUG);
+    assert_eq!(seen[1].4, 2);
+    assert!(!seen[1].3);
+    assert_eq!(
+        seen[2],
+        (
+            MetricsKind::SpanClose,
+            "conn".to_owned(),
+            Level::INFO,
+            true,
+            1
+        )
+    );
+    assert_eq!(
+        seen[3],
+        (
+            MetricsKind::SpanClose,
+            "server".to_owned(),
+            Level::INFO,
+            true,
+            0
+        )
+    );
+    assert_eq!(seen[4].4, 0);
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use tracing::{info, info_span, warn};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+/// Replaces the numbers of the `elapsed` members, which differ between runs.
+fn strip_elapsed(line: &str) -> String {
+    match line.find(",\"elapsed\":") {
+        Some(start) => format!("{},\"elapsed\":N}}", &line[..start]),
+        None => line.to_owned(),
+    }
+}
+
+#[test]
+fn mirrors_records() {
+    let tree = Output::default();
+    let mirror = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_max_events_per_span(1)
+        .with_writer(tree.clone())
+        .with_structured_mirror(mirror.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!(target: "app", "server", port = 8080).in_scope(|| {
+            info!(target: "app", peer = "a\tb", "connected");
+            warn!(target: "app", "dropped");
+        });
+        info!(target: "app", "outside");
+    });
+
+    let lines: Vec<_> = mirror.contents().lines().map(strip_elapsed).collect();
+    assert_eq!(
+        lines,
+        [
+            r#"{"type":"span_open","level":"INFO","target":"app","path":["server"],"name":"server","fields":{"port":"8080"}}"#,
+            r#"{"type":"event","level":"INFO","target":"app","path":["server"],"fields":{"message":"connected","peer":"\"a\\tb\""},"elapsed":N}"#,
+            r#"{"type":"event","level":"WARN","target":"app","path":["server"],"fields":{"message":"dropped"},"elapsed":N}"#,
+            r#"{"type":"span_close","level":"INFO","target":"app","path":["server"],"name":"server","elapsed":N}"#,
+            r#"{"type":"event","level":"INFO","target":"app","path":[],"fields":{"message":"outside"}}"#,
+        ]
+    );
+    assert!(!tree.contents().contains("dropped"));
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "opentelemetry")]
+
+mod common;
+
+use opentelemetry::{
+    trace::{
+        noop::NoopTracer, SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState,
+    },
+    Context,
+};
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+fn remote_context() -> Context {
+    let span_context = SpanContext::new(
+        TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+        SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+        TraceFlags::SAMPLED,
+        true,
+        TraceState::default(),
+    );
+    Context::new().with_remote_span_context(span_context)
+}
+
+fn render(otel_ids: bool) -> String {
+    let output = Output::default();
+    let subscriber = Registry::default()
+        .with(tracing_opentelemetry::layer().with_tracer(NoopTracer::new()))
+        .with(
+            HierarchicalLayer::new(2)
+                .with_ansi(false)
+                .with_otel_ids(otel_ids)
+                .with_writer(output.clone()),
+        );
+    tracing::subscriber::with_default(subscriber, || {
+        let _cx = remote_context().attach();
+        info_span!("request", id = 1).in_scope(|| {
+            info_span!("query").in_scope(|| info!("done"));
+        });
+    });
+    output.contents()
+}
+
+#[test]
+fn trace_ids_on_open_lines() {
+    assert_eq!(
+        render(true),
+        " request id=1 trace_id=4bf92f35\n   query trace_id=4bf92f35\n    INFO done\n"
+    );
+}
+
+#[test]
+fn disabled_by_default() {
+    assert_eq!(render(false), " request id=1\n   query \n    INFO done\n");
+}
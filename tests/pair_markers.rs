@@ -0,0 +1,39 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[test]
+fn marks_both_ends_of_a_span() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_pair_markers(true)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("server", port = 80).in_scope(|| {
+            info_span!("conn").in_scope(|| info!("accepted"));
+        });
+    });
+
+    assert_eq!(
+        output.contents(),
+        "\
+┐server port=80 ⟨#1⟩
+└─┐conn ⟨#2⟩
+  ├─ INFO accepted
+┌─┘⟨#2⟩
+┘⟨#1⟩
+"
+    );
+}
+
+#[test]
+fn pair_markers_option() {
+    let layer = HierarchicalLayer::default().with_options("pair_markers");
+    assert!(layer.config().pair_markers);
+}
@@ -0,0 +1,141 @@
+#![cfg(feature = "perfetto")]
+
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{perfetto::PerfettoTrace, HierarchicalLayer};
+
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+fn varint(buf: &mut &[u8]) -> u64 {
+    let mut value = 0;
+    for shift in (0..).step_by(7) {
+        let byte = buf[0];
+        *buf = &buf[1..];
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            break;
+        }
+    }
+    value
+}
+
+/// Decodes the fields of a protobuf message.
+fn decode(mut buf: &[u8]) -> Vec<(u64, Value<'_>)> {
+    let mut fields = Vec::new();
+    while !buf.is_empty() {
+        let key = varint(&mut buf);
+        let value = match key & 7 {
+            0 => Value::Varint(varint(&mut buf)),
+            2 => {
+                let len = varint(&mut buf) as usize;
+                let (value, rest) = buf.split_at(len);
+                buf = rest;
+                Value::Bytes(value)
+            }
+            wire => panic!("unexpected wire type {}", wire),
+        };
+        fields.push((key >> 3, value));
+    }
+    fields
+}
+
+fn bytes<'a>(fields: &[(u64, Value<'a>)], number: u64) -> Option<&'a [u8]> {
+    fields.iter().find_map(|(n, value)| match value {
+        Value::Bytes(bytes) if *n == number => Some(*bytes),
+        _ => None,
+    })
+}
+
+fn number(fields: &[(u64, Value<'_>)], number: u64) -> Option<u64> {
+    fields.iter().find_map(|(n, value)| match value {
+        Value::Varint(value) if *n == number => Some(*value),
+        _ => None,
+    })
+}
+
+fn string<'a>(fields: &[(u64, Value<'a>)], n: u64) -> &'a str {
+    std::str::from_utf8(bytes(fields, n).unwrap()).unwrap()
+}
+
+#[test]
+fn track_events() {
+    let out = Shared::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(io::sink)
+        .with_perfetto_trace(PerfettoTrace::new(out.clone()));
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("outer", id = 7).in_scope(|| {
+            info_span!("inner").in_scope(|| info!(n = 3, "hello"));
+        });
+    });
+
+    let trace = out.0.lock().unwrap().clone();
+    let packets: Vec<_> = decode(&trace)
+        .into_iter()
+        .map(|(n, value)| match value {
+            Value::Bytes(packet) if n == 1 => decode(packet),
+            _ => panic!("unexpected field {} in trace", n),
+        })
+        .collect();
+    // The process track, the track of `outer`, the event and two slices
+    assert_eq!(packets.len(), 7);
+    assert_eq!(number(&packets[0], 13), Some(1));
+
+    let track = decode(bytes(&packets[1], 60).unwrap());
+    assert_eq!(string(&track, 2), "outer");
+    assert_eq!(
+        number(&track, 5),
+        number(&decode(bytes(&packets[0], 60).unwrap()), 1)
+    );
+    let uuid = number(&track, 1);
+
+    let events: Vec<_> = packets[2..]
+        .iter()
+        .map(|packet| {
+            assert!(number(packet, 8).is_some());
+            decode(bytes(packet, 11).unwrap())
+        })
+        .collect();
+    assert!(events.iter().all(|event| number(event, 11) == uuid));
+    let kinds: Vec<_> = events
+        .iter()
+        .map(|event| number(event, 9).unwrap())
+        .collect();
+    assert_eq!(kinds, [3, 1, 2, 1, 2]);
+    assert_eq!(string(&events[0], 23), "hello");
+    assert_eq!(string(&events[1], 23), "inner");
+    assert_eq!(string(&events[3], 23), "outer");
+
+    let annotation = decode(bytes(&events[3], 4).unwrap());
+    assert_eq!(string(&annotation, 10), "id");
+    assert_eq!(string(&annotation, 6), "7");
+
+    // Slices end after they begin
+    let ts: Vec<_> = packets[3..]
+        .iter()
+        .map(|packet| number(packet, 8).unwrap())
+        .collect();
+    assert!(ts[0] <= ts[1] && ts[2] <= ts[3]);
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use tracing::{debug, error, info_span, Level};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+fn layer(output: &Output) -> HierarchicalLayer<Output> {
+    HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_quiet_until_error(Level::ERROR)
+        .with_writer(output.clone())
+}
+
+#[test]
+fn deferred_span_summarizes_its_held_events() {
+    let output = Output::default();
+    let layer = layer(&output).with_deferred_spans(true);
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request").in_scope(|| {
+            debug!("parsing");
+            debug!("parsed");
+        });
+    });
+
+    assert_eq!(
+        output.contents(),
+        concat!("┐request \n", "├─ … 2 quiet events dropped\n", "┘\n",)
+    );
+}
+
+#[test]
+fn held_events_are_capped() {
+    let output = Output::default();
+    tracing::subscriber::with_default(Registry::default().with(layer(&output)), || {
+        info_span!("request").in_scope(|| {
+            for i in 0..1100 {
+                debug!(i, "polling");
+            }
+            error!("failed");
+        });
+    });
+
+    let contents = output.contents();
+    let lines: Vec<_> = contents.lines().collect();
+    assert_eq!(
+        lines[1], "├─ … 76 earlier quiet events dropped",
+        "{}",
+        contents
+    );
+    assert_eq!(lines[2], "├─ DEBUG polling, i=76", "{}", contents);
+    assert_eq!(lines.len(), 1 + 1 + 1024 + 1 + 1, "{}", contents);
+}
+
+#[test]
+fn dropped_held_events_are_counted_in_the_summary() {
+    let output = Output::default();
+    tracing::subscriber::with_default(Registry::default().with(layer(&output)), || {
+        info_span!("request").in_scope(|| {
+            for _ in 0..1100 {
+                debug!("polling");
+            }
+        });
+    });
+
+    assert_eq!(
+        output.contents(),
+        concat!("┐request \n", "├─ … 1100 quiet events dropped\n", "┘\n",)
+    );
+}
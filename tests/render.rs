@@ -0,0 +1,111 @@
+mod common;
+
+use tracing::{debug, info, info_span, warn};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{
+    render::{Record, RecordKind, Renderer},
+    tree::Builder,
+    Config, HierarchicalLayer,
+};
+
+use common::Output;
+
+fn work() {
+    info_span!(target: "app", "server", port = 8080).in_scope(|| {
+        info!(target: "app", peer = "a", "connected");
+        info_span!(target: "app", "conn", id = 1).in_scope(|| {
+            debug!(target: "app", bytes = 42, "read");
+        });
+        warn!(target: "app", "dropped");
+    });
+    info!(target: "app", "outside");
+}
+
+#[test]
+fn replay_matches_live_tree() {
+    let tree = Output::default();
+    let mirror = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_targets(true)
+        .with_timer(())
+        .with_writer(tree.clone())
+        .with_structured_mirror(mirror.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), work);
+
+    let config = Config::default()
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_targets(true);
+    let mut out = Vec::new();
+    Renderer::new(config)
+        .replay(mirror.contents().as_bytes(), &mut out)
+        .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), tree.contents());
+}
+
+#[test]
+fn render_captured_tree() {
+    let builder = Builder::new();
+    tracing::subscriber::with_default(Registry::default().with(builder.clone()), work);
+    let trees = builder.take();
+
+    let mut renderer = Renderer::new(
+        Config::default()
+            .with_ansi(false)
+            .with_bracketed_fields(true),
+    );
+    let out = renderer.render_tree(&trees[0]);
+    assert_eq!(
+        out,
+        " server{port=8080}\n  INFO connected, peer=a\n   conn{id=1}\n    DEBUG read, bytes=42\n  WARN dropped\n",
+    );
+}
+
+#[test]
+fn parse_records() {
+    let record: Record = r#"{"type":"event","level":"WARN","target":"app","path":["a","b"],"fields":{"message":"café \"ok\""},"elapsed":1500}"#
+        .parse()
+        .unwrap();
+    assert_eq!(record.kind, RecordKind::Event);
+    assert_eq!(record.level, tracing::Level::WARN);
+    assert_eq!(record.path, ["a", "b"]);
+    assert_eq!(
+        record.fields,
+        [("message".to_owned(), "café \"ok\"".to_owned())]
+    );
+    assert_eq!(record.elapsed, Some(std::time::Duration::from_micros(1500)));
+
+    let err = r#"{"type":"span_open","level":"LOUD","target":"app","path":[]}"#
+        .parse::<Record>()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "invalid record: unknown level");
+
+    let err = Renderer::new(Config::default())
+        .replay("\n{\"type\":".as_bytes(), Vec::new())
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        "line 2: invalid record: unexpected end of input"
+    );
+}
+
+#[test]
+fn parse_surrogate_pairs() {
+    let record = |message: &str| {
+        format!(
+            r#"{{"type":"event","level":"INFO","target":"app","path":[],"fields":{{"message":"{}"}}}}"#,
+            message
+        )
+        .parse::<Record>()
+    };
+    let parsed = record(r"\ud83d\ude00").unwrap();
+    assert_eq!(parsed.fields, [("message".to_owned(), "😀".to_owned())]);
+
+    for unpaired in [r"\ud800\u0041", r"\ud800x", r"\udc00"] {
+        let err = record(unpaired).unwrap_err();
+        assert_eq!(err.to_string(), "invalid record: unpaired surrogate");
+    }
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[test]
+fn retraced_spans_show_elapsed_time() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_span_retrace(true)
+        .with_span_modes(true)
+        .with_retrace_elapsed(true)
+        .with_deterministic_output(true)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        let a = info_span!("a");
+        let b = info_span!("b");
+        a.in_scope(|| info!("first"));
+        b.in_scope(|| info!("second"));
+        a.in_scope(|| info!("again"));
+    });
+
+    let output = output.contents();
+    let retraces: Vec<_> = output.lines().filter(|l| l.contains("retrace")).collect();
+    assert!(!retraces.is_empty(), "{}", output);
+    assert!(
+        retraces
+            .iter()
+            .all(|line| line.ends_with(" a (+0ns)") || line.ends_with(" b (+0ns)")),
+        "{}",
+        output
+    );
+    let opens: Vec<_> = output.lines().filter(|l| l.contains("open")).collect();
+    assert!(opens.iter().all(|line| !line.contains("(+")), "{}", output);
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use std::thread;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{HierarchicalLayer, Separator};
+
+use common::Output;
+
+fn render(separator: Separator) -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_width(10)
+        .with_root_separator(separator)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("first").in_scope(|| {
+            info_span!("nested").in_scope(|| info!("inner"));
+        });
+        info_span!("second").in_scope(|| info!("done"));
+    });
+    output.contents()
+}
+
+#[test]
+fn blank_line_separates_root_spans() {
+    assert_eq!(
+        render(Separator::BlankLine),
+        concat!(
+            "┐first \n",
+            "└─┐nested \n",
+            "  ├─ INFO inner\n",
+            "┌─┘\n",
+            "┘\n",
+            "\n",
+            "┐second \n",
+            "├─ INFO done\n",
+            "┘\n",
+        )
+    );
+}
+
+#[test]
+fn rule_spans_the_width() {
+    let output = render(Separator::Rule('='));
+    assert_eq!(
+        output.lines().filter(|line| *line == "==========").count(),
+        1,
+        "{}",
+        output
+    );
+    assert!(output.starts_with("┐first"), "{}", output);
+}
+
+#[test]
+fn root_spans_on_other_threads_are_separated() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_root_separator(Separator::BlankLine)
+        .with_writer(output.clone());
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        info_span!("a").in_scope(|| info!("first"));
+        let dispatch = tracing::dispatcher::get_default(|dispatch| dispatch.clone());
+        thread::spawn(move || {
+            tracing::dispatcher::with_default(&dispatch, || {
+                info_span!("b").in_scope(|| info!("second"));
+            });
+        })
+        .join()
+        .unwrap();
+    });
+
+    assert_eq!(
+        output.contents(),
+        concat!(
+            "┐a \n",
+            "├─ INFO first\n",
+            "┘\n",
+            "\n",
+            "┐b \n",
+            "├─ INFO second\n",
+            "┘\n",
+        )
+    );
+}
+
+#[test]
+fn layers_do_not_share_the_first_root_span() {
+    for _ in 0..2 {
+        let output = Output::default();
+        let layer = HierarchicalLayer::new(2)
+            .with_ansi(false)
+            .with_indent_lines(true)
+            .with_root_separator(Separator::BlankLine)
+            .with_writer(output.clone());
+        tracing::subscriber::with_default(Registry::default().with(layer), || {
+            info_span!("first").in_scope(|| info!("done"));
+        });
+        assert_eq!(output.contents(), "┐first \n├─ INFO done\n┘\n");
+    }
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+fn run(layer: HierarchicalLayer<Output>) {
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("span", name = %"evil\u{202e}txt.exe").in_scope(|| {
+            info!(progress = %"50%\r100%", "bell\u{7} and back\u{8}space");
+        });
+    });
+}
+
+#[test]
+fn escapes_control_characters() {
+    let output = Output::default();
+    run(HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(output.clone()));
+    assert_eq!(
+        output.contents(),
+        " span name=evil\\u{202e}txt.exe\n  \
+         INFO bell\\u{7} and back\\u{8}space, progress=50%\\r100%\n"
+    );
+}
+
+#[test]
+fn sanitizing_can_be_disabled() {
+    let output = Output::default();
+    run(HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_sanitize_fields(false)
+        .with_writer(output.clone()));
+    let contents = output.contents();
+    assert!(contents.contains("evil\u{202e}txt.exe"));
+    assert!(contents.contains("50%\r100%"));
+}
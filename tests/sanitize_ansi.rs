@@ -0,0 +1,70 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+fn run(layer: HierarchicalLayer<Output>) {
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("span", title = %"\x1b]0;pwned\x07home").in_scope(|| {
+            info!(
+                link = %"\x1b]8;;http://evil\x1b\\click\x1b]8;;\x1b\\",
+                "\x1b[2J\x1b[1;31mERROR\x1b[0m fake"
+            );
+        });
+    });
+}
+
+#[test]
+fn strips_escape_sequences_without_ansi() {
+    let output = Output::default();
+    run(HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(output.clone()));
+    assert_eq!(
+        output.contents(),
+        concat!(" span title=home\n", "  INFO ERROR fake, link=click\n",)
+    );
+}
+
+#[test]
+fn keeps_escape_sequences_with_ansi() {
+    let output = Output::default();
+    run(HierarchicalLayer::new(2)
+        .with_ansi(true)
+        .with_writer(output.clone()));
+    assert!(
+        output.contents().contains("\x1b[1;31mERROR"),
+        "{:?}",
+        output.contents()
+    );
+}
+
+#[test]
+fn stripping_can_be_forced() {
+    let output = Output::default();
+    run(HierarchicalLayer::new(2)
+        .with_ansi(true)
+        .with_sanitize_ansi(true)
+        .with_writer(output.clone()));
+    let contents = output.contents();
+    assert!(!contents.contains("pwned"), "{:?}", contents);
+    assert!(!contents.contains("\x1b[2J"), "{:?}", contents);
+    assert!(!contents.contains("\x1b[1;31m"), "{:?}", contents);
+}
+
+#[test]
+fn stripping_can_be_disabled() {
+    let output = Output::default();
+    run(HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_sanitize_ansi(false)
+        .with_writer(output.clone()));
+    assert!(
+        output.contents().contains("\x1b[2J"),
+        "{:?}",
+        output.contents()
+    );
+}
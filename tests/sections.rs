@@ -0,0 +1,135 @@
+mod common;
+
+use std::fmt;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{
+    section::{Buildkite, SectionMarker, TeamCity},
+    HierarchicalLayer,
+};
+
+use common::Output;
+
+fn run(layer: HierarchicalLayer<Output>) {
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("test", name = "first").in_scope(|| {
+            info_span!("setup").in_scope(|| info!("ready"));
+            info!("done");
+        });
+        info_span!("test", name = "silent").in_scope(|| {});
+        info_span!("test", name = "second").in_scope(|| info!("done"));
+    });
+}
+
+#[test]
+fn github_groups() {
+    let output = Output::default();
+    run(HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_github_groups(true)
+        .with_writer(output.clone()));
+    assert_eq!(
+        output.contents(),
+        "::group::test name=\"first\"\n \
+         test name=\"first\"\n   \
+         setup \n    \
+         INFO ready\n  \
+         INFO done\n\
+         ::endgroup::\n\
+         ::group::test name=\"silent\"\n \
+         test name=\"silent\"\n\
+         ::endgroup::\n\
+         ::group::test name=\"second\"\n \
+         test name=\"second\"\n  \
+         INFO done\n\
+         ::endgroup::\n"
+    );
+}
+
+#[test]
+fn github_groups_skip_deferred_spans() {
+    let output = Output::default();
+    run(HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_deferred_spans(true)
+        .with_github_groups(true)
+        .with_writer(output.clone()));
+    let contents = output.contents();
+    assert!(!contents.contains("silent"), "{}", contents);
+    assert_eq!(contents.matches("::group::").count(), 2);
+    assert_eq!(contents.matches("::endgroup::").count(), 2);
+}
+
+#[test]
+fn teamcity_blocks() {
+    let output = Output::default();
+    run(HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_section_marker(TeamCity)
+        .with_writer(output.clone()));
+    let contents = output.contents();
+    let lines: Vec<_> = contents.lines().collect();
+    assert_eq!(
+        lines[0],
+        "##teamcity[blockOpened name='test name=\"first\"']"
+    );
+    assert_eq!(
+        lines.iter().filter(|l| l.starts_with("##teamcity")).count(),
+        6
+    );
+    assert!(lines.contains(&"##teamcity[blockClosed name='test name=\"second\"']"));
+}
+
+#[test]
+fn teamcity_escapes_names() {
+    let mut out = String::new();
+    TeamCity.open("it's [a|b]\n", &mut out).unwrap();
+    assert_eq!(out, "##teamcity[blockOpened name='it|'s |[a||b|]|n']\n");
+}
+
+#[test]
+fn buildkite_sections() {
+    let output = Output::default();
+    run(HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_section_marker(Buildkite::expanded())
+        .with_writer(output.clone()));
+    let contents = output.contents();
+    let headers: Vec<_> = contents.lines().filter(|l| l.starts_with("+++")).collect();
+    assert_eq!(
+        headers,
+        [
+            "+++ test name=\"first\"",
+            "+++ test name=\"silent\"",
+            "+++ test name=\"second\"",
+        ]
+    );
+}
+
+#[test]
+fn custom_marker() {
+    struct Brackets;
+
+    impl SectionMarker for Brackets {
+        fn open(&self, name: &str, w: &mut dyn fmt::Write) -> fmt::Result {
+            writeln!(w, "[{}", name)
+        }
+
+        fn close(&self, name: &str, w: &mut dyn fmt::Write) -> fmt::Result {
+            writeln!(w, "{}]", name)
+        }
+    }
+
+    let output = Output::default();
+    run(HierarchicalLayer::new(2)
+        .with_ansi(false)
+        // The custom marker takes precedence
+        .with_github_groups(true)
+        .with_section_marker(Brackets)
+        .with_writer(output.clone()));
+    let contents = output.contents();
+    assert!(!contents.contains("::group::"));
+    assert!(contents.starts_with("[test name=\"first\"\n"));
+    assert!(contents.ends_with("test name=\"second\"]\n"));
+}
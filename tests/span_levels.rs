@@ -0,0 +1,36 @@
+mod common;
+
+use tracing::{info, info_span, trace_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[test]
+fn shows_span_levels() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_verbose_exit(true)
+        .with_span_levels(true)
+        .with_writer(output.clone());
+
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request", id = 1).in_scope(|| {
+            trace_span!("pool").in_scope(|| info!("acquired"));
+        });
+    });
+
+    assert_eq!(
+        output.contents(),
+        "\
+┐INFO request id=1
+└─┐TRACE pool 
+  ├─ INFO acquired
+ ┌┘TRACE pool 
+┌┘INFO request id=1
+┘INFO request id=1
+"
+    );
+}
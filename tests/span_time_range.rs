@@ -0,0 +1,79 @@
+mod common;
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{time::FormatTime, HierarchicalLayer};
+
+use common::Output;
+
+/// Prints a clock that advances by one second whenever it is read.
+#[derive(Default)]
+struct Ticking(AtomicUsize);
+
+impl FormatTime for Ticking {
+    fn format_time(&self, w: &mut impl std::fmt::Write) -> std::fmt::Result {
+        write!(w, "10:00:{:02}", self.0.fetch_add(1, Ordering::Relaxed))
+    }
+    fn style_timestamp(
+        &self,
+        _ansi: bool,
+        _elapsed: Duration,
+        _w: &mut impl std::fmt::Write,
+    ) -> std::fmt::Result {
+        Ok(())
+    }
+}
+
+fn render(
+    layer: HierarchicalLayer<Output, impl FormatTime + Send + Sync + 'static>,
+    output: &Output,
+) -> String {
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request").in_scope(|| {
+            info_span!("query").in_scope(|| info!("done"));
+        });
+    });
+    output.contents()
+}
+
+#[test]
+fn prints_open_and_close_times() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_span_time_range(true)
+        .with_timer(Ticking::default())
+        .with_writer(output.clone());
+
+    assert_eq!(
+        render(layer, &output),
+        "\
+┐request 
+└─┐query 
+  ├─10:00:02  INFO done
+┌─┘10:00:01 → 10:00:03
+┘10:00:00 → 10:00:04
+"
+    );
+}
+
+#[test]
+fn nothing_without_timer() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_span_time_range(true)
+        .with_writer(output.clone());
+
+    assert_eq!(
+        render(layer, &output),
+        "┐request \n└─┐query \n  ├─ INFO done\n┌─┘\n┘\n"
+    );
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "tracing-error")]
+
+mod common;
+
+use std::{error::Error, fmt};
+
+use tracing::{error, info_span};
+use tracing_error::{ErrorLayer, InstrumentError};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[derive(Debug)]
+struct Refused;
+
+impl fmt::Display for Refused {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("connection refused")
+    }
+}
+
+impl Error for Refused {}
+
+fn render(indent_lines: bool) -> String {
+    let output = Output::default();
+    let subscriber = Registry::default().with(ErrorLayer::default()).with(
+        HierarchicalLayer::new(2)
+            .with_ansi(false)
+            .with_indent_lines(indent_lines)
+            .with_writer(output.clone()),
+    );
+    tracing::subscriber::with_default(subscriber, || {
+        let err = info_span!("server", port = 8080)
+            .in_scope(|| info_span!("conn", peer = "a").in_scope(|| Refused.in_current_span()));
+        info_span!("retry").in_scope(|| {
+            error!(error = &err as &(dyn Error + 'static), "giving up");
+        });
+    });
+    output.contents()
+}
+
+#[test]
+fn renders_captured_spans() {
+    assert_eq!(
+        render(false),
+        " server port=8080\n   conn peer=\"a\"\n retry \n  ERROR giving up, error=connection refused\n    in conn peer=\"a\"\n    in server port=8080\n"
+    );
+}
+
+#[test]
+fn renders_captured_spans_with_lines() {
+    assert_eq!(
+        render(true),
+        "┐server port=8080\n└─┐conn peer=\"a\"\n┐retry \n├─ ERROR giving up, error=connection refused\n  ├─ in conn peer=\"a\"\n  ├─ in server port=8080\n┘\n┌─┘\n┘\n"
+    );
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "sqlite")]
+
+use std::io;
+
+use tracing::{info, info_span, warn};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{sqlite::SqliteSink, HierarchicalLayer};
+
+#[test]
+fn inserts_spans_and_events() {
+    let sink = SqliteSink::open_in_memory().unwrap();
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(io::sink)
+        .with_sqlite_sink(sink.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("server", port = 8080).in_scope(|| {
+            info_span!("conn").in_scope(|| info!(peer = "a", "connected"));
+            let _open = info_span!("idle").entered();
+            warn!("dropped");
+        });
+        info!("outside");
+    });
+
+    sink.with_connection(|db| {
+        let spans: Vec<(String, Option<String>, bool)> = db
+            .prepare(
+                "SELECT s.name, p.name, s.duration_us IS NOT NULL
+                 FROM spans s LEFT JOIN spans p ON s.parent_id = p.id ORDER BY s.id",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            spans,
+            [
+                ("server".to_owned(), None, true),
+                ("conn".to_owned(), Some("server".to_owned()), true),
+                ("idle".to_owned(), Some("server".to_owned()), true),
+            ]
+        );
+
+        let events: Vec<(String, String, Option<String>)> = db
+            .prepare(
+                "SELECT e.level, e.message, s.name
+                 FROM events e LEFT JOIN spans s ON e.span_id = s.id ORDER BY e.id",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            [
+                ("INFO".into(), "connected".into(), Some("conn".into())),
+                ("WARN".into(), "dropped".into(), Some("idle".into())),
+                ("INFO".into(), "outside".into(), None),
+            ]
+        );
+
+        let fields: Vec<(String, String, String)> = db
+            .prepare(
+                "SELECT coalesce(s.name, e.message), f.name, f.value FROM fields f
+                 LEFT JOIN spans s ON f.span_id = s.id
+                 LEFT JOIN events e ON f.event_id = e.id ORDER BY f.rowid",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            fields,
+            [
+                ("server".into(), "port".into(), "8080".into()),
+                ("connected".into(), "peer".into(), "\"a\"".into()),
+            ]
+        );
+    })
+    .unwrap();
+}
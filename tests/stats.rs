@@ -0,0 +1,62 @@
+mod common;
+
+use std::io;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{stats::SpanStats, HierarchicalLayer};
+
+use common::Output;
+
+fn workload() {
+    info_span!("server").in_scope(|| {
+        for _ in 0..3 {
+            info_span!("conn").in_scope(|| info!("connected"));
+        }
+    });
+}
+
+#[test]
+fn collects_per_callsite() {
+    let stats = SpanStats::new();
+    let layer = HierarchicalLayer::new(2)
+        .with_writer(io::sink)
+        .with_span_stats(stats.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+
+    let server = stats.get("server").unwrap();
+    let conn = stats.get("conn").unwrap();
+    assert_eq!(server.count, 1);
+    assert_eq!(conn.count, 3);
+    assert!(conn.max <= conn.total);
+    assert!(conn.mean() <= conn.max);
+    assert!(server.total >= conn.total);
+    assert!(stats.get("client").is_none());
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.len(), 2);
+    assert_eq!(snapshot[0].name, "server");
+
+    stats.reset();
+    assert!(stats.snapshot().is_empty());
+}
+
+#[test]
+fn included_in_summary() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_summary_report(true)
+        .with_span_stats(SpanStats::new())
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+
+    let contents = output.contents();
+    let timings = &contents[contents.find("span timings:\n").unwrap()..];
+    assert!(timings.contains("    count      total       mean        max name\n"));
+    let conn = timings
+        .lines()
+        .find(|line| line.ends_with(" conn"))
+        .unwrap();
+    assert!(conn.trim_start().starts_with("3 "));
+}
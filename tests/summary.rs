@@ -0,0 +1,57 @@
+mod common;
+
+use tracing::{debug, info, info_span, warn};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+fn workload() {
+    info_span!("server").in_scope(|| {
+        info!("starting");
+        info_span!("conn").in_scope(|| {
+            debug!("connected");
+            warn!("slow peer");
+        });
+    });
+}
+
+#[test]
+fn summary_on_drop() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_summary_report(true)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+
+    let contents = output.contents();
+    let summary = &contents[contents.find("summary:").unwrap()..];
+    assert!(summary.starts_with("summary: 2 spans, 3 events\n"));
+    assert!(summary.contains("   WARN 1\n"));
+    assert!(summary.contains("   INFO 1\n"));
+    assert!(summary.contains("  DEBUG 1\n"));
+    assert!(!summary.contains("ERROR"));
+    // `server` encloses `conn`, so it is always the slowest span
+    let slowest = &summary[summary.find("slowest spans:\n").unwrap()..];
+    assert!(slowest.find(" server\n").unwrap() < slowest.find(" conn\n").unwrap());
+}
+
+#[test]
+fn explicit_report() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_summary_report(true)
+        .with_writer(output.clone());
+    layer.report();
+    assert_eq!(output.contents(), "summary: 0 spans, 0 events\n");
+}
+
+#[test]
+fn no_summary_by_default() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2).with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+    assert!(!output.contents().contains("summary:"));
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[test]
+fn marks_switches_between_branches() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_deferred_spans(true)
+        .with_span_retrace(true)
+        .with_switch_markers(true)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("server").in_scope(|| {
+            let a = info_span!("conn", id = 1);
+            let b = info_span!("conn", id = 2);
+            a.in_scope(|| info!("first"));
+            b.in_scope(|| info!("second"));
+            // Returning to the parent is no switch
+            info!("back");
+        });
+    });
+
+    assert_eq!(
+        output.contents(),
+        "\
+┐server 
+└─┐conn id=1
+  ├─ INFO first
+├─ ⋯ (switching from server>conn#2)
+└─┐conn id=2
+  ├─ INFO second
+├─ INFO back
+┌─┘
+┌─┘
+┘
+"
+    );
+}
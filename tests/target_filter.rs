@@ -0,0 +1,45 @@
+mod common;
+
+use tracing::{debug, debug_span, info, info_span, level_filters::LevelFilter, trace, warn, Level};
+use tracing_subscriber::{layer::SubscriberExt, Layer, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+#[test]
+fn filters_by_target_prefix() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_max_level(Level::INFO)
+        .with_target_filter([("hyper", Level::WARN), ("my_crate", Level::TRACE)])
+        .with_writer(output.clone());
+    assert_eq!(
+        Layer::<Registry>::max_level_hint(&layer),
+        Some(LevelFilter::TRACE)
+    );
+
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!(target: "my_crate", "server").in_scope(|| {
+            debug_span!(target: "hyper::proto", "conn").in_scope(|| {
+                info!(target: "hyper::proto", "ignored");
+                warn!(target: "hyper::proto", "slow peer");
+                trace!(target: "my_crate::handler", "handling");
+            });
+            info!(target: "hyperlocal", "kept");
+            debug!(target: "other", "ignored");
+        });
+    });
+
+    assert_eq!(
+        output.contents(),
+        "\
+┐server 
+├─ WARN slow peer
+├─ TRACE handling
+├─ INFO kept
+┘
+"
+    );
+}
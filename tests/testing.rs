@@ -0,0 +1,57 @@
+use tracing::{info, info_span};
+use tracing_tree::{assert_tree, testing::capture};
+
+fn workload() {
+    info_span!("server").in_scope(|| {
+        info!("starting");
+        info_span!("conn", peer = "a").in_scope(|| {
+            info!("connected");
+            info!("disconnected");
+        });
+        info_span!("conn", peer = "b").in_scope(|| info!("connected"));
+    });
+    info_span!("shutdown").in_scope(|| {});
+}
+
+#[test]
+fn matches_structure() {
+    let trees = capture(workload);
+    assert_tree!(trees, {
+        "server" => {
+            "conn" => ["connected", "disconnected"],
+            "conn" => ["connected"],
+        },
+        "shutdown" => [],
+    });
+    assert_tree!(trees, { "server" => ["starting"], "shutdown" => {} });
+}
+
+#[test]
+#[should_panic(expected = "in `server`: expected spans [\"conn\"], found [\"conn\", \"conn\"]")]
+fn reports_missing_spans() {
+    let trees = capture(workload);
+    assert_tree!(trees, { "server" => { "conn" => [] }, "shutdown" => [] });
+}
+
+#[test]
+#[should_panic(
+    expected = "in `server/conn`: expected events [\"connected\"], found [\"connected\", \"disconnected\"]"
+)]
+fn reports_unexpected_events() {
+    let trees = capture(workload);
+    assert_tree!(trees, {
+        "server" => { "conn" => ["connected"], "conn" => ["connected"] },
+        "shutdown" => [],
+    });
+}
+
+#[test]
+fn includes_captured_trees() {
+    let trees = capture(workload);
+    let error =
+        tracing_tree::testing::check(&trees, &tracing_tree::testing::Expected::Spans(Vec::new()))
+            .unwrap_err();
+    assert!(error.starts_with("at the root: expected spans [], found [\"server\", \"shutdown\"]"));
+    assert!(error.contains("\n\ncaptured:\n"), "{}", error);
+    assert!(error.contains("disconnected"), "{}", error);
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use tracing::{info, info_span, warn};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::{time::Uptime, HierarchicalLayer, Theme};
+
+use common::Output;
+
+fn render(theme: Theme) -> String {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(true)
+        .with_targets(true)
+        .with_timer(Uptime::default())
+        .with_theme(theme)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("request").in_scope(|| {
+            info!("handling");
+            warn!("slow");
+        });
+    });
+    output.contents()
+}
+
+#[test]
+fn light_theme_avoids_dimmed_text() {
+    let dark = render(Theme::default());
+    assert!(dark.contains("\x1b[2m"), "{:?}", dark);
+    assert!(dark.contains("32mrequest"), "{:?}", dark);
+
+    let light = render(Theme::light());
+    assert!(!light.contains("\x1b[2m"), "{:?}", light);
+    assert!(light.contains("38;5;22mrequest"), "{:?}", light);
+    assert!(light.contains("38;5;130m WARN"), "{:?}", light);
+}
+
+#[test]
+fn theme_option() {
+    let layer = HierarchicalLayer::default().with_options("theme=light");
+    assert_eq!(layer.config().theme, Theme::light());
+}
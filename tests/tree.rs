@@ -0,0 +1,113 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use tracing::{debug, info, info_span, warn, Level};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::tree::{diff, Builder, Change, Node};
+
+fn workload() {
+    info_span!("server", port = 8080).in_scope(|| {
+        info!("starting");
+        let conn = info_span!("conn", peer = "a", bytes = tracing::field::Empty);
+        conn.in_scope(|| {
+            debug!(bytes = 42, "response sent");
+            warn!("slow peer");
+        });
+        conn.record("bytes", 42);
+    });
+    info!("outside of any span");
+}
+
+#[test]
+fn builds_trees() {
+    let builder = Builder::new();
+    tracing::subscriber::with_default(Registry::default().with(builder.clone()), workload);
+
+    let trees = builder.take();
+    assert_eq!(trees.len(), 1);
+    let server = &trees[0];
+    assert_eq!(server.name, "server");
+    assert_eq!(server.level, Level::INFO);
+    assert_eq!(server.fields, [("port", "8080".to_owned())]);
+    assert!(matches!(
+        &server.children[..],
+        [Node::Event(_), Node::Span(_)]
+    ));
+
+    let conn = server.find("conn").unwrap();
+    assert_eq!(conn.field("peer"), Some("a"));
+    assert_eq!(conn.field("bytes"), Some("42"));
+    assert!(conn.duration <= server.duration);
+    let events: Vec<_> = conn.events().collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].message, "response sent");
+    assert_eq!(events[0].level, Level::DEBUG);
+    assert_eq!(events[0].field("bytes"), Some("42"));
+    assert_eq!(events[1].message, "slow peer");
+    assert!(events[0].elapsed <= events[1].elapsed);
+
+    assert!(builder.take().is_empty());
+}
+
+#[test]
+fn per_root_callback() {
+    let names = Arc::new(Mutex::new(Vec::new()));
+    let builder = Builder::new().on_root({
+        let names = names.clone();
+        move |tree| names.lock().unwrap().push(tree.name)
+    });
+    tracing::subscriber::with_default(Registry::default().with(builder.clone()), || {
+        workload();
+        info_span!("client").in_scope(|| {});
+    });
+
+    assert_eq!(*names.lock().unwrap(), ["server", "client"]);
+    assert!(builder.take().is_empty());
+}
+
+#[test]
+fn diff_trees() {
+    let builder = Builder::new();
+    tracing::subscriber::with_default(Registry::default().with(builder.clone()), || {
+        info_span!("server").in_scope(|| {
+            info_span!("query").in_scope(|| {});
+            info_span!("retry").in_scope(|| {});
+        });
+        info_span!("server").in_scope(|| {
+            info_span!("query").in_scope(|| thread::sleep(Duration::from_millis(20)));
+            info_span!("cache").in_scope(|| {});
+        });
+    });
+    let trees = builder.take();
+
+    let same = diff(&trees[0], &trees[0]);
+    assert!(!same.has_changes());
+
+    let diff = diff(&trees[0], &trees[1]);
+    assert!(diff.has_changes());
+    let entries: Vec<_> = diff
+        .entries()
+        .iter()
+        .map(|entry| (entry.change, entry.depth, entry.span.name))
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            (Change::Regressed, 0, "server"),
+            (Change::Regressed, 1, "query"),
+            (Change::Removed, 1, "retry"),
+            (Change::Added, 1, "cache"),
+        ]
+    );
+
+    let rendered = diff.to_string();
+    let lines: Vec<_> = rendered.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("! server "), "{}", rendered);
+    assert!(lines[1].starts_with("!   query ") && lines[1].ends_with("%)"));
+    assert!(lines[2].starts_with("-   retry "));
+    assert!(lines[3].starts_with("+   cache "));
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_tree::HierarchicalLayer;
+
+use common::Output;
+
+fn recurse(n: usize) {
+    info_span!("step", n).in_scope(|| {
+        info!("stepping");
+        if n > 0 {
+            recurse(n - 1);
+        }
+    });
+}
+
+#[test]
+fn printed_on_root_close() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_tree_stats(true)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("server").in_scope(|| {
+            info!("started");
+            recurse(3);
+            info_span!("conn").in_scope(|| {});
+        });
+        info_span!("idle").in_scope(|| {});
+    });
+
+    let contents = output.contents();
+    let stats: Vec<_> = contents
+        .lines()
+        .filter(|line| line.contains("tree: "))
+        .collect();
+    assert_eq!(
+        stats,
+        [
+            " tree: 5 spans, depth 4, 5 events",
+            " tree: 0 spans, depth 0, 0 events",
+        ],
+        "{}",
+        contents
+    );
+}
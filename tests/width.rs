@@ -0,0 +1,18 @@
+use tracing_tree::HierarchicalLayer;
+
+// A single test, as the detected width is cached for the whole process
+#[test]
+fn width() {
+    std::env::set_var("COLUMNS", "93");
+    assert_eq!(HierarchicalLayer::default().width(), Some(93));
+    assert_eq!(
+        HierarchicalLayer::default().with_width(40).width(),
+        Some(40)
+    );
+    assert_eq!(
+        HierarchicalLayer::default()
+            .with_options("width=72")
+            .width(),
+        Some(72)
+    );
+}
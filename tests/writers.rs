@@ -0,0 +1,379 @@
+mod common;
+
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use tracing::{error, info, info_span, warn, Dispatch, Level};
+use tracing_subscriber::{
+    fmt::{writer::MakeWriterExt, MakeWriter},
+    layer::SubscriberExt,
+    Registry,
+};
+use tracing_tree::{
+    writer::{Background, LevelWriters, StripAnsi, TestCapture},
+    HierarchicalLayer, SeverityPrefix, WriteErrorPolicy,
+};
+
+use common::Output;
+
+fn workload() {
+    info_span!("server").in_scope(|| {
+        info!("starting");
+        info_span!("conn").in_scope(|| {
+            warn!("slow peer");
+            error!("connection reset");
+        });
+    });
+}
+
+#[test]
+fn level_writers() {
+    let severe = Output::default();
+    let rest = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_level_writers(severe.clone(), rest.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+
+    assert_eq!(
+        severe.contents(),
+        "  ├─ WARN slow peer\n  ├─ ERROR connection reset\n"
+    );
+    let rest = rest.contents();
+    assert!(rest.contains("├─ INFO starting\n"));
+    assert!(!rest.contains("slow peer"));
+    assert!(!rest.contains("connection reset"));
+}
+
+#[test]
+fn level_writers_threshold() {
+    let severe = Output::default();
+    let rest = Output::default();
+    let writers = LevelWriters::new(severe.clone(), rest.clone()).with_threshold(Level::ERROR);
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(writers);
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+
+    assert!(severe.contents().contains("connection reset"));
+    assert!(!severe.contents().contains("slow peer"));
+    assert!(rest.contents().contains("slow peer"));
+}
+
+#[test]
+fn writers_see_metadata() {
+    let output = Output::default();
+    let writer = output.clone().with_filter(|meta| meta.target() != "noisy");
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_verbose_exit(true)
+        .with_writer(writer);
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("server").in_scope(|| {
+            info_span!(target: "noisy", "poll").in_scope(|| info!(target: "noisy", "polled"));
+            info!("listening");
+        });
+    });
+
+    let contents = output.contents();
+    assert!(contents.contains("server"));
+    assert!(contents.contains("listening"));
+    assert!(!contents.contains("poll"), "{}", contents);
+}
+
+/// Remembers every single write separately.
+#[derive(Clone, Default)]
+struct Chunks(Arc<Mutex<Vec<String>>>);
+
+impl io::Write for Chunks {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let chunk = String::from_utf8(buf.to_vec()).unwrap();
+        self.0.lock().unwrap().push(chunk);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Chunks {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[test]
+fn one_write_per_record() {
+    let chunks = Chunks::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_span_retrace(true)
+        .with_verbose_entry(true)
+        .with_verbose_exit(true)
+        .with_writer(chunks.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        let a = info_span!("a");
+        let b = info_span!("b");
+        a.in_scope(|| info!("in a"));
+        b.in_scope(|| info!("in b"));
+        a.in_scope(|| info!("in a again"));
+    });
+
+    let chunks = chunks.0.lock().unwrap();
+    for chunk in chunks.iter() {
+        assert!(chunk.ends_with('\n'), "{:?}", chunk);
+    }
+    // The retraced span is written along with the event that caused the retrace
+    let again = chunks.iter().find(|c| c.contains("in a again")).unwrap();
+    assert!(again.lines().count() > 1, "{:?}", again);
+    assert!(again.contains(" a"), "{:?}", again);
+}
+
+#[test]
+fn one_write_call_per_record() {
+    let chunks = Chunks::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(true)
+        .with_verbose_exit(true)
+        .with_writer(StripAnsi::new(chunks.clone()));
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("a", field = %"multi\nline").in_scope(|| info!("one\ntwo\nthree"));
+    });
+
+    // The open of the span, the event and the close, no matter how many lines each has
+    let chunks = chunks.0.lock().unwrap();
+    assert_eq!(chunks.len(), 3, "{:?}", chunks);
+}
+
+#[test]
+fn strip_ansi_per_writer() {
+    let colored = Output::default();
+    let plain = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(true)
+        .with_writer(colored.clone().and(StripAnsi::new(plain.clone())));
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+
+    assert!(colored.contents().contains('\x1b'));
+    assert!(plain.contents().contains(" INFO starting\n"));
+    assert!(!plain.contents().contains('\x1b'));
+    assert_eq!(
+        plain.contents().lines().count(),
+        colored.contents().lines().count()
+    );
+}
+
+/// Counts how often it is flushed.
+#[derive(Clone, Default)]
+struct Flushes(Arc<AtomicUsize>);
+
+impl io::Write for Flushes {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Flushes {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[test]
+fn flush_explicitly_and_on_drop() {
+    let flushes = Flushes::default();
+    let layer = HierarchicalLayer::new(2).with_writer(flushes.clone());
+    layer.flush().unwrap();
+    assert_eq!(flushes.0.load(Ordering::Relaxed), 1);
+
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+    assert_eq!(flushes.0.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn systemd_severity_prefix() {
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_indent_lines(true)
+        .with_severity_prefix(SeverityPrefix::Systemd)
+        .with_writer(output.clone());
+    tracing::subscriber::with_default(Registry::default().with(layer), workload);
+
+    assert_eq!(
+        output.contents(),
+        "<5>┐server \n\
+         <5>├─ INFO starting\n\
+         <5>└─┐conn \n\
+         <4>  ├─ WARN slow peer\n\
+         <3>  ├─ ERROR connection reset\n\
+         <5>┌─┘\n\
+         <5>┘\n"
+    );
+}
+
+#[test]
+fn test_capture_per_thread() {
+    let capture = TestCapture::new().with_print(false);
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(capture.clone());
+    let subscriber = Arc::new(Registry::default().with(layer));
+
+    let threads: Vec<_> = ["first", "second"]
+        .iter()
+        .map(|&name| {
+            let capture = capture.clone();
+            let subscriber = subscriber.clone();
+            thread::spawn(move || {
+                tracing::subscriber::with_default(subscriber, || {
+                    info_span!("test", name).in_scope(|| info!("hello"));
+                });
+                capture.contents()
+            })
+        })
+        .collect();
+    let contents: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+
+    assert_eq!(contents[0], " test name=\"first\"\n  INFO hello\n");
+    assert_eq!(contents[1], " test name=\"second\"\n  INFO hello\n");
+    // Nothing was written on this thread
+    assert_eq!(capture.contents(), "");
+
+    tracing::subscriber::with_default(subscriber, || info!("here"));
+    capture.assert_contains("INFO here");
+    capture.clear();
+    assert_eq!(capture.contents(), "");
+}
+
+/// A closed pipe
+#[derive(Clone, Copy)]
+struct Broken;
+
+impl io::Write for Broken {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Broken {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        *self
+    }
+}
+
+#[test]
+fn write_errors() {
+    let log = || {
+        info_span!("span").in_scope(|| info!("lost"));
+    };
+
+    let layer = HierarchicalLayer::new(2).with_writer(Broken);
+    tracing::subscriber::with_default(Registry::default().with(layer), log);
+
+    let layer = HierarchicalLayer::new(2)
+        .with_write_error_policy(WriteErrorPolicy::CountAndIgnore)
+        .with_writer(Broken);
+    let dispatch = Dispatch::new(Registry::default().with(layer));
+    tracing::dispatcher::with_default(&dispatch, log);
+    let layer = dispatch
+        .downcast_ref::<HierarchicalLayer<Broken>>()
+        .unwrap();
+    // The span and the event
+    assert_eq!(layer.write_errors(), 2);
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let sink = errors.clone();
+    let layer = HierarchicalLayer::new(2)
+        .with_write_error_policy(WriteErrorPolicy::callback(move |err| {
+            sink.lock().unwrap().push(err.kind());
+        }))
+        .with_writer(Broken);
+    tracing::subscriber::with_default(Registry::default().with(layer), log);
+    assert_eq!(*errors.lock().unwrap(), [io::ErrorKind::BrokenPipe; 2]);
+}
+
+#[test]
+fn threads_render_independently() {
+    let capture = TestCapture::new().with_print(false);
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(capture.clone());
+    let subscriber = Arc::new(Registry::default().with(layer));
+
+    let threads: Vec<_> = (0..8)
+        .map(|i| {
+            let capture = capture.clone();
+            let subscriber = subscriber.clone();
+            thread::spawn(move || {
+                tracing::subscriber::with_default(subscriber, || {
+                    for _ in 0..100 {
+                        info_span!("outer", i).in_scope(|| {
+                            info_span!("inner").in_scope(|| info!("event"));
+                        });
+                    }
+                });
+                (i, capture.contents())
+            })
+        })
+        .collect();
+    for thread in threads {
+        let (i, contents) = thread.join().unwrap();
+        let expected = format!(" outer i={}\n   inner \n    INFO event\n", i);
+        assert_eq!(contents, expected.repeat(100));
+    }
+}
+
+#[test]
+fn background_writer() {
+    let direct = Output::default();
+    tracing::subscriber::with_default(
+        Registry::default().with(
+            HierarchicalLayer::new(2)
+                .with_ansi(false)
+                .with_writer(direct.clone()),
+        ),
+        workload,
+    );
+
+    let output = Output::default();
+    let layer = HierarchicalLayer::new(2)
+        .with_ansi(false)
+        .with_writer(Background::with_capacity(output.clone(), 1));
+    let dispatch = Dispatch::new(Registry::default().with(layer));
+    tracing::dispatcher::with_default(&dispatch, workload);
+    dispatch
+        .downcast_ref::<HierarchicalLayer<Background>>()
+        .unwrap()
+        .flush()
+        .unwrap();
+    assert_eq!(output.contents(), direct.contents());
+
+    // Dropping the writer waits for everything queued
+    tracing::dispatcher::with_default(&dispatch, workload);
+    drop(dispatch);
+    assert_eq!(output.contents(), direct.contents().repeat(2));
+}